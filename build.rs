@@ -0,0 +1,18 @@
+use std::process::Command;
+
+fn main() {
+    // Embed the current commit for /version, if this is built from a git checkout
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    if let Some(commit) = commit {
+        println!("cargo:rustc-env=GIT_COMMIT={}", commit.trim());
+    }
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    // sqlx::migrate! embeds the migrations, so new ones need a rebuild
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,7 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS tracked_messages (
+    channel_id TEXT NOT NULL,
+    message_id TEXT NOT NULL,
+    timestamp TEXT NOT NULL,
+    PRIMARY KEY (channel_id, message_id)
+);
\ No newline at end of file
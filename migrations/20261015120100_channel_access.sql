@@ -0,0 +1,6 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS channel_access (
+    channel_id TEXT NOT NULL,
+    allowed BOOLEAN NOT NULL,
+    PRIMARY KEY (channel_id)
+);
\ No newline at end of file
@@ -0,0 +1,6 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS blocked_keywords (
+    channel_id TEXT NOT NULL,
+    keyword TEXT NOT NULL,
+    PRIMARY KEY (channel_id, keyword)
+);
\ No newline at end of file
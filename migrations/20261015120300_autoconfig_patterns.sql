@@ -0,0 +1,7 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS autoconfig_patterns (
+    guild_id TEXT NOT NULL,
+    pattern TEXT NOT NULL,
+    channel_limit INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, pattern)
+);
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE channel_limits ADD COLUMN pins_count_toward_limit BOOLEAN NOT NULL DEFAULT 0;
\ No newline at end of file
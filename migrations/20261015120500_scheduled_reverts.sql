@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS scheduled_reverts (
+    channel_id TEXT NOT NULL,
+    original_limit INTEGER NOT NULL,
+    raised_limit INTEGER NOT NULL,
+    revert_at INTEGER NOT NULL,
+    PRIMARY KEY (channel_id)
+);
\ No newline at end of file
@@ -0,0 +1,8 @@
+-- Add migration script here
+ALTER TABLE channel_limits ADD COLUMN keep_oldest INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS protected_messages (
+    channel_id TEXT NOT NULL,
+    message_id TEXT NOT NULL,
+    PRIMARY KEY (channel_id, message_id)
+);
\ No newline at end of file
@@ -0,0 +1,6 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS guild_settings (
+    guild_id TEXT NOT NULL,
+    unmanage_after_idle INTEGER,
+    PRIMARY KEY (guild_id)
+);
\ No newline at end of file
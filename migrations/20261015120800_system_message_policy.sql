@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE channel_limits ADD COLUMN system_message_policy TEXT NOT NULL DEFAULT 'normal';
\ No newline at end of file
@@ -0,0 +1,6 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS channel_stats (
+    channel_id TEXT NOT NULL,
+    deleted_count INTEGER NOT NULL,
+    PRIMARY KEY (channel_id)
+);
\ No newline at end of file
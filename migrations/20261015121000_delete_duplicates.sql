@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE channel_limits ADD COLUMN delete_duplicates BOOLEAN NOT NULL DEFAULT 0;
\ No newline at end of file
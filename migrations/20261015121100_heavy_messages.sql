@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE channel_limits ADD COLUMN heavy_limit INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE channel_limits ADD COLUMN heavy_min_size INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE channel_limits ADD COLUMN heavy_videos BOOLEAN NOT NULL DEFAULT 0;
\ No newline at end of file
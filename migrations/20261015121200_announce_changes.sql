@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE guild_settings ADD COLUMN announce_changes BOOLEAN NOT NULL DEFAULT 0;
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE channel_limits ADD COLUMN snoozed_until INTEGER;
@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE channel_limits ADD COLUMN limit_kind TEXT NOT NULL DEFAULT 'count';
+ALTER TABLE channel_limits ADD COLUMN byte_budget INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE tracked_messages ADD COLUMN size INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE guild_settings ADD COLUMN warning_threshold INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE guild_settings ADD COLUMN warning_channel TEXT;
@@ -0,0 +1,10 @@
+-- Add migration script here
+ALTER TABLE tracked_messages ADD COLUMN author_id TEXT;
+CREATE TABLE IF NOT EXISTS deletion_log (
+    channel_id TEXT NOT NULL,
+    message_id TEXT NOT NULL,
+    author_id TEXT,
+    timestamp TEXT NOT NULL,
+    deleted_at INTEGER NOT NULL,
+    PRIMARY KEY (channel_id, message_id)
+);
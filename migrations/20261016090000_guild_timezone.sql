@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE guild_settings ADD COLUMN timezone TEXT;
@@ -0,0 +1,6 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS protected_roles (
+    channel_id TEXT NOT NULL,
+    role_id TEXT NOT NULL,
+    PRIMARY KEY (channel_id, role_id)
+);
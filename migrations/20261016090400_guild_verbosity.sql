@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE guild_settings ADD COLUMN verbosity TEXT NOT NULL DEFAULT 'verbose';
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE guild_settings ADD COLUMN confirm_important_channels BOOLEAN NOT NULL DEFAULT 1;
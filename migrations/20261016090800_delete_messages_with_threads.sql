@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE channel_limits ADD COLUMN delete_messages_with_threads BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,6 @@
+-- Add migration script here
+-- Left NULL for existing rows, which are filled in as their channels are seen again
+ALTER TABLE channel_limits ADD COLUMN guild_id TEXT;
+ALTER TABLE channel_limit_edits ADD COLUMN guild_id TEXT;
+CREATE INDEX IF NOT EXISTS channel_limits_guild ON channel_limits (guild_id);
+CREATE INDEX IF NOT EXISTS channel_limit_edits_guild ON channel_limit_edits (guild_id);
@@ -0,0 +1,128 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::Utc;
+use log::{debug, warn};
+use serde_json::json;
+use serenity::model::prelude::{Attachment, AttachmentId, ChannelId, MessageId, UserId};
+
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+const INDEX_FILE: &str = "attachments.jsonl";
+
+/// What is needed to preserve an attachment, captured when its message is first seen
+/// (CDN URLs stop working once the message is deleted)
+#[derive(Clone)]
+pub struct AttachmentInfo {
+    id: AttachmentId,
+    filename: String,
+    url: String,
+    size: u64,
+    content_type: Option<String>,
+}
+
+impl From<&Attachment> for AttachmentInfo {
+    fn from(attachment: &Attachment) -> Self {
+        AttachmentInfo { id: attachment.id, filename: attachment.filename.clone(), url: attachment.url.clone(), size: attachment.size, content_type: attachment.content_type.clone() }
+    }
+}
+
+/// Records the attachments of messages about to be deleted in `<dir>/attachments.jsonl`,
+/// optionally saving their files under `<dir>/<channel id>/` as well:
+///
+/// ```json
+/// {"message_id": "...", "channel_id": "...", "author_id": "...", "archived_at": "...", "attachments": [
+///     {"id": "...", "filename": "cat.png", "url": "https://cdn.discordapp.com/...", "size": 1234, "content_type": "image/png", "saved_as": "123/456_789_cat.png"}
+/// ]}
+/// ```
+#[derive(Clone)]
+pub struct AttachmentArchive {
+    dir: PathBuf,
+    download: bool,
+    // Larger files are only recorded, never downloaded
+    max_download_size: u64,
+    client: reqwest::Client,
+}
+
+impl AttachmentArchive {
+    pub fn new(dir: PathBuf, download: bool, max_download_size: u64) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(DOWNLOAD_TIMEOUT)
+            .build()
+            .expect("Couldn't build archive client");
+        AttachmentArchive { dir, download, max_download_size, client }
+    }
+
+    /// Records a message's attachments, downloading each file when enabled and small enough.
+    /// A failed download is noted in the record rather than failing the whole message,
+    /// and callers are expected to delete the message whatever the outcome.
+    pub async fn preserve_attachments(&self, channel: ChannelId, message: MessageId, author: Option<UserId>, attachments: &[AttachmentInfo]) -> Result<(), String> {
+        if attachments.is_empty() {
+            return Ok(());
+        }
+
+        let mut records = Vec::with_capacity(attachments.len());
+        for attachment in attachments {
+            let saved_as = if !self.download {
+                None
+            } else if attachment.size > self.max_download_size {
+                debug!("Not downloading attachment {} of message {} ({} bytes is over the cap)", attachment.id, message, attachment.size);
+                None
+            } else {
+                match self.download_attachment(channel, message, attachment).await {
+                    Ok(saved_as) => Some(saved_as),
+                    Err(why) => {
+                        warn!("Cannot download attachment {} of message {}: {}", attachment.id, message, why);
+                        None
+                    }
+                }
+            };
+            records.push(json!({
+                "id": attachment.id.to_string(),
+                "filename": attachment.filename,
+                "url": attachment.url,
+                "size": attachment.size,
+                "content_type": attachment.content_type,
+                "saved_as": saved_as,
+            }));
+        }
+
+        let line = json!({
+            "message_id": message.to_string(),
+            "channel_id": channel.to_string(),
+            "author_id": author.map(|author| author.to_string()),
+            "archived_at": Utc::now().to_rfc3339(),
+            "attachments": records,
+        }).to_string();
+        fs::create_dir_all(&self.dir).map_err(|error| format!("Cannot create {}: {}", self.dir.display(), error))?;
+        let index = self.dir.join(INDEX_FILE);
+        let mut file = OpenOptions::new().create(true).append(true).open(&index)
+            .map_err(|error| format!("Cannot open {}: {}", index.display(), error))?;
+        writeln!(file, "{}", line).map_err(|error| format!("Cannot write to {}: {}", index.display(), error))
+    }
+
+    /// Saves the file, returning its path relative to the archive directory
+    async fn download_attachment(&self, channel: ChannelId, message: MessageId, attachment: &AttachmentInfo) -> Result<String, String> {
+        let response = self.client.get(&attachment.url).send().await
+            .and_then(|response| response.error_for_status())
+            .map_err(|error| error.to_string())?;
+        // The announced size could be wrong, so the actual one is checked too
+        if response.content_length().is_some_and(|length| length > self.max_download_size) {
+            return Err(format!("the file is larger than {} bytes", self.max_download_size));
+        }
+        let bytes = response.bytes().await.map_err(|error| error.to_string())?;
+
+        // Filenames come from users, so only harmless characters are kept
+        let filename: String = attachment.filename.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        let relative = format!("{}/{}_{}_{}", channel, message, attachment.id, filename);
+        let path = self.dir.join(&relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|error| format!("Cannot create {}: {}", parent.display(), error))?;
+        }
+        fs::write(&path, &bytes).map_err(|error| format!("Cannot write {}: {}", path.display(), error))?;
+        Ok(relative)
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde_json::json;
+use serenity::http::request::RequestBuilder;
+use serenity::http::routing::RouteInfo;
+use serenity::model::prelude::{Channel, ChannelId, GuildId, MessageId};
+use serenity::prelude::*;
+
+const REASON_HEADER: &str = "X-Audit-Log-Reason";
+/// Discord drops anything longer
+pub const REASON_MAX_LENGTH: usize = 512;
+
+/// The reason shown in a server's audit log for the bot's deletions: the guild's own, or else the default.
+/// Shared between the manager, which changes it, and the delete workers, which read it.
+#[derive(Clone, Default)]
+pub struct DeletionReasons {
+    default: Option<String>,
+    guilds: Arc<RwLock<HashMap<GuildId, String>>>,
+}
+
+impl DeletionReasons {
+    pub fn new(default: Option<String>) -> Self {
+        DeletionReasons { default, guilds: Arc::new(RwLock::new(HashMap::new())) }
+    }
+
+    /// Sets the guild's reason, or goes back to the default with `None`
+    pub fn set(&self, guild_id: GuildId, reason: Option<String>) {
+        let mut guilds = self.guilds.write().expect("Deletion reasons lock is poisoned");
+        match reason {
+            Some(reason) => guilds.insert(guild_id, reason),
+            None => guilds.remove(&guild_id),
+        };
+    }
+
+    pub fn default_reason(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+
+    /// The reason for deleting in a channel, which only resolves the guild from the cache (falling back to the default)
+    pub fn for_channel(&self, ctx: &Context, channel: ChannelId) -> Option<String> {
+        let guild_reason = match channel.to_channel_cached(&ctx.cache) {
+            Some(Channel::Guild(guild_channel)) => self.guilds.read().expect("Deletion reasons lock is poisoned").get(&guild_channel.guild_id).cloned(),
+            _ => None,
+        };
+        guild_reason.or_else(|| self.default.clone())
+    }
+}
+
+/// Headers must be ASCII, so Discord expects the reason to be percent-encoded
+fn reason_header(reason: &str) -> HeaderMap {
+    let encoded: String = reason.bytes()
+        .map(|byte| if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) { (byte as char).to_string() } else { format!("%{:02X}", byte) })
+        .collect();
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&encoded) {
+        headers.insert(REASON_HEADER, value);
+    }
+    headers
+}
+
+/// Like `ChannelId::delete_message`, which can't give a reason
+pub async fn delete_message(ctx: &Context, channel: ChannelId, message: MessageId, reason: Option<&str>) -> serenity::Result<()> {
+    let mut request = RequestBuilder::new(RouteInfo::DeleteMessage { channel_id: channel.0, message_id: message.0 });
+    request.headers(reason.map(reason_header));
+    ctx.http.request(request.build()).await.map(|_| ())
+}
+
+/// Like `ChannelId::delete_messages`, which can't give a reason. Discord takes 2 to 100 messages at once.
+pub async fn delete_messages(ctx: &Context, channel: ChannelId, messages: &[MessageId], reason: Option<&str>) -> serenity::Result<()> {
+    let body = json!({ "messages": messages.iter().map(|message| message.to_string()).collect::<Vec<_>>() }).to_string();
+    let mut request = RequestBuilder::new(RouteInfo::DeleteMessages { channel_id: channel.0 });
+    request.body(Some(body.as_bytes())).headers(reason.map(reason_header));
+    ctx.http.request(request.build()).await.map(|_| ())
+}
@@ -0,0 +1,85 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::Utc;
+use log::{error, info, warn};
+use sqlx::{Pool, Sqlite};
+
+const BACKUP_PREFIX: &str = "database-";
+const BACKUP_EXTENSION: &str = ".sqlite";
+
+/// Copies the database to `<dir>/database-<timestamp>.sqlite`, keeping the `keep` most recent copies.
+/// Copies are made with `VACUUM INTO`, which reads a consistent snapshot without blocking writers
+/// (the database is in WAL mode), so a backup never catches a half-written change.
+#[derive(Clone)]
+pub struct DatabaseBackups {
+    dir: PathBuf,
+    keep: usize,
+    // A slow backup isn't started over by the next tick
+    running: Arc<AtomicBool>,
+}
+
+impl DatabaseBackups {
+    pub fn new(dir: PathBuf, keep: usize) -> Self {
+        DatabaseBackups { dir, keep: keep.max(1), running: Arc::new(AtomicBool::new(false)) }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Backs the database up in the background, so the caller (the command loop) never waits on it
+    pub fn start(&self, db: Pool<Sqlite>) {
+        if self.running.swap(true, Ordering::SeqCst) {
+            warn!("The previous database backup is still running, skipping this one");
+            return;
+        }
+        let backups = self.clone();
+        tokio::spawn(async move {
+            match backups.backup(&db).await {
+                Ok(path) => info!("Backed the database up to {}", path.display()),
+                Err(why) => error!("Database backup failed: {}", why),
+            }
+            backups.prune();
+            backups.running.store(false, Ordering::SeqCst);
+        });
+    }
+
+    async fn backup(&self, db: &Pool<Sqlite>) -> Result<PathBuf, String> {
+        fs::create_dir_all(&self.dir).map_err(|error| format!("Cannot create {}: {}", self.dir.display(), error))?;
+        let path = self.dir.join(format!("{}{}{}", BACKUP_PREFIX, Utc::now().format("%Y%m%d-%H%M%S"), BACKUP_EXTENSION));
+        if path.exists() {
+            return Err(format!("{} already exists", path.display()));
+        }
+        sqlx::query("VACUUM INTO ?")
+            .bind(path.to_string_lossy().to_string())
+            .execute(db).await
+            .map_err(|error| format!("Cannot write {}: {}", path.display(), error))?;
+        Ok(path)
+    }
+
+    /// Removes the oldest backups past `keep`. Timestamps sort like their names, and other files are left alone.
+    fn prune(&self) {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(error) => {
+                warn!("Cannot list backups in {}: {}", self.dir.display(), error);
+                return;
+            }
+        };
+        let mut backups: Vec<PathBuf> = entries.filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_str().is_some_and(|name| name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_EXTENSION)))
+            .map(|entry| entry.path())
+            .collect();
+        backups.sort();
+        let excess = backups.len().saturating_sub(self.keep);
+        for old_backup in backups.into_iter().take(excess) {
+            match fs::remove_file(&old_backup) {
+                Ok(()) => info!("Removed old database backup {}", old_backup.display()),
+                Err(error) => warn!("Cannot remove old database backup {}: {}", old_backup.display(), error),
+            }
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("adjust")
+        .description("Raise or lower this channel's limit by a number of messages")
+        .create_option(|option| {
+            option
+                .name("delta")
+                .description("How many messages to add (or remove, if negative)")
+                .kind(CommandOptionType::Integer)
+                .required(true)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<i64, ()> {
+    let option = options
+        .first()
+        .expect("Expected delta option")
+        .resolved
+        .as_ref()
+        .expect("Expected integer object");
+    if let CommandDataOptionValue::Integer(i) = option {
+        Ok(*i)
+    } else {
+        Err(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,55 @@
+use serenity::builder;
+use serenity::model::prelude::ChannelId;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+use crate::msgman::ChannelAccess;
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("allowchannel")
+        .description("Allow or deny a channel from being autodeleted (bot owner only)")
+        .create_option(|option| {
+            option
+                .name("channel")
+                .description("Which channel to update")
+                .kind(CommandOptionType::Channel)
+                .required(true)
+        })
+        .create_option(|option| {
+            option
+                .name("access")
+                .description("Whether the channel may be autodeleted")
+                .kind(CommandOptionType::String)
+                .add_string_choice("allow", "allow")
+                .add_string_choice("deny", "deny")
+                .add_string_choice("clear", "clear")
+                .required(true)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<(ChannelId, ChannelAccess), ()> {
+    let mut channel = None;
+    let mut access = None;
+    for option in options {
+        match (option.name.as_str(), option.resolved.as_ref()) {
+            ("channel", Some(CommandDataOptionValue::Channel(partial_channel))) => channel = Some(partial_channel.id),
+            ("access", Some(CommandDataOptionValue::String(value))) => access = match value.as_str() {
+                "allow" => Some(ChannelAccess::Allow),
+                "deny" => Some(ChannelAccess::Deny),
+                "clear" => Some(ChannelAccess::Clear),
+                _ => None,
+            },
+            _ => {}
+        }
+    }
+    match (channel, access) {
+        (Some(channel), Some(access)) => Ok((channel, access)),
+        _ => Err(()),
+    }
+}
\ No newline at end of file
@@ -0,0 +1,35 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("announce")
+        .description("Choose whether limit changes are announced in the affected channels of this server")
+        .create_option(|option| {
+            option
+                .name("enabled")
+                .description("Whether limit changes are announced")
+                .kind(CommandOptionType::Boolean)
+                .required(true)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<bool, ()> {
+    let option = options
+        .first()
+        .expect("Expected enabled option")
+        .resolved
+        .as_ref()
+        .expect("Expected boolean object");
+    if let CommandDataOptionValue::Boolean(enabled) = option {
+        Ok(*enabled)
+    } else {
+        Err(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,45 @@
+use serenity::builder;
+use serenity::model::Permissions;
+use serenity::model::prelude::UserId;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("audit")
+        .description("List every limit change made by a member, across all channels")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .create_option(|option| {
+            option
+                .name("user")
+                .description("Whose limit changes to list")
+                .kind(CommandOptionType::User)
+                .required(true)
+        })
+        .create_option(|option| {
+            option
+                .name("page")
+                .description("Which page of changes to show, newest first (defaults to the first)")
+                .kind(CommandOptionType::Integer)
+                .required(false)
+        })
+}
+
+/// The member and the requested page, if given
+pub fn run(options: &[CommandDataOption]) -> Result<(UserId, Option<i64>), ()> {
+    let mut user = None;
+    let mut page = None;
+    for option in options {
+        match (option.name.as_str(), option.resolved.as_ref()) {
+            ("user", Some(CommandDataOptionValue::User(value, _))) => user = Some(value.id),
+            ("page", Some(CommandDataOptionValue::Integer(value))) => page = Some(*value),
+            _ => {}
+        }
+    }
+    user.map(|user| (user, page)).ok_or(())
+}
\ No newline at end of file
@@ -0,0 +1,44 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("autoconfig-pattern")
+        .description("Automatically configure autodelete for channels whose name matches a pattern")
+        .create_option(|option| {
+            option
+                .name("pattern")
+                .description("Channel name pattern, where * matches anything and ? matches a single character")
+                .kind(CommandOptionType::String)
+                .required(true)
+        })
+        .create_option(|option| {
+            option
+                .name("messages")
+                .description("How many messages to keep (0 removes the pattern)")
+                .kind(CommandOptionType::Integer)
+                .required(true)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<(String, i64), ()> {
+    let mut pattern = None;
+    let mut limit = None;
+    for option in options {
+        match (option.name.as_str(), option.resolved.as_ref()) {
+            ("pattern", Some(CommandDataOptionValue::String(value))) => pattern = Some(value.trim().to_string()),
+            ("messages", Some(CommandDataOptionValue::Integer(value))) => limit = Some(*value),
+            _ => {}
+        }
+    }
+    match (pattern, limit) {
+        (Some(pattern), Some(limit)) if !pattern.is_empty() => Ok((pattern, limit)),
+        _ => Err(()),
+    }
+}
\ No newline at end of file
@@ -0,0 +1,51 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+use crate::msgman::KeywordAction;
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("blockword")
+        .description("Immediately delete messages containing a keyword in this channel")
+        .create_option(|option| {
+            option
+                .name("action")
+                .description("What to do with the keyword")
+                .kind(CommandOptionType::String)
+                .add_string_choice("add", "add")
+                .add_string_choice("remove", "remove")
+                .add_string_choice("list", "list")
+                .required(true)
+        })
+        .create_option(|option| {
+            option
+                .name("keyword")
+                .description("The keyword to add or remove")
+                .kind(CommandOptionType::String)
+                .required(false)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<KeywordAction, ()> {
+    let mut action = None;
+    let mut keyword = None;
+    for option in options {
+        match (option.name.as_str(), option.resolved.as_ref()) {
+            ("action", Some(CommandDataOptionValue::String(value))) => action = Some(value.clone()),
+            ("keyword", Some(CommandDataOptionValue::String(value))) => keyword = Some(value.clone()),
+            _ => {}
+        }
+    }
+    match (action.as_deref(), keyword) {
+        (Some("add"), Some(keyword)) => Ok(KeywordAction::Add(keyword)),
+        (Some("remove"), Some(keyword)) => Ok(KeywordAction::Remove(keyword)),
+        (Some("list"), _) => Ok(KeywordAction::List),
+        _ => Err(()),
+    }
+}
\ No newline at end of file
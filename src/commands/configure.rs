@@ -1,36 +1,138 @@
 use serenity::builder;
+use serenity::model::prelude::ChannelId;
 use serenity::model::prelude::command::CommandOptionType;
 use serenity::model::prelude::interaction::application_command::{
     CommandDataOption,
     CommandDataOptionValue,
 };
 
+/// Limits offered as a dropdown, easier to pick than typing a number on mobile
+const PRESET_LIMITS: [i64; 6] = [10, 25, 50, 100, 250, 500];
+
 pub fn register(
     command: &mut builder::CreateApplicationCommand,
 ) -> &mut builder::CreateApplicationCommand {
     command
         .name("configure")
-        .description("Configure autodelete for this channel")
+        .description("Configure autodelete for this channel (or another one)")
         .create_option(|option| {
             option
                 .name("messages")
-                .description("How many messages to keep")
+                .description("How many messages to keep (0 deletes every new message right away), or pick a preset")
+                .kind(CommandOptionType::Integer)
+                .required(false)
+        })
+        .create_option(|option| {
+            option
+                .name("preset")
+                .description("A common number of messages to keep, instead of typing one")
+                .kind(CommandOptionType::Integer)
+                .required(false);
+            for limit in PRESET_LIMITS {
+                option.add_int_choice(format!("{} messages", limit), limit as i32);
+            }
+            option
+        })
+        .create_option(|option| {
+            option
+                .name("max_bytes")
+                .description("Also delete older messages once their text adds up to more than this many bytes")
                 .kind(CommandOptionType::Integer)
-                .required(true)
+                .required(false)
+        })
+        .create_option(|option| {
+            option
+                .name("channel")
+                .description("Which channel to configure (defaults to this one)")
+                .kind(CommandOptionType::Channel)
+                .required(false)
         })
 }
 
-pub fn run(options: &[CommandDataOption]) -> Result<i64, ()> {
-    let option = options
-        .get(0)
-        .expect("Expected messages option")
-        .resolved
-        .as_ref()
-        .expect("Expected user object");    
-    if let CommandDataOptionValue::Integer(i) = option {
-        Ok(*i)
-    } else {
-        Err(())
+/// Some clients send integer options as numbers or strings, which are accepted as long as they are whole
+fn whole_number(value: &CommandDataOptionValue) -> Result<i64, ()> {
+    match value {
+        CommandDataOptionValue::Integer(i) => Ok(*i),
+        CommandDataOptionValue::Number(n) if n.fract() == 0.0 && *n >= i64::MIN as f64 && *n <= i64::MAX as f64 => Ok(*n as i64),
+        CommandDataOptionValue::String(s) => s.trim().parse::<i64>().map_err(|_| ()),
+        _ => Err(()),
+    }
+}
+
+const CONFIRM_ID_PREFIX: &str = "configure-confirm";
+
+/// The custom id of the button confirming a limit change, which carries the whole request
+/// so that nothing has to be remembered until it is pressed
+pub fn confirm_id(channel: ChannelId, limit: usize, byte_budget: Option<usize>) -> String {
+    match byte_budget {
+        Some(byte_budget) => format!("{}:{}:{}:{}", CONFIRM_ID_PREFIX, channel, limit, byte_budget),
+        None => format!("{}:{}:{}", CONFIRM_ID_PREFIX, channel, limit),
+    }
+}
+
+/// The channel, limit and byte budget of a confirmation button, if `custom_id` is one
+pub fn parse_confirm_id(custom_id: &str) -> Option<(ChannelId, usize, Option<usize>)> {
+    let mut parts = custom_id.split(':');
+    if parts.next()? != CONFIRM_ID_PREFIX {
+        return None;
+    }
+    let channel = ChannelId::from(parts.next()?.parse::<u64>().ok()?);
+    let limit = parts.next()?.parse::<usize>().ok()?;
+    let byte_budget = match parts.next() {
+        Some(byte_budget) => Some(byte_budget.parse::<usize>().ok()?),
+        None => None,
+    };
+    Some((channel, limit, byte_budget))
+}
+
+/// The message limit (typed or picked from the presets, but not both), the byte budget and the target channel, if they were given
+pub fn run(options: &[CommandDataOption]) -> Result<(i64, Option<i64>, Option<ChannelId>), ()> {
+    let mut limit = None;
+    let mut preset = None;
+    let mut byte_budget = None;
+    let mut channel = None;
+    for option in options {
+        match (option.name.as_str(), option.resolved.as_ref()) {
+            ("messages", Some(value)) => limit = Some(whole_number(value)?),
+            ("preset", Some(value)) => preset = Some(whole_number(value)?),
+            ("max_bytes", Some(value)) => byte_budget = Some(whole_number(value)?),
+            ("channel", Some(CommandDataOptionValue::Channel(target))) => channel = Some(target.id),
+            _ => {}
+        }
+    }
+    match (limit, preset) {
+        (Some(limit), None) | (None, Some(limit)) => Ok((limit, byte_budget, channel)),
+        _ => Err(()),
     }
-    // "Hey, I'm alive!".to_string()
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The options of a /configure invocation, resolved by serenity as they would be from Discord's payload
+    fn configure_options(options: serde_json::Value) -> Vec<CommandDataOption> {
+        let data: serenity::model::application::interaction::application_command::CommandData = serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "name": "configure",
+            "type": 1,
+            "options": options,
+        })).expect("Command data is valid");
+        data.options
+    }
+
+    #[test]
+    fn configure_accepts_whole_numbers_in_any_encoding() {
+        let integer = configure_options(serde_json::json!([{ "name": "messages", "type": 4, "value": 25 }]));
+        assert_eq!(run(&integer), Ok((25, None, None)));
+        let number = configure_options(serde_json::json!([{ "name": "messages", "type": 10, "value": 25.0 }]));
+        assert_eq!(run(&number), Ok((25, None, None)));
+        let string = configure_options(serde_json::json!([{ "name": "messages", "type": 3, "value": " 25 " }]));
+        assert_eq!(run(&string), Ok((25, None, None)));
+
+        let fraction = configure_options(serde_json::json!([{ "name": "messages", "type": 10, "value": 25.5 }]));
+        assert_eq!(run(&fraction), Err(()));
+        let not_a_number = configure_options(serde_json::json!([{ "name": "messages", "type": 3, "value": "twenty" }]));
+        assert_eq!(run(&not_a_number), Err(()));
+    }
+}
@@ -0,0 +1,45 @@
+use serenity::builder;
+use serenity::model::prelude::ChannelId;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("copyconfig")
+        .description("Apply one channel's autodelete configuration to another channel")
+        .create_option(|option| {
+            option
+                .name("from")
+                .description("The channel to copy the configuration from")
+                .kind(CommandOptionType::Channel)
+                .required(true)
+        })
+        .create_option(|option| {
+            option
+                .name("to")
+                .description("The channel to apply the configuration to")
+                .kind(CommandOptionType::Channel)
+                .required(true)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<(ChannelId, ChannelId), ()> {
+    let mut source = None;
+    let mut target = None;
+    for option in options {
+        match (option.name.as_str(), option.resolved.as_ref()) {
+            ("from", Some(CommandDataOptionValue::Channel(channel))) => source = Some(channel.id),
+            ("to", Some(CommandDataOptionValue::Channel(channel))) => target = Some(channel.id),
+            _ => {}
+        }
+    }
+    match (source, target) {
+        (Some(source), Some(target)) => Ok((source, target)),
+        _ => Err(()),
+    }
+}
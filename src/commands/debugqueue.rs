@@ -0,0 +1,34 @@
+use serenity::builder;
+use serenity::model::prelude::ChannelId;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("debug-queue")
+        .description("Show the messages tracked for a channel (bot owner only)")
+        .create_option(|option| {
+            option
+                .name("channel")
+                .description("Which channel to inspect (defaults to this one)")
+                .kind(CommandOptionType::Channel)
+                .required(false)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Option<ChannelId> {
+    let option = options
+        .first()?
+        .resolved
+        .as_ref()?;
+    if let CommandDataOptionValue::Channel(channel) = option {
+        Some(channel.id)
+    } else {
+        None
+    }
+}
\ No newline at end of file
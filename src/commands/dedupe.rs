@@ -0,0 +1,35 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("dedupe")
+        .description("Delete repeated identical messages from the same author in this channel")
+        .create_option(|option| {
+            option
+                .name("enabled")
+                .description("Whether rapid duplicates are deleted")
+                .kind(CommandOptionType::Boolean)
+                .required(true)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<bool, ()> {
+    let option = options
+        .first()
+        .expect("Expected enabled option")
+        .resolved
+        .as_ref()
+        .expect("Expected boolean object");
+    if let CommandDataOptionValue::Boolean(enabled) = option {
+        Ok(*enabled)
+    } else {
+        Err(())
+    }
+}
\ No newline at end of file
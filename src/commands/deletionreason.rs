@@ -0,0 +1,34 @@
+use serenity::builder;
+use serenity::model::Permissions;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+use crate::auditlog::REASON_MAX_LENGTH;
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("deletion-reason")
+        .description("Choose the reason shown in this server's audit log for the bot's deletions")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .create_option(|option| {
+            option
+                .name("reason")
+                .description("The reason to show (leave empty to go back to the default)")
+                .kind(CommandOptionType::String)
+                .max_length(REASON_MAX_LENGTH as u16)
+                .required(false)
+        })
+}
+
+/// The reason, or `None` to go back to the default
+pub fn run(options: &[CommandDataOption]) -> Option<String> {
+    match options.first().and_then(|option| option.resolved.as_ref()) {
+        Some(CommandDataOptionValue::String(reason)) if !reason.trim().is_empty() => Some(reason.trim().to_string()),
+        _ => None,
+    }
+}
\ No newline at end of file
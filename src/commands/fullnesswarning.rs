@@ -0,0 +1,42 @@
+use serenity::builder;
+use serenity::model::prelude::ChannelId;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("fullness-warning")
+        .description("Get notified when a channel of this server is nearly full")
+        .create_option(|option| {
+            option
+                .name("percent")
+                .description("How full a channel gets before the notice is posted (0 to disable)")
+                .kind(CommandOptionType::Integer)
+                .required(true)
+        })
+        .create_option(|option| {
+            option
+                .name("log_channel")
+                .description("Where to post the notices")
+                .kind(CommandOptionType::Channel)
+                .required(false)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<(i64, Option<ChannelId>), ()> {
+    let mut percent = None;
+    let mut log_channel = None;
+    for option in options {
+        match (option.name.as_str(), option.resolved.as_ref()) {
+            ("percent", Some(CommandDataOptionValue::Integer(value))) => percent = Some(*value),
+            ("log_channel", Some(CommandDataOptionValue::Channel(channel))) => log_channel = Some(channel.id),
+            _ => {}
+        }
+    }
+    percent.map(|percent| (percent, log_channel)).ok_or(())
+}
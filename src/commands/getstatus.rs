@@ -1,4 +1,9 @@
 use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
 
 pub fn register(
     command: &mut builder::CreateApplicationCommand,
@@ -6,4 +11,32 @@ pub fn register(
     command
         .name("status")
         .description("Collect data about managed channels")
+        .create_option(|option| {
+            option
+                .name("text")
+                .description("Reply in plain text instead of embeds")
+                .kind(CommandOptionType::Boolean)
+                .required(false)
+        })
+        .create_option(|option| {
+            option
+                .name("public")
+                .description("Post the status for everyone in this channel to see, instead of only you")
+                .kind(CommandOptionType::Boolean)
+                .required(false)
+        })
+}
+
+/// Whether to reply in plain text, and whether to reply publicly
+pub fn run(options: &[CommandDataOption]) -> (bool, bool) {
+    let mut text = false;
+    let mut public = false;
+    for option in options {
+        match (option.name.as_str(), option.resolved.as_ref()) {
+            ("text", Some(CommandDataOptionValue::Boolean(value))) => text = *value,
+            ("public", Some(CommandDataOptionValue::Boolean(value))) => public = *value,
+            _ => {}
+        }
+    }
+    (text, public)
 }
\ No newline at end of file
@@ -0,0 +1,50 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("heavy")
+        .description("Keep fewer messages with large attachments or videos in this channel")
+        .create_option(|option| {
+            option
+                .name("messages")
+                .description("How many heavy messages to keep (0 to disable)")
+                .kind(CommandOptionType::Integer)
+                .required(true)
+        })
+        .create_option(|option| {
+            option
+                .name("min_size_mb")
+                .description("Attachments at least this large (in MB) make a message heavy")
+                .kind(CommandOptionType::Integer)
+                .required(false)
+        })
+        .create_option(|option| {
+            option
+                .name("videos")
+                .description("Whether video attachments make a message heavy")
+                .kind(CommandOptionType::Boolean)
+                .required(false)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<(i64, i64, bool), ()> {
+    let mut limit = None;
+    let mut min_size_mb = 0;
+    let mut videos = false;
+    for option in options {
+        match (option.name.as_str(), option.resolved.as_ref()) {
+            ("messages", Some(CommandDataOptionValue::Integer(value))) => limit = Some(*value),
+            ("min_size_mb", Some(CommandDataOptionValue::Integer(value))) => min_size_mb = *value,
+            ("videos", Some(CommandDataOptionValue::Boolean(value))) => videos = *value,
+            _ => {}
+        }
+    }
+    limit.map(|limit| (limit, min_size_mb, videos)).ok_or(())
+}
\ No newline at end of file
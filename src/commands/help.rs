@@ -0,0 +1,37 @@
+use serenity::builder;
+
+use super::{command_help, Access};
+
+// Discord messages hold at most 2000 characters
+const MESSAGE_LENGTH_LIMIT: usize = 2000;
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("help")
+        .description("List the bot's commands and which of them you can use")
+}
+
+/// Lists every registered command, marking those the user can't use, split into messages that fit Discord's limit
+pub fn run(is_admin: bool, is_owner: bool, killswitch_enabled: bool) -> Vec<String> {
+    let mut pages = Vec::new();
+    let mut page = "Available commands (🔒 means you can't use it):\n".to_string();
+    for command in command_help(killswitch_enabled) {
+        let restriction = match command.access {
+            Access::Everyone => "",
+            Access::Admin if is_admin => "",
+            Access::Admin => " 🔒 server administrators only",
+            Access::Owner if is_owner => "",
+            Access::Owner => " 🔒 bot owner only",
+        };
+        let line = format!("- `/{}`: {}{}\n", command.name, command.description, restriction);
+        if page.len() + line.len() > MESSAGE_LENGTH_LIMIT {
+            pages.push(page);
+            page = String::new();
+        }
+        page.push_str(&line);
+    }
+    pages.push(page);
+    pages
+}
\ No newline at end of file
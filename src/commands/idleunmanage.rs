@@ -0,0 +1,35 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("idleunmanage")
+        .description("Stop autodeleting channels in this server once they have been idle for a while")
+        .create_option(|option| {
+            option
+                .name("days")
+                .description("How many days without messages before a channel stops being managed (0 to disable)")
+                .kind(CommandOptionType::Integer)
+                .required(true)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<i64, ()> {
+    let option = options
+        .first()
+        .expect("Expected days option")
+        .resolved
+        .as_ref()
+        .expect("Expected integer object");
+    if let CommandDataOptionValue::Integer(i) = option {
+        Ok(*i)
+    } else {
+        Err(())
+    }
+}
\ No newline at end of file
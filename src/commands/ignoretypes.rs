@@ -0,0 +1,31 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+use crate::msgman::parse_ignored_types;
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("ignore-types")
+        .description("Choose which kinds of messages are never tracked nor deleted in this server")
+        .create_option(|option| {
+            option
+                .name("types")
+                .description("Comma-separated types (e.g. pins-added, thread-created), \"default\" or \"none\"")
+                .kind(CommandOptionType::String)
+                .required(true)
+        })
+}
+
+/// The ignored type names, or the reason they were rejected
+pub fn run(options: &[CommandDataOption]) -> Result<Vec<&'static str>, String> {
+    match options.first().and_then(|option| option.resolved.as_ref()) {
+        Some(CommandDataOptionValue::String(types)) => parse_ignored_types(types),
+        _ => Err("Please list the message types to ignore".to_string()),
+    }
+}
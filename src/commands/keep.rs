@@ -0,0 +1,46 @@
+use serenity::builder;
+use serenity::model::prelude::{ChannelId, GuildId, MessageId};
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("keep")
+        .description("Never delete a message, without having to pin it")
+        .create_option(|option| {
+            option
+                .name("message")
+                .description("The message's link or ID")
+                .kind(CommandOptionType::String)
+                .required(true)
+        })
+}
+
+/// Parses a message link (`https://discord.com/channels/<guild>/<channel>/<message>`) or a bare message ID,
+/// which belongs to the channel the command is used in (`None`)
+pub fn parse_message_reference(value: &str) -> Option<(Option<(GuildId, ChannelId)>, MessageId)> {
+    let value = value.trim();
+    if let Ok(message_id) = value.parse::<u64>() {
+        return Some((None, MessageId::from(message_id)));
+    }
+    let path = value.split("/channels/").nth(1)?;
+    match path.split('/').collect::<Vec<&str>>().as_slice() {
+        [guild, channel, message] => {
+            let location = (GuildId::from(guild.parse::<u64>().ok()?), ChannelId::from(channel.parse::<u64>().ok()?));
+            Some((Some(location), MessageId::from(message.parse::<u64>().ok()?)))
+        },
+        _ => None,
+    }
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<(Option<(GuildId, ChannelId)>, MessageId), ()> {
+    match options.first().and_then(|option| option.resolved.as_ref()) {
+        Some(CommandDataOptionValue::String(value)) => parse_message_reference(value).ok_or(()),
+        _ => Err(()),
+    }
+}
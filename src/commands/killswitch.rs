@@ -1,9 +1,11 @@
 use serenity::builder;
 
+pub const NAME: &str = "killswitch";
+
 pub fn register(
     command: &mut builder::CreateApplicationCommand,
 ) -> &mut builder::CreateApplicationCommand {
     command
-        .name("killswitch")
+        .name(NAME)
         .description("Kill this bot if it starts behaving unexpectedly")
 }
\ No newline at end of file
@@ -0,0 +1,46 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+use crate::msgman::LeaderboardMetric;
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("leaderboard")
+        .description("Rank the autodeleted channels by how much they churn")
+        .create_option(|option| {
+            option
+                .name("metric")
+                .description("What to rank the channels by (defaults to deleted messages)")
+                .kind(CommandOptionType::String)
+                .add_string_choice("deleted messages", "deleted")
+                .add_string_choice("fullness", "fullness")
+                .add_string_choice("messages per day", "rate")
+                .required(false)
+        })
+        .create_option(|option| {
+            option
+                .name("count")
+                .description("How many channels to list")
+                .kind(CommandOptionType::Integer)
+                .required(false)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> (LeaderboardMetric, Option<i64>) {
+    let mut metric = LeaderboardMetric::default();
+    let mut count = None;
+    for option in options {
+        match (option.name.as_str(), option.resolved.as_ref()) {
+            ("metric", Some(CommandDataOptionValue::String(value))) => metric = LeaderboardMetric::parse(value).unwrap_or_default(),
+            ("count", Some(CommandDataOptionValue::Integer(value))) => count = Some(*value),
+            _ => {}
+        }
+    }
+    (metric, count)
+}
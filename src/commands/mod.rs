@@ -1,4 +1,233 @@
 pub mod configure;
 pub mod remove;
 pub mod killswitch;
-pub mod getstatus;
\ No newline at end of file
+pub mod getstatus;
+pub mod trim;
+pub mod info;
+pub mod allowchannel;
+pub mod blockword;
+pub mod autoconfig;
+pub mod countpins;
+pub mod tempraise;
+pub mod keepoldest;
+pub mod idleunmanage;
+pub mod setmultiple;
+pub mod systemmessages;
+pub mod resetstats;
+pub mod removeall;
+pub mod dedupe;
+pub mod adjust;
+pub mod debugqueue;
+pub mod heavy;
+pub mod version;
+pub mod announce;
+pub mod snooze;
+pub mod copyconfig;
+pub mod fullnesswarning;
+pub mod recentdeletes;
+pub mod ignoretypes;
+pub mod settimezone;
+pub mod protectrole;
+pub mod leaderboard;
+pub mod keep;
+pub mod unkeep;
+pub mod retention;
+pub mod testdelete;
+pub mod reload;
+pub mod simulate;
+pub mod keeplatest;
+pub mod tombstone;
+pub mod audit;
+pub mod threads;
+pub mod verbosity;
+pub mod confirmimportant;
+pub mod purgeonban;
+pub mod deletionreason;
+pub mod help;
+pub mod text;
+
+use serde_json::Value;
+use serenity::builder::CreateApplicationCommand;
+use serenity::model::application::command::Command;
+
+type Register = fn(&mut CreateApplicationCommand) -> &mut CreateApplicationCommand;
+
+/// Who can use a command, on top of the server's own integration settings
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Everyone,
+    /// Server administrators
+    Admin,
+    /// Whoever owns the bot's application
+    Owner,
+}
+
+/// What /help shows about a command, read from its registered definition
+pub struct CommandHelp {
+    pub name: String,
+    pub description: String,
+    pub access: Access,
+}
+
+const REGISTRATIONS: &[(Register, Access)] = &[
+    (configure::register, Access::Everyone),
+    (remove::register, Access::Everyone),
+    (killswitch::register, Access::Everyone),
+    (getstatus::register, Access::Everyone),
+    (trim::register, Access::Everyone),
+    (info::register, Access::Everyone),
+    (allowchannel::register, Access::Owner),
+    (blockword::register, Access::Everyone),
+    (autoconfig::register, Access::Everyone),
+    (countpins::register, Access::Everyone),
+    (tempraise::register, Access::Everyone),
+    (keepoldest::register, Access::Everyone),
+    (idleunmanage::register, Access::Everyone),
+    (setmultiple::register, Access::Everyone),
+    (systemmessages::register, Access::Everyone),
+    (resetstats::register, Access::Admin),
+    (removeall::register, Access::Admin),
+    (dedupe::register, Access::Everyone),
+    (adjust::register, Access::Everyone),
+    (debugqueue::register, Access::Owner),
+    (heavy::register, Access::Everyone),
+    (version::register, Access::Everyone),
+    (announce::register, Access::Everyone),
+    (snooze::register, Access::Everyone),
+    (copyconfig::register, Access::Everyone),
+    (fullnesswarning::register, Access::Everyone),
+    (recentdeletes::register, Access::Admin),
+    (ignoretypes::register, Access::Everyone),
+    (settimezone::register, Access::Everyone),
+    (protectrole::register, Access::Everyone),
+    (leaderboard::register, Access::Everyone),
+    (keep::register, Access::Everyone),
+    (unkeep::register, Access::Everyone),
+    (retention::register, Access::Everyone),
+    (testdelete::register, Access::Owner),
+    (reload::register, Access::Owner),
+    (simulate::register, Access::Owner),
+    (keeplatest::register, Access::Everyone),
+    (tombstone::register, Access::Everyone),
+    (audit::register, Access::Admin),
+    (threads::register, Access::Everyone),
+    (verbosity::register, Access::Everyone),
+    (confirmimportant::register, Access::Everyone),
+    (purgeonban::register, Access::Admin),
+    (deletionreason::register, Access::Admin),
+    (help::register, Access::Everyone),
+];
+
+/// The parts of a command definition that matter when deciding whether it needs to be registered again
+#[derive(PartialEq, Debug)]
+struct CommandSignature {
+    name: String,
+    description: String,
+    options: Vec<OptionSignature>,
+}
+
+#[derive(PartialEq, Debug)]
+struct OptionSignature {
+    kind: u64,
+    name: String,
+    description: String,
+    required: bool,
+    autocomplete: bool,
+    choices: Vec<(String, Value)>,
+}
+
+fn string_field(value: &Value, key: &str) -> String {
+    value.get(key).and_then(Value::as_str).unwrap_or_default().to_string()
+}
+
+/// Reads a command signature out of its JSON representation, which both the builders and the fetched commands share
+fn signature(value: &Value) -> CommandSignature {
+    let options = value.get("options").and_then(Value::as_array).map(|options| {
+        options.iter().map(|option| OptionSignature {
+            kind: option.get("type").and_then(Value::as_u64).unwrap_or_default(),
+            name: string_field(option, "name"),
+            description: string_field(option, "description"),
+            required: option.get("required").and_then(Value::as_bool).unwrap_or(false),
+            autocomplete: option.get("autocomplete").and_then(Value::as_bool).unwrap_or(false),
+            choices: option.get("choices").and_then(Value::as_array).map(|choices| {
+                choices.iter().map(|choice| (string_field(choice, "name"), choice.get("value").cloned().unwrap_or(Value::Null))).collect()
+            }).unwrap_or_default(),
+        }).collect()
+    }).unwrap_or_default();
+    CommandSignature {
+        name: string_field(value, "name"),
+        description: string_field(value, "description"),
+        options,
+    }
+}
+
+/// Builds the definitions of every command the bot registers, leaving the killswitch out when it is disabled
+pub fn desired_commands(killswitch_enabled: bool) -> Vec<CreateApplicationCommand> {
+    REGISTRATIONS.iter().map(|(register, _)| {
+        let mut command = CreateApplicationCommand::default();
+        register(&mut command);
+        command
+    }).filter(|command| killswitch_enabled || command.0.get("name").and_then(Value::as_str) != Some(killswitch::NAME)).collect()
+}
+
+/// Names and descriptions of every command the bot registers, in registration order
+pub fn command_help(killswitch_enabled: bool) -> Vec<CommandHelp> {
+    REGISTRATIONS.iter().map(|(register, access)| {
+        let mut command = CreateApplicationCommand::default();
+        register(&mut command);
+        let field = |key: &str| command.0.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+        CommandHelp { name: field("name"), description: field("description"), access: *access }
+    }).filter(|command| killswitch_enabled || command.name != killswitch::NAME).collect()
+}
+
+/// Whether the registered commands differ from the desired ones (by name, description or options)
+pub fn commands_changed(desired: &[CreateApplicationCommand], existing: &[Command]) -> bool {
+    let mut desired: Vec<CommandSignature> = desired.iter()
+        .map(|command| signature(&Value::Object(command.0.iter().map(|(key, value)| (key.to_string(), value.clone())).collect())))
+        .collect();
+    let mut existing: Vec<CommandSignature> = existing.iter()
+        .filter_map(|command| serde_json::to_value(command).ok())
+        .map(|value| signature(&value))
+        .collect();
+    desired.sort_by(|a, b| a.name.cmp(&b.name));
+    existing.sort_by(|a, b| a.name.cmp(&b.name));
+    desired != existing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The commands as Discord would return them once registered
+    fn registered_commands(desired: &[CreateApplicationCommand]) -> Vec<Command> {
+        desired.iter().enumerate().map(|(index, command)| {
+            let mut value: serde_json::Map<String, serde_json::Value> = command.0.iter().map(|(key, value)| (key.to_string(), value.clone())).collect();
+            value.insert("id".to_string(), serde_json::json!((index + 1).to_string()));
+            value.insert("application_id".to_string(), serde_json::json!("1"));
+            value.insert("version".to_string(), serde_json::json!("1"));
+            value.insert("type".to_string(), serde_json::json!(1));
+            serde_json::from_value(serde_json::Value::Object(value)).expect("Registered command is valid")
+        }).collect()
+    }
+
+    #[test]
+    fn unchanged_commands_are_not_registered_again() {
+        let desired = desired_commands(true);
+        let mut registered = registered_commands(&desired);
+        assert!(!commands_changed(&desired, &registered));
+        // Discord doesn't return them in registration order
+        registered.reverse();
+        assert!(!commands_changed(&desired, &registered));
+
+        let mut edited = registered.clone();
+        edited[0].description = format!("{} (edited)", edited[0].description);
+        assert!(commands_changed(&desired, &edited));
+        let mut missing_option = registered.clone();
+        let with_options = missing_option.iter_mut().find(|command| !command.options.is_empty()).expect("Some command has options");
+        with_options.options.pop();
+        assert!(commands_changed(&desired, &missing_option));
+        assert!(commands_changed(&desired, &registered[1..]));
+        // Disabling the killswitch unregisters it
+        assert!(commands_changed(&desired_commands(false), &registered));
+    }
+}
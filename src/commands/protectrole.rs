@@ -0,0 +1,51 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+use crate::msgman::RoleAction;
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("protectrole")
+        .description("Never delete messages from members with a role in this channel")
+        .create_option(|option| {
+            option
+                .name("action")
+                .description("What to do with the role")
+                .kind(CommandOptionType::String)
+                .add_string_choice("add", "add")
+                .add_string_choice("remove", "remove")
+                .add_string_choice("list", "list")
+                .required(true)
+        })
+        .create_option(|option| {
+            option
+                .name("role")
+                .description("The role to protect or stop protecting")
+                .kind(CommandOptionType::Role)
+                .required(false)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<RoleAction, ()> {
+    let mut action = None;
+    let mut role = None;
+    for option in options {
+        match (option.name.as_str(), option.resolved.as_ref()) {
+            ("action", Some(CommandDataOptionValue::String(value))) => action = Some(value.clone()),
+            ("role", Some(CommandDataOptionValue::Role(value))) => role = Some(value.id),
+            _ => {}
+        }
+    }
+    match (action.as_deref(), role) {
+        (Some("add"), Some(role)) => Ok(RoleAction::Add(role)),
+        (Some("remove"), Some(role)) => Ok(RoleAction::Remove(role)),
+        (Some("list"), _) => Ok(RoleAction::List),
+        _ => Err(()),
+    }
+}
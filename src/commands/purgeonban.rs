@@ -0,0 +1,37 @@
+use serenity::builder;
+use serenity::model::Permissions;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("purge-on-ban")
+        .description("Choose whether the tracked messages of members banned from this server are deleted right away")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .create_option(|option| {
+            option
+                .name("enabled")
+                .description("Whether a banned member's messages are deleted")
+                .kind(CommandOptionType::Boolean)
+                .required(true)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<bool, ()> {
+    let option = options
+        .first()
+        .expect("Expected enabled option")
+        .resolved
+        .as_ref()
+        .expect("Expected boolean object");
+    if let CommandDataOptionValue::Boolean(enabled) = option {
+        Ok(*enabled)
+    } else {
+        Err(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,44 @@
+use serenity::builder;
+use serenity::model::Permissions;
+use serenity::model::prelude::ChannelId;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("recent-deletes")
+        .description("List the messages the bot deleted most recently in a channel")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .create_option(|option| {
+            option
+                .name("count")
+                .description("How many deleted messages to list")
+                .kind(CommandOptionType::Integer)
+                .required(false)
+        })
+        .create_option(|option| {
+            option
+                .name("channel")
+                .description("Which channel to list (defaults to this one)")
+                .kind(CommandOptionType::Channel)
+                .required(false)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> (Option<i64>, Option<ChannelId>) {
+    let mut count = None;
+    let mut channel = None;
+    for option in options {
+        match (option.name.as_str(), option.resolved.as_ref()) {
+            ("count", Some(CommandDataOptionValue::Integer(value))) => count = Some(*value),
+            ("channel", Some(CommandDataOptionValue::Channel(value))) => channel = Some(value.id),
+            _ => {}
+        }
+    }
+    (count, channel)
+}
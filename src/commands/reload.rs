@@ -0,0 +1,9 @@
+use serenity::builder;
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("reload")
+        .description("Pick up channel limits edited directly in the database (bot owner only)")
+}
\ No newline at end of file
@@ -0,0 +1,11 @@
+use serenity::builder;
+use serenity::model::Permissions;
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("removeall")
+        .description("Stop autodeleting every channel in this server")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+}
\ No newline at end of file
@@ -0,0 +1,30 @@
+use serenity::builder;
+use serenity::model::Permissions;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("resetstats")
+        .description("Reset the deleted messages counter of this channel")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .create_option(|option| {
+            option
+                .name("all")
+                .description("Reset the counters of every managed channel instead")
+                .kind(CommandOptionType::Boolean)
+                .required(false)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> bool {
+    match options.first().and_then(|option| option.resolved.as_ref()) {
+        Some(CommandDataOptionValue::Boolean(all)) => *all,
+        _ => false,
+    }
+}
\ No newline at end of file
@@ -0,0 +1,39 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+use crate::msgman::RetentionDirection;
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("retention")
+        .description("Choose whether this channel keeps its newest or its oldest messages")
+        .create_option(|option| {
+            option
+                .name("keep")
+                .description("Which messages are kept once the limit is reached")
+                .kind(CommandOptionType::String)
+                .add_string_choice("newest (delete the oldest messages)", "newest")
+                .add_string_choice("oldest (delete new messages once full)", "oldest")
+                .required(true)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<RetentionDirection, ()> {
+    let option = options
+        .first()
+        .expect("Expected keep option")
+        .resolved
+        .as_ref()
+        .expect("Expected string object");
+    if let CommandDataOptionValue::String(direction) = option {
+        RetentionDirection::parse(direction).ok_or(())
+    } else {
+        Err(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,67 @@
+use serenity::builder;
+use serenity::model::prelude::ChannelId;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("set-multiple")
+        .description("Configure autodelete for several channels at once")
+        .create_option(|option| {
+            option
+                .name("messages")
+                .description("How many messages to keep")
+                .kind(CommandOptionType::Integer)
+                .required(true)
+        })
+        .create_option(|option| {
+            option
+                .name("channels")
+                .description("The channels to configure, e.g. #general #memes")
+                .kind(CommandOptionType::String)
+                .required(true)
+                .set_autocomplete(true)
+        })
+}
+
+/// Extracts every `<#id>` channel mention from the text, in order and without duplicates
+pub fn parse_channels(text: &str) -> Vec<ChannelId> {
+    let mut channels = Vec::new();
+    for token in text.split("<#").skip(1) {
+        let Some((id, _)) = token.split_once('>') else { continue; };
+        if let Ok(id) = id.parse::<u64>() {
+            let channel = ChannelId(id);
+            if !channels.contains(&channel) {
+                channels.push(channel);
+            }
+        }
+    }
+    channels
+}
+
+/// The text typed so far in the focused option, when autocompleting
+pub fn autocomplete_input(options: &[CommandDataOption]) -> Option<String> {
+    let option = options.iter().find(|option| option.focused && option.name == "channels")?;
+    option.value.as_ref()?.as_str().map(str::to_string)
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<(i64, Vec<ChannelId>), ()> {
+    let mut limit = None;
+    let mut channels = None;
+    for option in options {
+        match (option.name.as_str(), option.resolved.as_ref()) {
+            ("messages", Some(CommandDataOptionValue::Integer(value))) => limit = Some(*value),
+            ("channels", Some(CommandDataOptionValue::String(value))) => channels = Some(parse_channels(value)),
+            _ => {}
+        }
+    }
+    match (limit, channels) {
+        (Some(limit), Some(channels)) if !channels.is_empty() => Ok((limit, channels)),
+        _ => Err(()),
+    }
+}
\ No newline at end of file
@@ -0,0 +1,32 @@
+use chrono::FixedOffset;
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+use crate::msgman::parse_timezone;
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("settimezone")
+        .description("Choose the timezone used to show times in this server")
+        .create_option(|option| {
+            option
+                .name("timezone")
+                .description("An offset from UTC, such as UTC, UTC+2, UTC-05:00 or UTC+05:30")
+                .kind(CommandOptionType::String)
+                .required(true)
+        })
+}
+
+/// The timezone, or the reason it was rejected
+pub fn run(options: &[CommandDataOption]) -> Result<FixedOffset, String> {
+    match options.first().and_then(|option| option.resolved.as_ref()) {
+        Some(CommandDataOptionValue::String(timezone)) => parse_timezone(timezone),
+        _ => Err("Please choose a timezone".to_string()),
+    }
+}
@@ -0,0 +1,42 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("simulate")
+        .description("Project how a channel's queue would behave, without touching any channel (bot owner only)")
+        .create_option(|option| {
+            option
+                .name("limit")
+                .description("The limit to simulate")
+                .kind(CommandOptionType::Integer)
+                .required(true)
+        })
+        .create_option(|option| {
+            option
+                .name("messages")
+                .description("How many messages get posted")
+                .kind(CommandOptionType::Integer)
+                .required(true)
+        })
+}
+
+/// The limit and the number of messages
+pub fn run(options: &[CommandDataOption]) -> Result<(i64, i64), ()> {
+    let mut limit = None;
+    let mut messages = None;
+    for option in options {
+        match (option.name.as_str(), option.resolved.as_ref()) {
+            ("limit", Some(CommandDataOptionValue::Integer(value))) => limit = Some(*value),
+            ("messages", Some(CommandDataOptionValue::Integer(value))) => messages = Some(*value),
+            _ => {}
+        }
+    }
+    limit.zip(messages).ok_or(())
+}
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("snooze")
+        .description("Stop deleting messages in this channel for a while")
+        .create_option(|option| {
+            option
+                .name("duration")
+                .description("How long to snooze for (e.g. 30m, 2h, 1d), or \"off\" to resume right away")
+                .kind(CommandOptionType::String)
+                .required(true)
+        })
+}
+
+/// Parses durations made of a number and a unit (`m`, `h` or `d`)
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim().to_lowercase();
+    let unit_seconds = match value.chars().last()? {
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return None,
+    };
+    let amount = value[..value.len() - 1].trim().parse::<u64>().ok()?;
+    Some(Duration::from_secs(amount.checked_mul(unit_seconds)?))
+}
+
+/// `None` means the snooze should be lifted
+pub fn run(options: &[CommandDataOption]) -> Result<Option<Duration>, ()> {
+    let option = options
+        .first()
+        .expect("Expected duration option")
+        .resolved
+        .as_ref()
+        .expect("Expected string object");
+    match option {
+        CommandDataOptionValue::String(value) if value.trim().eq_ignore_ascii_case("off") => Ok(None),
+        CommandDataOptionValue::String(value) => parse_duration(value).map(Some).ok_or(()),
+        _ => Err(()),
+    }
+}
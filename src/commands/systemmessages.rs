@@ -0,0 +1,40 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+use crate::msgman::SystemMessagePolicy;
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("systemmessages")
+        .description("Choose how system messages (joins, boosts, pins...) are handled in this channel")
+        .create_option(|option| {
+            option
+                .name("policy")
+                .description("What to do with system messages")
+                .kind(CommandOptionType::String)
+                .add_string_choice("normal (count them like any other message)", "normal")
+                .add_string_choice("delete (delete them right away)", "delete")
+                .add_string_choice("keep (never delete them)", "keep")
+                .required(true)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<SystemMessagePolicy, ()> {
+    let option = options
+        .first()
+        .expect("Expected policy option")
+        .resolved
+        .as_ref()
+        .expect("Expected string object");
+    if let CommandDataOptionValue::String(policy) = option {
+        SystemMessagePolicy::parse(policy).ok_or(())
+    } else {
+        Err(())
+    }
+}
\ No newline at end of file
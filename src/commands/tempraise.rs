@@ -0,0 +1,44 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("tempraise")
+        .description("Temporarily raise this channel's limit, reverting it after a while")
+        .create_option(|option| {
+            option
+                .name("messages")
+                .description("How many messages to keep in the meantime")
+                .kind(CommandOptionType::Integer)
+                .required(true)
+        })
+        .create_option(|option| {
+            option
+                .name("minutes")
+                .description("How long until the previous limit is restored")
+                .kind(CommandOptionType::Integer)
+                .required(true)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<(i64, i64), ()> {
+    let mut limit = None;
+    let mut minutes = None;
+    for option in options {
+        match (option.name.as_str(), option.resolved.as_ref()) {
+            ("messages", Some(CommandDataOptionValue::Integer(value))) => limit = Some(*value),
+            ("minutes", Some(CommandDataOptionValue::Integer(value))) => minutes = Some(*value),
+            _ => {}
+        }
+    }
+    match (limit, minutes) {
+        (Some(limit), Some(minutes)) => Ok((limit, minutes)),
+        _ => Err(()),
+    }
+}
\ No newline at end of file
@@ -0,0 +1,9 @@
+use serenity::builder;
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("testdelete")
+        .description("Post a test message here and delete it, to check the bot can delete (bot owner only)")
+}
\ No newline at end of file
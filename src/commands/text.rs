@@ -0,0 +1,28 @@
+/// The legacy text commands, for servers where slash commands can't be used (e.g. `!autodelete configure 100`)
+pub enum TextCommand {
+    Configure(i64),
+    Remove,
+    Status,
+    Info,
+}
+
+/// `None` when the message isn't a text command at all, otherwise the command or how to use them
+pub fn parse(prefix: &str, content: &str) -> Option<Result<TextCommand, String>> {
+    let arguments = content.trim().strip_prefix(prefix)?;
+    // "!autodeleteconfigure" is not a command
+    if !arguments.is_empty() && !arguments.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let words: Vec<&str> = arguments.split_whitespace().collect();
+    let command = match words.as_slice() {
+        ["configure", limit] => match limit.parse::<i64>() {
+            Ok(limit) => Ok(TextCommand::Configure(limit)),
+            Err(_) => Err("Please choose a valid number".to_string()),
+        },
+        ["remove"] => Ok(TextCommand::Remove),
+        ["status"] => Ok(TextCommand::Status),
+        ["info"] => Ok(TextCommand::Info),
+        _ => Err(format!("Usage: `{0} configure <messages>`, `{0} remove`, `{0} status` or `{0} info`", prefix)),
+    };
+    Some(command)
+}
@@ -0,0 +1,35 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("trim")
+        .description("Delete older messages in this channel once, without autodeleting going forward")
+        .create_option(|option| {
+            option
+                .name("messages")
+                .description("How many messages to keep")
+                .kind(CommandOptionType::Integer)
+                .required(true)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<i64, ()> {
+    let option = options
+        .first()
+        .expect("Expected messages option")
+        .resolved
+        .as_ref()
+        .expect("Expected integer object");
+    if let CommandDataOptionValue::Integer(i) = option {
+        Ok(*i)
+    } else {
+        Err(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,19 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("unkeep")
+        .description("Let a message kept with /keep be deleted again")
+        .create_option(|option| {
+            option
+                .name("message")
+                .description("The message's link or ID")
+                .kind(CommandOptionType::String)
+                .required(true)
+        })
+}
+
+// Its option is parsed by `keep::run`
@@ -0,0 +1,40 @@
+use serenity::builder;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    CommandDataOption,
+    CommandDataOptionValue,
+};
+
+use crate::msgman::Verbosity;
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("verbosity")
+        .description("Choose how much the bot says when a limit is changed in this server")
+        .create_option(|option| {
+            option
+                .name("level")
+                .description("How detailed confirmations are (errors are always shown in full)")
+                .kind(CommandOptionType::String)
+                .add_string_choice("verbose (full explanations)", "verbose")
+                .add_string_choice("terse (short confirmations)", "terse")
+                .add_string_choice("silent (just a checkmark)", "silent")
+                .required(true)
+        })
+}
+
+pub fn run(options: &[CommandDataOption]) -> Result<Verbosity, ()> {
+    let option = options
+        .first()
+        .expect("Expected level option")
+        .resolved
+        .as_ref()
+        .expect("Expected string object");
+    if let CommandDataOptionValue::String(level) = option {
+        Verbosity::parse(level).ok_or(())
+    } else {
+        Err(())
+    }
+}
\ No newline at end of file
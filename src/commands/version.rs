@@ -0,0 +1,9 @@
+use serenity::builder;
+
+pub fn register(
+    command: &mut builder::CreateApplicationCommand,
+) -> &mut builder::CreateApplicationCommand {
+    command
+        .name("version")
+        .description("Show which version of the bot is running")
+}
\ No newline at end of file
@@ -1,7 +1,12 @@
 mod commands;
 
+use std::collections::HashMap;
 use std::process::exit;
 use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
 use dotenv::dotenv;
 
@@ -10,23 +15,180 @@ use serenity::async_trait;
 use serenity::model::application::interaction::{Interaction, InteractionResponseType};
 use serenity::model::prelude::MessageFlags;
 use serenity::model::gateway::Ready;
-use serenity::model::id::GuildId;
-use serenity::model::prelude::{Message, ChannelPinsUpdateEvent, MessageId, ChannelId};
+use serenity::model::id::{GuildId, UserId};
+use serenity::model::prelude::{Message, ChannelPinsUpdateEvent, MessageId, ChannelId, Channel, GuildChannel, Role};
 use serenity::model::prelude::application_command::ApplicationCommandInteraction;
+use serenity::model::prelude::component::ButtonStyle;
+use serenity::model::prelude::{Member, User};
 use serenity::prelude::*;
 
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::Sender;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::oneshot;
 
 mod msgman;
-use msgman::{MessageManagerReceiver,Command};
+use msgman::{MessageManagerReceiver,Command,HeavyRule,FullnessWarning};
+use commands::text::TextCommand;
+
+mod webhook;
+use webhook::ConfigWebhook;
+
+mod seed;
+use seed::SeedConfig;
+
+mod membercache;
+
+mod archive;
+use archive::AttachmentArchive;
+
+mod auditlog;
+use auditlog::DeletionReasons;
+
+mod backup;
+use backup::DatabaseBackups;
+
+mod shutdown;
 
 struct Bot {
     sender: Sender<Command>,
+    backpressure_events: AtomicUsize,
+    killswitch_armed: Mutex<HashMap<UserId, Instant>>,
+    // When disabled, the killswitch is neither registered nor obeyed
+    killswitch_enabled: bool,
+    guild_id: GuildId,
+    started_at: Instant,
+    // Set when text commands are enabled
+    text_command_prefix: Option<String>,
 }
 
 const QUEUE_LIMIT_MIN: i64 = 5;
 const QUEUE_LIMIT_MAX: i64 = 500;
+// Keeps no messages at all, every new message is deleted as soon as it's posted
+const EPHEMERAL_LIMIT: i64 = 0;
+// A full queue of maximum length messages
+const BYTE_BUDGET_MAX: i64 = QUEUE_LIMIT_MAX * 4000;
+const DEFAULT_LIMIT_COOLDOWN_SECS: u64 = 30;
+const DEFAULT_COMMAND_QUEUE_CAPACITY: usize = 32;
+const DEFAULT_SHARD_COUNT: u64 = 1;
+const DEFAULT_CLIENT_START_ATTEMPTS: u32 = 5;
+const DEFAULT_CLIENT_START_BACKOFF_MS: u64 = 1000;
+const DEFAULT_CLIENT_START_MAX_BACKOFF_MS: u64 = 60 * 1000;
+const DEFAULT_PERSIST_INTERVAL_SECS: u64 = 60;
+const DEFAULT_PERSIST_BATCH_SIZE: usize = 500;
+// Replied to commands once the message manager has stopped for good
+const DEGRADED_REPLY: &str = "The bot is in a degraded state, please contact an admin";
+// How long exiting waits for the queues to be persisted, in case the manager is stuck
+const EXIT_FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+const SCHEDULED_REVERT_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+const DELETION_BACKLOG_INTERVAL: Duration = Duration::from_secs(5);
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const SNOOZE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const DEFAULT_PERMISSION_CHECK_INTERVAL_SECS: u64 = 10 * 60;
+const DEFAULT_AUDIT_INTERVAL_SECS: u64 = 60 * 60;
+const DEFAULT_AUDIT_SAMPLE_SIZE: usize = 3;
+const DEFAULT_DELETE_WORKERS: usize = 4;
+const DEFAULT_MEMBER_CACHE_SIZE: usize = 1000;
+const DEFAULT_ARCHIVE_MAX_ATTACHMENT_MB: u64 = 25;
+const DEFAULT_SLOW_COMMAND_WARN_MS: u64 = 5000;
+const DEFAULT_TEXT_COMMAND_PREFIX: &str = "!autodelete";
+const DEFAULT_BACKUP_INTERVAL_SECS: u64 = 24 * 60 * 60;
+const DEFAULT_BACKUP_KEEP: usize = 7;
+const DEFAULT_DELETION_REASON: &str = "Autodelete: channel limit";
+const IDLE_UNMANAGE_MAX_DAYS: i64 = 365;
+const SET_MULTIPLE_MAX_CHANNELS: usize = 25;
+const REMOVE_ALL_CONFIRM_ID: &str = "removeall-confirm";
+const HEAVY_MIN_SIZE_MAX_MB: i64 = 500;
+const KILLSWITCH_CONFIRMATION_WINDOW: Duration = Duration::from_secs(10);
+const TEMPRAISE_MAX_MINUTES: i64 = 7 * 24 * 60;
+const SNOOZE_MAX: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+// Discord returns at most 100 messages per request
+const KEEP_OLDEST_MAX: i64 = 100;
+const RECENT_DELETES_DEFAULT: i64 = 10;
+// As many as each channel's deletion log keeps
+const RECENT_DELETES_MAX: i64 = 50;
+const LEADERBOARD_DEFAULT: i64 = 10;
+const LEADERBOARD_MAX: i64 = 25;
+const SIMULATE_MESSAGES_MAX: i64 = 1_000_000;
+
+/// Whether a channel can be set to keep this many messages
+fn is_valid_limit(limit: i64) -> bool {
+    limit == EPHEMERAL_LIMIT || (QUEUE_LIMIT_MIN..=QUEUE_LIMIT_MAX).contains(&limit)
+}
+
+/// Whether the member invoking an interaction is an administrator of the server
+fn is_admin(member: Option<&Member>) -> bool {
+    member.and_then(|member| member.permissions).is_some_and(|permissions| permissions.administrator())
+}
+
+/// Whether the user owns the bot's application
+async fn is_owner(context: &Context, user_id: UserId) -> bool {
+    match context.http.get_current_application_info().await {
+        Ok(application) => application.owner.id == user_id,
+        Err(why) => {
+            warn!("Cannot fetch application info: {}", why);
+            false
+        }
+    }
+}
+
+impl Bot {
+    /// Queues a command for the message manager.
+    /// If the queue is full, the manager is falling behind (deleting messages is slow), so we log it and wait for room.
+    /// Waiting blocks this event handler, which stalls the gateway until the manager catches up; a larger
+    /// `COMMAND_QUEUE_CAPACITY` absorbs longer bursts at the cost of holding more pending events in memory.
+    async fn send_command(&self, command: Command) {
+        let command = match self.sender.try_send(command) {
+            Ok(()) => return,
+            Err(TrySendError::Full(command)) => {
+                let events = self.backpressure_events.fetch_add(1, Ordering::Relaxed) + 1;
+                warn!("Message manager queue is full, waiting for room (backpressure events so far: {})", events);
+                command
+            }
+            Err(TrySendError::Closed(command)) => command,
+        };
+        // The manager only stops for good after crashing too often, the bot stays up to say so
+        if let Err(why) = self.sender.send(command).await {
+            error!("Error during sendcommand {}", why);
+            if let Some((context, interaction)) = why.0.interaction() {
+                if let Err(why) = interaction.create_followup_message(context, |response| response.content(DEGRADED_REPLY)).await {
+                    warn!("Cannot respond to slash command: {}", why);
+                }
+            }
+        }
+    }
+}
+
+impl Bot {
+    /// Validates and forwards a text command the way the matching slash command would be.
+    /// Returns whether the message was a text command.
+    async fn handle_text_command(&self, context: &Context, message: &Message, prefix: &str) -> bool {
+        let Some(parsed) = commands::text::parse(prefix, &message.content) else { return false; };
+        info!("Received text command from {} ({}) in {}: {}", message.author.name, message.author.id, message.channel_id, message.content);
+
+        // Slash commands are restricted through the server's integration settings, text commands need a permission instead
+        let can_manage = context.cache.guild_channel(message.channel_id)
+            .and_then(|channel| channel.permissions_for_user(&context.cache, message.author.id).ok())
+            .is_some_and(|permissions| permissions.manage_messages());
+        let rejection = match &parsed {
+            _ if message.guild_id.is_none() => Some("This command can only be used in a server".to_string()),
+            _ if !can_manage => Some("Only members who can manage messages here can use text commands".to_string()),
+            Err(why) => Some(why.clone()),
+            Ok(TextCommand::Configure(limit)) if !is_valid_limit(*limit) => Some(format!("The limit should be {} or between {} and {}", EPHEMERAL_LIMIT, QUEUE_LIMIT_MIN, QUEUE_LIMIT_MAX)),
+            Ok(_) => None,
+        };
+        match (rejection, parsed) {
+            (Some(why), _) => {
+                if let Err(why) = message.reply(context, why).await {
+                    warn!("Cannot respond to text command: {}", why);
+                }
+            },
+            (None, Ok(command)) => self.send_command(Command::TextCommandReceived { command, context: context.clone(), message: message.clone() }).await,
+            (None, Err(_)) => {},
+        }
+        true
+    }
+}
 
 #[async_trait]
 impl EventHandler for Bot {
@@ -39,10 +201,13 @@ impl EventHandler for Bot {
                 return;
             }
         }
-        if let Err(why) = self.sender.send(Command::MessageReceived { context, message }).await {
-            error!("Error during sendcommand {}", why);
-            exit(1);
+        if let Some(prefix) = self.text_command_prefix.as_deref() {
+            // Text commands are never tracked, whether they are valid or not
+            if !message.author.bot && self.handle_text_command(&context, &message, prefix).await {
+                return;
+            }
         }
+        self.send_command(Command::MessageReceived { context, message }).await;
     }
 
     async fn message_delete_bulk(
@@ -53,10 +218,7 @@ impl EventHandler for Bot {
         guild_id: Option<GuildId>,
     ) {
         debug!("Received bulk message deletion (channel={})", channel_id);
-        if let Err(why) = self.sender.send(Command::MessagesDeleted { context, channel_id, message_ids, guild_id }).await {
-            error!("Error during sendcommand {}", why);
-            exit(1);
-        }
+        self.send_command(Command::MessagesDeleted { context, channel_id, message_ids, guild_id }).await;
     }
 
     async fn message_delete(
@@ -67,17 +229,47 @@ impl EventHandler for Bot {
         guild_id: Option<GuildId>,
     ) {
         debug!("Received message {} (channel={}) deletion", message_id, channel_id);
-        if let Err(why) = self.sender.send(Command::MessageDeleted { context, channel_id, message_id, guild_id }).await {
-            error!("Error during sendcommand {}", why);
-            exit(1);
-        }
+        self.send_command(Command::MessageDeleted { context, channel_id, message_id, guild_id }).await;
     }
 
     async fn channel_pins_update(&self, context: Context, pin: ChannelPinsUpdateEvent) {
         debug!("Received channel_pins_update");
-        if let Err(why) = self.sender.send(Command::ChannelPinsUpdated { context, channel: pin.channel_id }).await {
-            error!("Error during sendcommand {}", why);
-            exit(1);
+        self.send_command(Command::ChannelPinsUpdated { context, channel: pin.channel_id }).await;
+    }
+
+    async fn channel_create(&self, context: Context, channel: &GuildChannel) {
+        debug!("Received channel_create (channel={})", channel.id);
+        self.send_command(Command::ChannelChanged { context, channel: channel.clone() }).await;
+    }
+
+    async fn thread_create(&self, _context: Context, thread: GuildChannel) {
+        debug!("Received thread_create (thread={})", thread.id);
+        if let Some(parent) = thread.parent_id {
+            self.send_command(Command::ThreadCreated { parent, thread: thread.id }).await;
+        }
+    }
+
+    async fn guild_role_update(&self, _context: Context, _old: Option<Role>, new: Role) {
+        debug!("Received guild_role_update (role={})", new.id);
+        // The bot's own permissions may have changed, no need to wait for the next check
+        self.send_command(Command::CheckPermissions).await;
+        self.send_command(Command::RolesUpdated { guild_id: new.guild_id }).await;
+    }
+
+    async fn guild_member_update(&self, _context: Context, _old: Option<Member>, new: Member) {
+        debug!("Received guild_member_update (guild={}, user={})", new.guild_id, new.user.id);
+        self.send_command(Command::MemberUpdated { guild_id: new.guild_id, user_id: new.user.id }).await;
+    }
+
+    async fn guild_ban_addition(&self, context: Context, guild_id: GuildId, banned_user: User) {
+        debug!("Received guild_ban_addition (guild={}, user={})", guild_id, banned_user.id);
+        self.send_command(Command::UserBanned { guild_id, user_id: banned_user.id, context }).await;
+    }
+
+    async fn channel_update(&self, context: Context, _old: Option<Channel>, new: Channel) {
+        debug!("Received channel_update (channel={})", new.id());
+        if let Channel::Guild(channel) = new {
+            self.send_command(Command::ChannelChanged { context, channel }).await;
         }
     }
 
@@ -112,71 +304,534 @@ impl EventHandler for Bot {
             info!("Received /{} from {} ({}) in {}", command.data.name, command.user.name, command.user.id, command.channel_id);
             match command.data.name.as_str() {
                 "configure" => match commands::configure::run(&command.data.options) {
-                    Err(_) => reply(&command, &context, "Please choose a valid number".to_string(), true).await,
-                    Ok(limit) => {
-                        if limit >= QUEUE_LIMIT_MIN && limit <= QUEUE_LIMIT_MAX {
+                    Err(_) => reply(&command, &context, "Please choose a valid number of messages, or one of the presets (not both)".to_string(), true).await,
+                    Ok((limit, byte_budget, channel)) => {
+                        if byte_budget.is_some_and(|byte_budget| !(1..=BYTE_BUDGET_MAX).contains(&byte_budget)) {
+                            reply(&command, &context, format!("The byte budget should be between 1 and {}", BYTE_BUDGET_MAX), true).await;
+                        } else if is_valid_limit(limit) {
+                            let channel = channel.unwrap_or(command.channel_id);
                             defer(&command, &context, true).await;
-                            if let Err(why) = self.sender.send(Command::SetLimit { limit: limit as usize, context, interaction: command }).await {
-                                error!("Error during sendcommand {}", why);
-                                exit(1);
+                            self.send_command(Command::SetLimit { channel, limit: limit as usize, byte_budget: byte_budget.map(|byte_budget| byte_budget as usize), context, interaction: command }).await;
+                        } else {
+                            reply(&command, &context, format!("The limit should be {} or between {} and {}", EPHEMERAL_LIMIT, QUEUE_LIMIT_MIN, QUEUE_LIMIT_MAX), true).await;
+                        }
+                    }
+                }
+                "adjust" => match commands::adjust::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose a valid number".to_string(), true).await,
+                    Ok(delta) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::AdjustLimit { delta, context, interaction: command }).await;
+                    }
+                }
+                "info" => {
+                    let channel = commands::info::run(&command.data.options).unwrap_or(command.channel_id);
+                    defer(&command, &context, true).await;
+                    self.send_command(Command::GetChannelInfo { channel, context, interaction: command }).await;
+                }
+                "debug-queue" => {
+                    if !is_owner(&context, command.user.id).await {
+                        reply(&command, &context, "Only the bot owner can use this command".to_string(), true).await;
+                    } else {
+                        let channel = commands::debugqueue::run(&command.data.options).unwrap_or(command.channel_id);
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::DebugQueue { channel, context, interaction: command }).await;
+                    }
+                }
+                "testdelete" => {
+                    if !is_owner(&context, command.user.id).await {
+                        reply(&command, &context, "Only the bot owner can use this command".to_string(), true).await;
+                    } else {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::TestDelete { context, interaction: command }).await;
+                    }
+                }
+                "reload" => {
+                    if !is_owner(&context, command.user.id).await {
+                        reply(&command, &context, "Only the bot owner can use this command".to_string(), true).await;
+                    } else {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::Reload { context, interaction: command }).await;
+                    }
+                }
+                "simulate" => {
+                    if !is_owner(&context, command.user.id).await {
+                        reply(&command, &context, "Only the bot owner can use this command".to_string(), true).await;
+                    } else {
+                        match commands::simulate::run(&command.data.options) {
+                            Err(_) => reply(&command, &context, "Please give a limit and a number of messages".to_string(), true).await,
+                            Ok((limit, _)) if !is_valid_limit(limit) => reply(&command, &context, format!("Please use a limit of {} or between {} and {}", EPHEMERAL_LIMIT, QUEUE_LIMIT_MIN, QUEUE_LIMIT_MAX), true).await,
+                            Ok((_, messages)) if !(1..=SIMULATE_MESSAGES_MAX).contains(&messages) => reply(&command, &context, format!("Please simulate between 1 and {} messages", SIMULATE_MESSAGES_MAX), true).await,
+                            Ok((limit, messages)) => reply(&command, &context, msgman::simulate(limit as usize, messages as usize), true).await,
+                        }
+                    }
+                }
+                "allowchannel" => {
+                    if !is_owner(&context, command.user.id).await {
+                        reply(&command, &context, "Only the bot owner can use this command".to_string(), true).await;
+                    } else {
+                        match commands::allowchannel::run(&command.data.options) {
+                            Err(_) => reply(&command, &context, "Please choose a valid channel and access".to_string(), true).await,
+                            Ok((channel, access)) => {
+                                defer(&command, &context, true).await;
+                                self.send_command(Command::SetChannelAccess { channel, access, context, interaction: command }).await;
                             }
+                        }
+                    }
+                }
+                "countpins" => match commands::countpins::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose true or false".to_string(), true).await,
+                    Ok(enabled) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::SetPinsCountTowardLimit { enabled, context, interaction: command }).await;
+                    }
+                }
+                "heavy" => match commands::heavy::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose a valid number".to_string(), true).await,
+                    Ok((limit, min_size_mb, videos)) => {
+                        if !(0..=QUEUE_LIMIT_MAX).contains(&limit) {
+                            reply(&command, &context, format!("The limit should be between 0 and {}", QUEUE_LIMIT_MAX), true).await;
+                        } else if !(0..=HEAVY_MIN_SIZE_MAX_MB).contains(&min_size_mb) {
+                            reply(&command, &context, format!("The minimum size should be between 0 and {} MB", HEAVY_MIN_SIZE_MAX_MB), true).await;
+                        } else if limit > 0 && min_size_mb == 0 && !videos {
+                            reply(&command, &context, "Please choose a minimum size, videos, or both".to_string(), true).await;
                         } else {
+                            let rule = if limit > 0 {
+                                Some(HeavyRule { limit: limit as usize, min_size: min_size_mb as u64 * 1024 * 1024, videos })
+                            } else {
+                                None
+                            };
+                            defer(&command, &context, true).await;
+                            self.send_command(Command::SetHeavyRule { rule, context, interaction: command }).await;
+                        }
+                    }
+                }
+                "dedupe" => match commands::dedupe::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose true or false".to_string(), true).await,
+                    Ok(enabled) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::SetDeleteDuplicates { enabled, context, interaction: command }).await;
+                    }
+                }
+                "keeplatest" => match commands::keeplatest::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose true or false".to_string(), true).await,
+                    Ok(enabled) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::SetAlwaysKeepLatest { enabled, context, interaction: command }).await;
+                    }
+                }
+                "tombstone" => match commands::tombstone::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose true or false".to_string(), true).await,
+                    Ok(enabled) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::SetTombstone { enabled, context, interaction: command }).await;
+                    }
+                }
+                "threads" => match commands::threads::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose true or false".to_string(), true).await,
+                    Ok(delete) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::SetDeleteMessagesWithThreads { delete, context, interaction: command }).await;
+                    }
+                }
+                "systemmessages" => match commands::systemmessages::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose a valid policy".to_string(), true).await,
+                    Ok(policy) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::SetSystemMessagePolicy { policy, context, interaction: command }).await;
+                    }
+                }
+                "retention" => match commands::retention::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose which messages to keep".to_string(), true).await,
+                    Ok(direction) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::SetRetentionDirection { direction, context, interaction: command }).await;
+                    }
+                }
+                "ignore-types" => match (commands::ignoretypes::run(&command.data.options), command.guild_id) {
+                    (_, None) => reply(&command, &context, "This command can only be used in a server".to_string(), true).await,
+                    (Err(why), _) => reply(&command, &context, why, true).await,
+                    (Ok(types), Some(guild_id)) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::SetIgnoredTypes { guild_id, types, context, interaction: command }).await;
+                    }
+                }
+                "settimezone" => match (commands::settimezone::run(&command.data.options), command.guild_id) {
+                    (_, None) => reply(&command, &context, "This command can only be used in a server".to_string(), true).await,
+                    (Err(why), _) => reply(&command, &context, why, true).await,
+                    (Ok(timezone), Some(guild_id)) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::SetTimezone { guild_id, timezone, context, interaction: command }).await;
+                    }
+                }
+                "verbosity" => match (commands::verbosity::run(&command.data.options), command.guild_id) {
+                    (_, None) => reply(&command, &context, "This command can only be used in a server".to_string(), true).await,
+                    (Err(_), _) => reply(&command, &context, "Please choose a valid level".to_string(), true).await,
+                    (Ok(verbosity), Some(guild_id)) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::SetVerbosity { guild_id, verbosity, context, interaction: command }).await;
+                    }
+                }
+                "resetstats" => {
+                    if !is_admin(command.member.as_ref()) {
+                        reply(&command, &context, "Only server administrators can use this command".to_string(), true).await;
+                    } else {
+                        let all = commands::resetstats::run(&command.data.options);
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::ResetStats { all, context, interaction: command }).await;
+                    }
+                }
+                "leaderboard" => {
+                    let (metric, count) = commands::leaderboard::run(&command.data.options);
+                    let count = count.unwrap_or(LEADERBOARD_DEFAULT);
+                    if !(1..=LEADERBOARD_MAX).contains(&count) {
+                        reply(&command, &context, format!("The number of channels should be between 1 and {}", LEADERBOARD_MAX), true).await;
+                    } else {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::Leaderboard { metric, count: count as usize, context, interaction: command }).await;
+                    }
+                }
+                "recent-deletes" => {
+                    let (count, channel) = commands::recentdeletes::run(&command.data.options);
+                    let count = count.unwrap_or(RECENT_DELETES_DEFAULT);
+                    if !is_admin(command.member.as_ref()) {
+                        reply(&command, &context, "Only server administrators can use this command".to_string(), true).await;
+                    } else if !(1..=RECENT_DELETES_MAX).contains(&count) {
+                        reply(&command, &context, format!("The number of messages should be between 1 and {}", RECENT_DELETES_MAX), true).await;
+                    } else {
+                        let channel = channel.unwrap_or(command.channel_id);
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::RecentDeletes { channel, count: count as usize, context, interaction: command }).await;
+                    }
+                }
+                "audit" => match commands::audit::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose a member".to_string(), true).await,
+                    Ok((user_id, page)) => {
+                        if !is_admin(command.member.as_ref()) {
+                            reply(&command, &context, "Only server administrators can use this command".to_string(), true).await;
+                        } else if page.is_some_and(|page| page < 1) {
+                            reply(&command, &context, "Pages start at 1".to_string(), true).await;
+                        } else {
+                            defer(&command, &context, true).await;
+                            self.send_command(Command::AuditByUser { user_id, page: page.unwrap_or(1) as usize, context, interaction: command }).await;
+                        }
+                    }
+                }
+                "removeall" => {
+                    if command.guild_id.is_none() {
+                        reply(&command, &context, "This command can only be used in a server".to_string(), true).await;
+                    } else if !is_admin(command.member.as_ref()) {
+                        reply(&command, &context, "Only server administrators can use this command".to_string(), true).await;
+                    } else if let Err(why) = command
+                        .create_interaction_response(&context.http, |response| {
+                            response
+                                .kind(InteractionResponseType::ChannelMessageWithSource)
+                                .interaction_response_data(|message| message
+                                    .content("This will stop autodeleting every channel in this server. Are you sure?")
+                                    .ephemeral(true)
+                                    .components(|components| components.create_action_row(|row| row.create_button(|button| button
+                                        .custom_id(REMOVE_ALL_CONFIRM_ID)
+                                        .label("Remove all")
+                                        .style(ButtonStyle::Danger)))))
+                        })
+                        .await
+                    {
+                        warn!("Cannot respond to slash command: {}", why);
+                    }
+                }
+                "set-multiple" => match commands::setmultiple::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose a valid number and mention at least one channel".to_string(), true).await,
+                    Ok((limit, channels)) => {
+                        if !is_valid_limit(limit) {
+                            reply(&command, &context, format!("The limit should be {} or between {} and {}", EPHEMERAL_LIMIT, QUEUE_LIMIT_MIN, QUEUE_LIMIT_MAX), true).await;
+                        } else if channels.len() > SET_MULTIPLE_MAX_CHANNELS {
+                            reply(&command, &context, format!("Please mention at most {} channels at once", SET_MULTIPLE_MAX_CHANNELS), true).await;
+                        } else {
+                            defer(&command, &context, true).await;
+                            self.send_command(Command::SetMultipleLimits { limit: limit as usize, channels, context, interaction: command }).await;
+                        }
+                    }
+                }
+                "copyconfig" => match commands::copyconfig::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose the channels to copy from and to".to_string(), true).await,
+                    Ok((source, target)) if source == target => reply(&command, &context, "Please choose two different channels".to_string(), true).await,
+                    Ok((source, target)) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::CopyConfig { source, target, context, interaction: command }).await;
+                    }
+                }
+                "idleunmanage" => match (commands::idleunmanage::run(&command.data.options), command.guild_id) {
+                    (_, None) => reply(&command, &context, "This command can only be used in a server".to_string(), true).await,
+                    (Err(_), _) => reply(&command, &context, "Please choose a valid number".to_string(), true).await,
+                    (Ok(days), Some(guild_id)) => {
+                        if (0..=IDLE_UNMANAGE_MAX_DAYS).contains(&days) {
+                            defer(&command, &context, true).await;
+                            self.send_command(Command::SetIdleUnmanage { guild_id, days: days as u64, context, interaction: command }).await;
+                        } else {
+                            reply(&command, &context, format!("The number of days should be between 0 and {}", IDLE_UNMANAGE_MAX_DAYS), true).await;
+                        }
+                    }
+                }
+                "announce" => match (commands::announce::run(&command.data.options), command.guild_id) {
+                    (_, None) => reply(&command, &context, "This command can only be used in a server".to_string(), true).await,
+                    (Err(_), _) => reply(&command, &context, "Please choose true or false".to_string(), true).await,
+                    (Ok(enabled), Some(guild_id)) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::SetAnnounceChanges { guild_id, enabled, context, interaction: command }).await;
+                    }
+                }
+                "confirm-important" => match (commands::confirmimportant::run(&command.data.options), command.guild_id) {
+                    (_, None) => reply(&command, &context, "This command can only be used in a server".to_string(), true).await,
+                    (Err(_), _) => reply(&command, &context, "Please choose true or false".to_string(), true).await,
+                    (Ok(enabled), Some(guild_id)) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::SetConfirmImportant { guild_id, enabled, context, interaction: command }).await;
+                    }
+                }
+                "deletion-reason" => match command.guild_id {
+                    None => reply(&command, &context, "This command can only be used in a server".to_string(), true).await,
+                    Some(guild_id) => {
+                        let reason = commands::deletionreason::run(&command.data.options);
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::SetDeletionReason { guild_id, reason, context, interaction: command }).await;
+                    }
+                }
+                "purge-on-ban" => match (commands::purgeonban::run(&command.data.options), command.guild_id) {
+                    (_, None) => reply(&command, &context, "This command can only be used in a server".to_string(), true).await,
+                    (Err(_), _) => reply(&command, &context, "Please choose true or false".to_string(), true).await,
+                    (Ok(enabled), Some(guild_id)) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::SetPurgeOnBan { guild_id, enabled, context, interaction: command }).await;
+                    }
+                }
+                "fullness-warning" => match (commands::fullnesswarning::run(&command.data.options), command.guild_id) {
+                    (_, None) => reply(&command, &context, "This command can only be used in a server".to_string(), true).await,
+                    (Err(_), _) => reply(&command, &context, "Please choose a valid percentage".to_string(), true).await,
+                    (Ok((percent, _)), _) if !(0..=100).contains(&percent) => reply(&command, &context, "The percentage should be between 0 and 100".to_string(), true).await,
+                    (Ok((percent, None)), _) if percent > 0 => reply(&command, &context, "Please choose a channel to post the notices in".to_string(), true).await,
+                    (Ok((percent, log_channel)), Some(guild_id)) => {
+                        let warning = log_channel.filter(|_| percent > 0).map(|log_channel| FullnessWarning { log_channel, threshold: percent as u8 });
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::SetFullnessWarning { guild_id, warning, context, interaction: command }).await;
+                    }
+                }
+                "keepoldest" => match commands::keepoldest::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose a valid number".to_string(), true).await,
+                    Ok(count) => {
+                        if (0..=KEEP_OLDEST_MAX).contains(&count) {
+                            defer(&command, &context, true).await;
+                            self.send_command(Command::SetKeepOldest { count: count as usize, context, interaction: command }).await;
+                        } else {
+                            reply(&command, &context, format!("The number of protected messages should be between 0 and {}", KEEP_OLDEST_MAX), true).await;
+                        }
+                    }
+                }
+                "tempraise" => match commands::tempraise::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose a valid limit and duration".to_string(), true).await,
+                    Ok((limit, minutes)) => {
+                        if !(QUEUE_LIMIT_MIN..=QUEUE_LIMIT_MAX).contains(&limit) {
                             reply(&command, &context, format!("The limit should be between {} and {}", QUEUE_LIMIT_MIN, QUEUE_LIMIT_MAX), true).await;
+                        } else if !(1..=TEMPRAISE_MAX_MINUTES).contains(&minutes) {
+                            reply(&command, &context, format!("The duration should be between 1 and {} minutes", TEMPRAISE_MAX_MINUTES), true).await;
+                        } else {
+                            defer(&command, &context, true).await;
+                            self.send_command(Command::TempRaiseLimit { limit: limit as usize, minutes, context, interaction: command }).await;
+                        }
+                    }
+                }
+                "snooze" => match commands::snooze::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose a valid duration, such as 30m, 2h or 1d".to_string(), true).await,
+                    Ok(Some(duration)) if duration.is_zero() || duration > SNOOZE_MAX => {
+                        reply(&command, &context, format!("The duration should be between 1 minute and {} days", SNOOZE_MAX.as_secs() / 86400), true).await;
+                    }
+                    Ok(duration) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::Snooze { duration, context, interaction: command }).await;
+                    }
+                }
+                "autoconfig-pattern" => match (commands::autoconfig::run(&command.data.options), command.guild_id) {
+                    (_, None) => reply(&command, &context, "This command can only be used in a server".to_string(), true).await,
+                    (Err(_), _) => reply(&command, &context, "Please choose a valid pattern and number".to_string(), true).await,
+                    (Ok((pattern, limit)), Some(guild_id)) => {
+                        if limit == 0 || (QUEUE_LIMIT_MIN..=QUEUE_LIMIT_MAX).contains(&limit) {
+                            defer(&command, &context, true).await;
+                            self.send_command(Command::SetAutoconfigPattern { guild_id, pattern, limit: limit as usize, context, interaction: command }).await;
+                        } else {
+                            reply(&command, &context, format!("The limit should be 0 or between {} and {}", QUEUE_LIMIT_MIN, QUEUE_LIMIT_MAX), true).await;
+                        }
+                    }
+                }
+                "blockword" => match commands::blockword::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose a valid action and keyword".to_string(), true).await,
+                    Ok(action) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::UpdateBlockedKeywords { action, context, interaction: command }).await;
+                    }
+                }
+                "keep" | "unkeep" => match commands::keep::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please give a valid message link or ID".to_string(), true).await,
+                    // Links to other servers' messages are refused before anything is looked up
+                    Ok((Some((guild_id, _)), _)) if Some(guild_id) != command.guild_id => reply(&command, &context, "This message is not from this server".to_string(), true).await,
+                    Ok((location, message)) => {
+                        let channel = location.map_or(command.channel_id, |(_, channel)| channel);
+                        let keep = command.data.name == "keep";
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::KeepMessage { channel, message, keep, context, interaction: command }).await;
+                    }
+                }
+                "protectrole" => match commands::protectrole::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose a valid action and role".to_string(), true).await,
+                    Ok(action) => {
+                        defer(&command, &context, true).await;
+                        self.send_command(Command::UpdateProtectedRoles { action, context, interaction: command }).await;
+                    }
+                }
+                "trim" => match commands::trim::run(&command.data.options) {
+                    Err(_) => reply(&command, &context, "Please choose a valid number".to_string(), true).await,
+                    Ok(count) => {
+                        if (QUEUE_LIMIT_MIN..=QUEUE_LIMIT_MAX).contains(&count) {
+                            defer(&command, &context, true).await;
+                            self.send_command(Command::Trim { count: count as usize, context, interaction: command }).await;
+                        } else {
+                            reply(&command, &context, format!("The count should be between {} and {}", QUEUE_LIMIT_MIN, QUEUE_LIMIT_MAX), true).await;
                         }
                     }
                 }
                 "remove" => {
+                    let channel = commands::remove::run(&command.data.options).unwrap_or(command.channel_id);
                     defer(&command, &context, true).await;
-                    if let Err(why) = self.sender.send(Command::RemoveLimit { context, interaction: command }).await {
-                        error!("Error during sendcommand {}", why);
-                        exit(1);
-                    }
+                    self.send_command(Command::RemoveLimit { channel, context, interaction: command }).await;
                 }
-                "status" => {
+                "version" => {
                     defer(&command, &context, true).await;
-                    if let Err(why) = self.sender.send(Command::GetStatus { context, interaction: command }).await {
-                        error!("Error during sendcommand {}", why);
-                        exit(1);
+                    self.send_command(Command::GetVersion { uptime: self.started_at.elapsed(), context, interaction: command }).await;
+                }
+                "status" => {
+                    let (text, public) = commands::getstatus::run(&command.data.options);
+                    // Interactions carry the member's permissions in the channel
+                    let can_post = command.member.as_ref().and_then(|member| member.permissions)
+                        .is_some_and(|permissions| permissions.send_messages() && (text || permissions.embed_links()));
+                    if public && !can_post {
+                        reply(&command, &context, "You need permission to post messages (and embeds) here to share the status publicly".to_string(), true).await;
+                    } else {
+                        defer(&command, &context, !public).await;
+                        self.send_command(Command::GetStatus { text, public, context, interaction: command }).await;
+                    }
+                }
+                "help" => {
+                    let is_owner = is_owner(&context, command.user.id).await;
+                    let mut pages = commands::help::run(is_admin(command.member.as_ref()), is_owner, self.killswitch_enabled).into_iter();
+                    if let Some(first) = pages.next() {
+                        reply(&command, &context, first, true).await;
                     }
+                    for page in pages {
+                        if let Err(why) = command.create_followup_message(&context, |response| response.content(page).ephemeral(true)).await {
+                            warn!("Cannot send help follow-up: {}", why);
+                        }
+                    }
+                }
+                "killswitch" if !self.killswitch_enabled => {
+                    warn!("User {} ran the killswitch, which is disabled", command.user.id);
+                    reply(&command, &context, "The killswitch is disabled on this bot".to_string(), true).await;
                 }
                 "killswitch" => {
-                    error!("User {} flipped the killswitch!", command.user.id);
-                    reply(&command, &context, "Killswitch flipped, bye bye~".to_string(), true).await;
-                    exit(1)
+                    // The killswitch only fires when the same user runs it twice within the confirmation window
+                    let confirmed = {
+                        let mut armed = self.killswitch_armed.lock().await;
+                        match armed.remove(&command.user.id) {
+                            Some(armed_at) if armed_at.elapsed() < KILLSWITCH_CONFIRMATION_WINDOW => true,
+                            _ => {
+                                armed.insert(command.user.id, Instant::now());
+                                false
+                            }
+                        }
+                    };
+                    if confirmed {
+                        error!("User {} flipped the killswitch!", command.user.id);
+                        reply(&command, &context, "Killswitch flipped, bye bye~".to_string(), true).await;
+                        flush(&self.sender).await;
+                        exit(1)
+                    } else {
+                        warn!("User {} armed the killswitch", command.user.id);
+                        reply(&command, &context, format!("Are you sure? Run /killswitch again within {} seconds to confirm.", KILLSWITCH_CONFIRMATION_WINDOW.as_secs()), true).await;
+                    }
                 }
                 _ => reply(&command, &context, "not implemented :(".to_string(), true).await
             };
+        } else if let Interaction::MessageComponent(component) = interaction {
+            info!("Received {} button from {} ({}) in {}", component.data.custom_id, component.user.name, component.user.id, component.channel_id);
+            match (component.data.custom_id.as_str(), component.guild_id) {
+                (REMOVE_ALL_CONFIRM_ID, Some(guild_id)) if is_admin(component.member.as_ref()) => {
+                    if let Err(why) = component
+                        .create_interaction_response(&context.http, |response| response.kind(InteractionResponseType::DeferredUpdateMessage))
+                        .await
+                    {
+                        warn!("Cannot defer button: {}", why);
+                    }
+                    self.send_command(Command::RemoveAllLimits { guild_id, context, interaction: component }).await;
+                }
+                (custom_id, Some(_)) if commands::configure::parse_confirm_id(custom_id).is_some() => {
+                    let (channel, limit, byte_budget) = commands::configure::parse_confirm_id(custom_id).expect("Checked above");
+                    if let Err(why) = component
+                        .create_interaction_response(&context.http, |response| response.kind(InteractionResponseType::DeferredUpdateMessage))
+                        .await
+                    {
+                        warn!("Cannot defer button: {}", why);
+                    }
+                    self.send_command(Command::ConfirmSetLimit { channel, limit, byte_budget, context, interaction: component }).await;
+                }
+                _ => debug!("Ignoring button {}", component.data.custom_id),
+            }
+        } else if let Interaction::Autocomplete(autocomplete) = interaction {
+            match (autocomplete.data.name.as_str(), commands::setmultiple::autocomplete_input(&autocomplete.data.options)) {
+                ("set-multiple", Some(input)) => self.send_command(Command::AutocompleteChannels { input, guild_id: autocomplete.guild_id, context, interaction: autocomplete }).await,
+                _ => debug!("Ignoring autocomplete for /{}", autocomplete.data.name),
+            }
         }
     }
 
     async fn ready(&self, ctx: Context, ready: Ready) {
-        info!("{} is connected!", ready.user.name);
+        info!("{} is connected! (shard {:?})", ready.user.name, ready.shard);
+
+        // Every shard gets its own ready event, but commands and the manager are global,
+        // so only the first shard takes care of them
+        if ready.shard.is_some_and(|[shard_id, _]| shard_id != 0) {
+            return;
+        }
 
         // self.queue_manager.init(&ctx).await;
 
-        let guild_id = GuildId(
-            env::var("GUILD_ID")
-                .expect("Expected GUILD_ID in environment")
-                .parse()
-                .expect("GUILD_ID must be an integer"),
-        );
+        let guild_id = self.guild_id;
+        let desired_commands = commands::desired_commands(self.killswitch_enabled);
+        let unchanged = match guild_id.get_application_commands(&ctx.http).await {
+            Ok(existing_commands) => !commands::commands_changed(&desired_commands, &existing_commands),
+            Err(error) => {
+                warn!("Cannot fetch existing commands: {}", error);
+                false
+            }
+        };
 
-        let commands = GuildId::set_application_commands(&guild_id, &ctx.http, |commands| {
-            commands
-                .create_application_command(|command| commands::configure::register(command))
-                .create_application_command(|command| commands::remove::register(command))
-                .create_application_command(|command| commands::killswitch::register(command))
-                .create_application_command(|command| commands::getstatus::register(command))
-        })
-        .await;
+        if unchanged {
+            info!("Commands unchanged, skipping registration");
+        } else {
+            let commands = GuildId::set_application_commands(&guild_id, &ctx.http, |commands| {
+                commands.set_application_commands(desired_commands)
+            })
+            .await;
 
-        match commands {
-            Ok(_) => debug!("Guild commands created"),
-            Err(error) => error!("Error while creating commands: {}", error)
+            match commands {
+                Ok(_) => debug!("Guild commands created"),
+                Err(error) => error!("Error while creating commands: {}", error)
+            }
         }
 
         debug!("Initializing message manager");
-        if let Err(why) = self.sender.send(Command::Initialize { context: ctx }).await {
+        // Discord's GATEWAY_MESSAGE_CONTENT(_LIMITED) flags, which serenity defines one bit off
+        let message_content_available = ready.application.flags.bits() & (1 << 18 | 1 << 19) != 0;
+        if !message_content_available {
+            warn!("The message content intent isn't enabled, byte budgets fall back to message counts");
+        }
+        if let Err(why) = self.sender.send(Command::Initialize { context: ctx, message_content_available }).await {
             error!("Error during sendcommand {}", why);
         }
 
@@ -184,20 +839,193 @@ impl EventHandler for Bot {
     }
 }
 
+/// Reads an environment variable, falling back to `default` when it is not set
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    match env::var(name) {
+        Ok(value) => value.parse().unwrap_or_else(|_| panic!("{} has an invalid value: {}", name, value)),
+        Err(_) => default,
+    }
+}
+
+/// Exponential backoff for the given (1-based) attempt, capped at `max` and with up to 50% of random jitter added
+fn start_backoff(attempt: u32, initial: Duration, max: Duration) -> Duration {
+    let backoff = initial.saturating_mul(2u32.saturating_pow(attempt - 1)).min(max);
+    // No need for a proper RNG, the clock's sub-second part is random enough to spread out retries
+    let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |now| now.subsec_nanos());
+    let jitter = backoff.mul_f64(nanos as f64 / 1_000_000_000.0 / 2.0);
+    backoff + jitter
+}
+
+/// Has the manager persist what it only holds in memory, before exiting
+async fn flush(sender: &Sender<Command>) {
+    let (done, flushed) = oneshot::channel();
+    if sender.send(Command::Flush { done }).await.is_err() {
+        return;
+    }
+    match tokio::time::timeout(EXIT_FLUSH_TIMEOUT, flushed).await {
+        Ok(_) => info!("Persisted the queues before exiting"),
+        Err(_) => warn!("Timed out persisting the queues before exiting"),
+    }
+}
+
+/// Sends a command to the message manager every `period`
+fn spawn_ticker(sender: Sender<Command>, period: Duration, command: fn() -> Command) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            if let Err(why) = sender.send(command()).await {
+                error!("Error during sendcommand {}", why);
+                break;
+            }
+        }
+    });
+}
+
 #[tokio::main]
 async fn main() {
     // Load .env file
     dotenv().ok();
     env_logger::init();
     info!("start main");
+    let started_at = Instant::now();
 
     // Configure the client with your Discord bot token in the environment.
     let token = env::var("DISCORD_TOKEN").expect("Expected a token in the environment");
-    let (sender, receiver) = mpsc::channel::<Command>(32);
+    // Checked before connecting, so a bad value doesn't take down an already connected client
+    let guild_id = match env::var("GUILD_ID").map(|value| value.parse::<u64>()) {
+        Ok(Ok(guild_id)) => GuildId(guild_id),
+        Ok(Err(_)) => {
+            error!("GUILD_ID must be an integer");
+            exit(1);
+        }
+        Err(_) => {
+            error!("Expected GUILD_ID in environment");
+            exit(1);
+        }
+    };
+    // A channel can't hold 0 commands
+    let command_queue_capacity = env_or("COMMAND_QUEUE_CAPACITY", DEFAULT_COMMAND_QUEUE_CAPACITY).max(1);
+    let (sender, receiver) = mpsc::channel::<Command>(command_queue_capacity);
 
-    let msgman = MessageManagerReceiver { };
-    msgman.run(receiver);
-    let bot = Bot {sender};
+    let limit_cooldown = env_or("LIMIT_COOLDOWN_SECS", DEFAULT_LIMIT_COOLDOWN_SECS);
+    // 0 lets Discord recommend how many shards to use
+    let shard_count = env_or("SHARD_COUNT", DEFAULT_SHARD_COUNT);
+    // Without it, the bot keeps running (without persisting anything) when the database is unavailable
+    let require_database = env_or("REQUIRE_DATABASE", true);
+    // The database lives in `database/` under BOT_DATA_DIR (the working directory by default)
+    let data_dir = env::var("BOT_DATA_DIR").map(PathBuf::from).unwrap_or_else(|_| PathBuf::from("."));
+    let database_dir = data_dir.join("database");
+    // SQLite creates the database file if needed, but not its directory
+    if let Err(why) = fs::create_dir_all(&database_dir) {
+        error!("Cannot create database directory {}: {}", database_dir.display(), why);
+        if require_database {
+            exit(1);
+        }
+    }
+    // Long purges outlive their /configure command, in which case the summary is sent by DM instead
+    let purge_summary_dm = env_or("PURGE_SUMMARY_DM", true);
+    // Caps how many messages each channel deletes per minute, so floods don't cause rate limit storms (0 for no cap)
+    let deletion_rate = env_or("DELETIONS_PER_MINUTE", 0);
+    // Limits of channels that were deleted (or that the bot was kicked from) are pruned on startup unless this is set
+    let keep_stale_channels = env_or("KEEP_STALE_CHANNELS", false);
+    // How often every managed channel is checked for the permission to delete messages (0 to only check on role updates)
+    let permission_check_interval = env_or("PERMISSION_CHECK_INTERVAL_SECS", DEFAULT_PERMISSION_CHECK_INTERVAL_SECS);
+    // Every audit fetches up to AUDIT_SAMPLE_SIZE of the oldest tracked messages per channel (0 for either disables audits)
+    let audit_interval = env_or("AUDIT_INTERVAL_SECS", DEFAULT_AUDIT_INTERVAL_SECS);
+    let audit_sample_size = env_or("AUDIT_SAMPLE_SIZE", DEFAULT_AUDIT_SAMPLE_SIZE);
+    // Logs the messages that would be deleted instead of deleting them, to try the bot out safely
+    let dry_run = env_or("DRY_RUN", false);
+    if dry_run {
+        warn!("!!! DRY_RUN is enabled: no message will actually be deleted");
+    }
+    // Legacy text commands (e.g. "!autodelete configure 100"), for servers where slash commands can't be used
+    let text_command_prefix = if env_or("TEXT_COMMANDS", false) {
+        Some(env::var("TEXT_COMMAND_PREFIX").unwrap_or_else(|_| DEFAULT_TEXT_COMMAND_PREFIX.to_string()))
+    } else {
+        None
+    };
+    // Posts in the guild's log channel when unpinning a message gets it deleted right away
+    let unpin_deletion_notices = env_or("UNPIN_DELETION_NOTICES", false);
+    // How many deletions can be in flight at once, away from the event loop
+    let delete_workers = env_or("DELETE_WORKERS", DEFAULT_DELETE_WORKERS).max(1);
+    // How many members' roles and permissions are kept around, 0 to resolve them every time
+    let member_cache_size = env_or("MEMBER_CACHE_SIZE", DEFAULT_MEMBER_CACHE_SIZE);
+    // Commands taking longer than this to handle are logged as warnings (0 to never warn)
+    let slow_command_threshold = Duration::from_millis(env_or("SLOW_COMMAND_WARN_MS", DEFAULT_SLOW_COMMAND_WARN_MS));
+    // Attachments of deleted messages are only recorded when an archive directory is set
+    let attachment_archive = env::var("ARCHIVE_DIR").ok().map(|dir| AttachmentArchive::new(
+        PathBuf::from(dir),
+        env_or("ARCHIVE_DOWNLOAD_ATTACHMENTS", false),
+        env_or("ARCHIVE_MAX_ATTACHMENT_MB", DEFAULT_ARCHIVE_MAX_ATTACHMENT_MB) * 1024 * 1024,
+    ));
+    // The database is only backed up when a backup directory is set, keeping the latest BACKUP_KEEP copies
+    let backups = env::var("BACKUP_DIR").ok().map(|dir| DatabaseBackups::new(PathBuf::from(dir), env_or("BACKUP_KEEP", DEFAULT_BACKUP_KEEP)));
+    let backup_interval = Duration::from_secs(env_or("BACKUP_INTERVAL_SECS", DEFAULT_BACKUP_INTERVAL_SECS).max(60));
+    // Channel limits to apply on startup, for declarative deployments (see `SeedConfig` for the format)
+    let seed_config = match env::var("CONFIG_FILE") {
+        Ok(path) => match SeedConfig::load(Path::new(&path)) {
+            Ok(seed_config) => Some(seed_config),
+            Err(why) => {
+                error!("Invalid CONFIG_FILE: {}", why);
+                exit(1);
+            }
+        },
+        Err(_) => None,
+    };
+    // Operators supervising the bot from outside may not want anyone to be able to stop it
+    let killswitch_enabled = env_or("ENABLE_KILLSWITCH", true);
+    if killswitch_enabled {
+        info!("The killswitch is enabled");
+    } else {
+        info!("The killswitch is disabled, /killswitch won't be registered");
+    }
+    // Shown in the audit log of servers that didn't choose their own with /deletion-reason (empty for none)
+    let default_deletion_reason = env::var("DELETION_REASON").unwrap_or_else(|_| DEFAULT_DELETION_REASON.to_string());
+    let deletion_reasons = DeletionReasons::new(Some(default_deletion_reason).filter(|reason| !reason.is_empty()));
+    // Statistics and deletion logs are kept in memory and written every so often (or once enough deletions pile up)
+    let persist_interval = Duration::from_secs(env_or("PERSIST_INTERVAL_SECS", DEFAULT_PERSIST_INTERVAL_SECS).max(1));
+    let persist_batch_size = env_or("PERSIST_BATCH_SIZE", DEFAULT_PERSIST_BATCH_SIZE);
+    let start_attempts = env_or("CLIENT_START_ATTEMPTS", DEFAULT_CLIENT_START_ATTEMPTS).max(1);
+    let start_backoff_initial = Duration::from_millis(env_or("CLIENT_START_BACKOFF_MS", DEFAULT_CLIENT_START_BACKOFF_MS));
+    let start_backoff_max = Duration::from_millis(env_or("CLIENT_START_MAX_BACKOFF_MS", DEFAULT_CLIENT_START_MAX_BACKOFF_MS));
+
+    // Changes to channel limits are only reported when a webhook is configured
+    let config_webhook = env::var("CONFIG_WEBHOOK_URL").ok()
+        .map(|url| ConfigWebhook::new(url, env::var("CONFIG_WEBHOOK_SECRET").ok()));
+
+    let msgman = MessageManagerReceiver { limit_cooldown: Duration::from_secs(limit_cooldown), config_webhook, require_database, purge_summary_dm, database_path: database_dir.join("database.sqlite"), deletion_rate, keep_stale_channels, seed_config, audit_sample_size, dry_run, delete_workers, unpin_deletion_notices, member_cache_size, attachment_archive, slow_command_threshold, persist_batch_size, deletion_reasons, backups: backups.clone() };
+    msgman.run(receiver, sender.clone());
+
+    // Periodically persist the queues so they can be restored after a restart
+    spawn_ticker(sender.clone(), persist_interval, || Command::PersistQueues);
+    if let Some(backups) = backups.as_ref() {
+        info!("Backing the database up every {:?} to {}", backup_interval, backups.dir().display());
+        spawn_ticker(sender.clone(), backup_interval, || Command::BackupDatabase);
+    }
+    spawn_ticker(sender.clone(), SCHEDULED_REVERT_CHECK_INTERVAL, || Command::ApplyScheduledReverts);
+    spawn_ticker(sender.clone(), IDLE_CHECK_INTERVAL, || Command::UnmanageIdleChannels);
+    spawn_ticker(sender.clone(), SNOOZE_CHECK_INTERVAL, || Command::ResumeSnoozed);
+    if permission_check_interval > 0 {
+        spawn_ticker(sender.clone(), Duration::from_secs(permission_check_interval), || Command::CheckPermissions);
+    }
+    if audit_interval > 0 && audit_sample_size > 0 {
+        spawn_ticker(sender.clone(), Duration::from_secs(audit_interval), || Command::AuditQueues);
+    }
+    if deletion_rate > 0 {
+        spawn_ticker(sender.clone(), DELETION_BACKLOG_INTERVAL, || Command::EvictBacklog);
+    }
+
+    let exit_sender = sender.clone();
+    // Stopping the bot (Ctrl+C, or a service manager's SIGTERM) persists the queues like the killswitch does
+    let signal_sender = sender.clone();
+    tokio::spawn(async move {
+        let signal = shutdown::stop_requested().await;
+        info!("Received signal {}, exiting", signal);
+        flush(&signal_sender).await;
+        exit(0)
+    });
+    let bot = Bot {sender, backpressure_events: AtomicUsize::new(0), killswitch_armed: Mutex::new(HashMap::new()), killswitch_enabled, guild_id, started_at, text_command_prefix};
 
     // Build our client.
     // let intents = 
@@ -211,11 +1039,36 @@ async fn main() {
         .await
         .expect("Error creating client");
 
-    // Finally, start a single shard, and start listening to events.
+    // Finally, start the shards, and start listening to events.
+    // Every shard feeds the same queue, which the message manager consumes one command at a time.
     //
-    // Shards will automatically attempt to reconnect, and will perform
-    // exponential backoff until it reconnects.
-    if let Err(why) = client.start().await {
-        error!("Client error: {:?}", why);
+    // Once connected, shards will automatically attempt to reconnect, and will perform
+    // exponential backoff until it reconnects. Failing to connect in the first place
+    // (e.g. a network blip at boot) is retried here.
+    let mut attempt = 1;
+    loop {
+        info!("Starting client with {} shards (attempt {}/{})", if shard_count == 0 { "automatic".to_string() } else { shard_count.to_string() }, attempt, start_attempts);
+        let started = match shard_count {
+            0 => client.start_autosharded().await,
+            1 => client.start().await,
+            shard_count => client.start_shards(shard_count).await,
+        };
+        match started {
+            Ok(()) => {
+                info!("Client stopped");
+                flush(&exit_sender).await;
+                break;
+            },
+            Err(why) if attempt < start_attempts => {
+                let backoff = start_backoff(attempt, start_backoff_initial, start_backoff_max);
+                warn!("Client error: {:?}, retrying in {:?}", why, backoff);
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+            }
+            Err(why) => {
+                error!("Client error: {:?}, giving up after {} attempts", why, attempt);
+                exit(1);
+            }
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serenity::model::prelude::{GuildId, RoleId, UserId};
+use serenity::prelude::*;
+
+// Role changes are also invalidated by events, this only bounds how stale a missed one can get
+const MEMBER_CACHE_TTL: Duration = Duration::from_secs(60);
+
+struct CachedMember {
+    roles: Vec<RoleId>,
+    resolved_at: Instant,
+    last_used: u64,
+}
+
+/// Remembers the roles of recently seen members, so features checking them on every message don't each
+/// resolve them again. Holds at most `capacity` members, evicting the least recently used one;
+/// a capacity of 0 resolves every lookup.
+#[derive(Default)]
+pub struct MemberCache {
+    capacity: usize,
+    members: HashMap<(GuildId, UserId), CachedMember>,
+    // Bumped on every lookup, to tell which member was used least recently
+    uses: u64,
+}
+
+impl MemberCache {
+    pub fn new(capacity: usize) -> Self {
+        MemberCache { capacity, members: HashMap::with_capacity(capacity), uses: 0 }
+    }
+
+    /// Looks the member up, resolving it from the serenity cache when it isn't cached or is stale.
+    /// Returns `None` when the member (or their guild) isn't in the serenity cache either.
+    pub fn roles(&mut self, ctx: &Context, guild_id: GuildId, user_id: UserId) -> Option<Vec<RoleId>> {
+        self.uses += 1;
+        if let Some(cached) = self.members.get_mut(&(guild_id, user_id)) {
+            if cached.resolved_at.elapsed() < MEMBER_CACHE_TTL {
+                cached.last_used = self.uses;
+                return Some(cached.roles.clone());
+            }
+        }
+
+        let roles = ctx.cache.member(guild_id, user_id)?.roles;
+        if self.capacity == 0 {
+            return Some(roles);
+        }
+        if self.members.len() >= self.capacity && !self.members.contains_key(&(guild_id, user_id)) {
+            // Linear, but the cache is small and only full caches pay for it
+            if let Some(least_used) = self.members.iter().min_by_key(|(_, cached)| cached.last_used).map(|(key, _)| *key) {
+                self.members.remove(&least_used);
+            }
+        }
+        self.members.insert((guild_id, user_id), CachedMember { roles: roles.clone(), resolved_at: Instant::now(), last_used: self.uses });
+        Some(roles)
+    }
+
+    /// Forgets a member whose roles changed
+    pub fn invalidate_member(&mut self, guild_id: GuildId, user_id: UserId) {
+        self.members.remove(&(guild_id, user_id));
+    }
+
+    /// Forgets every member of a guild when one of its roles changed, as any of them may hold it
+    pub fn invalidate_guild(&mut self, guild_id: GuildId) {
+        self.members.retain(|(guild, _), _| *guild != guild_id);
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+}
@@ -1,27 +1,110 @@
 
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use chrono::Utc;
+use chrono::{FixedOffset, TimeZone, Utc};
 use serenity::model::prelude::application_command::ApplicationCommandInteraction;
-use serenity::model::prelude::{Message, ChannelId, UserId, MessageId, GuildId, MessageType};
-use serenity::futures::StreamExt;
+use serenity::model::prelude::message_component::MessageComponentInteraction;
+use serenity::model::prelude::autocomplete::AutocompleteInteraction;
+use serenity::model::prelude::component::ButtonStyle;
+use serenity::model::prelude::{Message, ChannelId, UserId, MessageId, GuildId, RoleId, MessageType, GuildChannel, ChannelType, Channel, ReactionType};
+use serenity::model::Timestamp;
+use serenity::builder::CreateEmbed;
+use serenity::futures::{Stream, StreamExt};
 use serenity::prelude::*;
-use sqlx::{Pool, Sqlite, FromRow};
+use serenity::utils::Colour;
+use sqlx::{Pool, Sqlite, FromRow, Transaction};
+use sqlx::migrate::MigrateError;
+use sqlx::sqlite::{SqliteJournalMode, SqliteQueryResult};
 use string_builder::Builder;
-use tokio::sync::mpsc::Receiver;
+use tokio::sync::mpsc::{self, Receiver, Sender, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
 use log::{debug, error, warn, info};
 
+use crate::archive::{AttachmentArchive, AttachmentInfo};
+use crate::auditlog::{self, DeletionReasons};
+use crate::backup::DatabaseBackups;
+use crate::commands::configure;
+use crate::commands::setmultiple::parse_channels;
+use crate::commands::text::TextCommand;
+use crate::membercache::MemberCache;
+use crate::seed::SeedConfig;
+use crate::webhook::ConfigWebhook;
+use crate::{QUEUE_LIMIT_MIN, QUEUE_LIMIT_MAX};
+
+// Discord's pin cap; only a capacity hint, channels with more pins are still reconciled correctly
 const CHANNEL_PIN_LIMIT: usize = 50;
+// Embeds hold at most 25 fields, and a message at most 6000 characters across its embeds
+const STATUS_FIELDS_PER_EMBED: usize = 25;
+const STATUS_EMBEDS_PER_MESSAGE: usize = 3;
+const CHANNEL_NAME_TTL: Duration = Duration::from_secs(300);
+// Discord messages hold at most 2000 characters
+const MESSAGE_LENGTH_LIMIT: usize = 2000;
+const DEBUG_QUEUE_SAMPLES: usize = 5;
+// Short enough that a page always fits in one message
+const AUDIT_PAGE_SIZE: usize = 15;
+// How many deletions each channel's deletion log keeps
+const DELETION_LOG_RETENTION: usize = 50;
+// Discord accepts at most 25 autocomplete choices, with values of at most 100 characters
+const AUTOCOMPLETE_MAX_CHOICES: usize = 25;
+const AUTOCOMPLETE_VALUE_MAX_LENGTH: usize = 100;
+const PINS_CACHE_TTL: Duration = Duration::from_secs(5);
+// How long a pin change already applied locally waits for its channel pins update event
+const EXPECTED_PIN_CHANGE_TTL: Duration = Duration::from_secs(10);
+// What silent guilds get in place of a confirmation
+const SILENT_ACK: &str = "✅";
+// How long /testdelete leaves its message up, so it can be seen before it goes
+const TEST_DELETE_DELAY: Duration = Duration::from_secs(3);
+const DUPLICATE_WINDOW_SECS: i64 = 60;
+const HISTORY_WALK_TIMEOUT: Duration = Duration::from_secs(60);
+// Attempts at fetching each page of history, backing off a little longer each time
+const HISTORY_PAGE_ATTEMPTS: u32 = 4;
+const HISTORY_PAGE_RETRY_DELAY: Duration = Duration::from_secs(1);
+// Messages fetched per page of history, after each of which the walk lets other tasks run
+const HISTORY_PAGE_SIZE: usize = 100;
+const HISTORY_PROGRESS_INTERVAL: usize = 1000;
+// Discord bulk deletes between 2 and 100 messages, none of them older than two weeks
+// (a day of margin covers messages aging while they wait for a worker)
+const BULK_DELETE_MAX: usize = 100;
+const BULK_DELETE_MAX_AGE_SECS: i64 = 13 * 24 * 60 * 60;
+// Snowflakes count milliseconds since the start of 2015
+const DISCORD_EPOCH_MS: i64 = 1_420_070_400_000;
+// What our own expired messages are edited into, in channels that keep a record of them
+const TOMBSTONE: &str = "[expired]";
+const SLOW_FILL_WARNING_DAYS: f64 = 30.0;
+const DB_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+const DB_WRITE_ATTEMPTS: u32 = 3;
+const DB_RETRY_DELAY: Duration = Duration::from_millis(100);
+// A manager that keeps crashing is given up on, leaving the bot to tell users it is degraded
+const MANAGER_MAX_RESTARTS: u32 = 5;
+const MANAGER_RESTART_DELAY: Duration = Duration::from_secs(1);
+// A manager that ran this long before crashing was stable, so its crashes are counted anew
+const MANAGER_STABLE_PERIOD: Duration = Duration::from_secs(10 * 60);
 
 pub enum Command {
     Initialize {
         context: Context,
+        message_content_available: bool,
     },
     MessageReceived {
         context: Context,
         message: Message,
     },
+    TextCommandReceived {
+        command: TextCommand,
+        context: Context,
+        message: Message,
+    },
+    DeletionFinished {
+        message: TrackedMessage,
+        caller: &'static str,
+        result: serenity::Result<()>,
+    },
     MessageDeleted {
         context: Context,
         channel_id: ChannelId,
@@ -35,15 +118,84 @@ pub enum Command {
         guild_id: Option<GuildId>,
     },
     SetLimit {
+        channel: ChannelId,
+        limit: usize,
+        byte_budget: Option<usize>,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    AdjustLimit {
+        delta: i64,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetMultipleLimits {
         limit: usize,
+        channels: Vec<ChannelId>,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    AutocompleteChannels {
+        input: String,
+        guild_id: Option<GuildId>,
+        context: Context,
+        interaction: AutocompleteInteraction,
+    },
+    CopyConfig {
+        source: ChannelId,
+        target: ChannelId,
         context: Context,
         interaction: ApplicationCommandInteraction,
     },
+    ConfirmSetLimit {
+        channel: ChannelId,
+        limit: usize,
+        byte_budget: Option<usize>,
+        context: Context,
+        interaction: MessageComponentInteraction,
+    },
     RemoveLimit {
+        channel: ChannelId,
         context: Context,
         interaction: ApplicationCommandInteraction,
     },
+    RemoveAllLimits {
+        guild_id: GuildId,
+        context: Context,
+        interaction: MessageComponentInteraction,
+    },
     GetStatus {
+        text: bool,
+        // Replies are ephemeral unless asked otherwise
+        public: bool,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    GetVersion {
+        uptime: Duration,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    GetChannelInfo {
+        channel: ChannelId,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    TestDelete {
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    Reload {
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    DebugQueue {
+        channel: ChannelId,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    Trim {
+        count: usize,
         context: Context,
         interaction: ApplicationCommandInteraction,
     },
@@ -51,337 +203,5438 @@ pub enum Command {
         context: Context,
         channel: ChannelId,
     },
+    RefetchPins {
+        context: Context,
+        channel: ChannelId,
+    },
+    PersistQueues,
+    BackupDatabase,
+    // Persists right away, answering once done so the bot can exit without losing anything
+    Flush {
+        done: oneshot::Sender<()>,
+    },
+    SetChannelAccess {
+        channel: ChannelId,
+        access: ChannelAccess,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    UpdateBlockedKeywords {
+        action: KeywordAction,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    KeepMessage {
+        channel: ChannelId,
+        message: MessageId,
+        keep: bool,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    UpdateProtectedRoles {
+        action: RoleAction,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    TempRaiseLimit {
+        limit: usize,
+        minutes: i64,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    ApplyScheduledReverts,
+    Snooze {
+        duration: Option<Duration>,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    ResumeSnoozed,
+    CheckPermissions,
+    MemberUpdated {
+        guild_id: GuildId,
+        user_id: UserId,
+    },
+    RolesUpdated {
+        guild_id: GuildId,
+    },
+    AuditQueues,
+    // The outcome of a queue audit, checked outside of the manager
+    DropPhantoms {
+        phantoms: Vec<(ChannelId, Vec<MessageId>)>,
+    },
+    EvictBacklog,
+    UnmanageIdleChannels,
+    SetIdleUnmanage {
+        guild_id: GuildId,
+        days: u64,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetAnnounceChanges {
+        guild_id: GuildId,
+        enabled: bool,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetConfirmImportant {
+        guild_id: GuildId,
+        enabled: bool,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetPurgeOnBan {
+        guild_id: GuildId,
+        enabled: bool,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetDeletionReason {
+        guild_id: GuildId,
+        reason: Option<String>,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    UserBanned {
+        guild_id: GuildId,
+        user_id: UserId,
+        context: Context,
+    },
+    SetFullnessWarning {
+        guild_id: GuildId,
+        warning: Option<FullnessWarning>,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetKeepOldest {
+        count: usize,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetHeavyRule {
+        rule: Option<HeavyRule>,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetDeleteDuplicates {
+        enabled: bool,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetAlwaysKeepLatest {
+        enabled: bool,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetTombstone {
+        enabled: bool,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetPinsCountTowardLimit {
+        enabled: bool,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetIgnoredTypes {
+        guild_id: GuildId,
+        types: Vec<&'static str>,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetTimezone {
+        guild_id: GuildId,
+        timezone: FixedOffset,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetVerbosity {
+        guild_id: GuildId,
+        verbosity: Verbosity,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    Leaderboard {
+        metric: LeaderboardMetric,
+        count: usize,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    RecentDeletes {
+        channel: ChannelId,
+        count: usize,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    AuditByUser {
+        user_id: UserId,
+        page: usize,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    ResetStats {
+        all: bool,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetSystemMessagePolicy {
+        policy: SystemMessagePolicy,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetRetentionDirection {
+        direction: RetentionDirection,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    SetAutoconfigPattern {
+        guild_id: GuildId,
+        pattern: String,
+        limit: usize,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
+    ChannelChanged {
+        context: Context,
+        channel: GuildChannel,
+    },
+    ThreadCreated {
+        parent: ChannelId,
+        thread: ChannelId,
+    },
+    SetDeleteMessagesWithThreads {
+        delete: bool,
+        context: Context,
+        interaction: ApplicationCommandInteraction,
+    },
 }
 
-#[derive(Clone)]
-pub struct CappedQueue {
-    queue: VecDeque<Message>,
-    pins: VecDeque<Message>,
-    limit: usize,
+impl Command {
+    /// The variant's name, to tell commands apart in logs
+    fn name(&self) -> &'static str {
+        use Command::*;
+        match self {
+            Initialize { .. } => "Initialize",
+            MessageReceived { .. } => "MessageReceived",
+            TextCommandReceived { .. } => "TextCommandReceived",
+            DeletionFinished { .. } => "DeletionFinished",
+            MessageDeleted { .. } => "MessageDeleted",
+            MessagesDeleted { .. } => "MessagesDeleted",
+            SetLimit { .. } => "SetLimit",
+            AdjustLimit { .. } => "AdjustLimit",
+            SetMultipleLimits { .. } => "SetMultipleLimits",
+            AutocompleteChannels { .. } => "AutocompleteChannels",
+            CopyConfig { .. } => "CopyConfig",
+            RemoveLimit { .. } => "RemoveLimit",
+            RemoveAllLimits { .. } => "RemoveAllLimits",
+            GetStatus { .. } => "GetStatus",
+            GetVersion { .. } => "GetVersion",
+            GetChannelInfo { .. } => "GetChannelInfo",
+            TestDelete { .. } => "TestDelete",
+            Reload { .. } => "Reload",
+            DebugQueue { .. } => "DebugQueue",
+            Trim { .. } => "Trim",
+            ChannelPinsUpdated { .. } => "ChannelPinsUpdated",
+            RefetchPins { .. } => "RefetchPins",
+            PersistQueues => "PersistQueues",
+            BackupDatabase => "BackupDatabase",
+            Flush { .. } => "Flush",
+            SetChannelAccess { .. } => "SetChannelAccess",
+            UpdateBlockedKeywords { .. } => "UpdateBlockedKeywords",
+            KeepMessage { .. } => "KeepMessage",
+            UpdateProtectedRoles { .. } => "UpdateProtectedRoles",
+            TempRaiseLimit { .. } => "TempRaiseLimit",
+            ApplyScheduledReverts => "ApplyScheduledReverts",
+            Snooze { .. } => "Snooze",
+            ResumeSnoozed => "ResumeSnoozed",
+            CheckPermissions => "CheckPermissions",
+            MemberUpdated { .. } => "MemberUpdated",
+            RolesUpdated { .. } => "RolesUpdated",
+            AuditQueues => "AuditQueues",
+            DropPhantoms { .. } => "DropPhantoms",
+            EvictBacklog => "EvictBacklog",
+            UnmanageIdleChannels => "UnmanageIdleChannels",
+            SetIdleUnmanage { .. } => "SetIdleUnmanage",
+            SetAnnounceChanges { .. } => "SetAnnounceChanges",
+            SetConfirmImportant { .. } => "SetConfirmImportant",
+            SetPurgeOnBan { .. } => "SetPurgeOnBan",
+            SetDeletionReason { .. } => "SetDeletionReason",
+            UserBanned { .. } => "UserBanned",
+            ConfirmSetLimit { .. } => "ConfirmSetLimit",
+            SetFullnessWarning { .. } => "SetFullnessWarning",
+            SetKeepOldest { .. } => "SetKeepOldest",
+            SetHeavyRule { .. } => "SetHeavyRule",
+            SetDeleteDuplicates { .. } => "SetDeleteDuplicates",
+            SetAlwaysKeepLatest { .. } => "SetAlwaysKeepLatest",
+            SetTombstone { .. } => "SetTombstone",
+            SetPinsCountTowardLimit { .. } => "SetPinsCountTowardLimit",
+            SetIgnoredTypes { .. } => "SetIgnoredTypes",
+            SetTimezone { .. } => "SetTimezone",
+            SetVerbosity { .. } => "SetVerbosity",
+            Leaderboard { .. } => "Leaderboard",
+            RecentDeletes { .. } => "RecentDeletes",
+            AuditByUser { .. } => "AuditByUser",
+            ResetStats { .. } => "ResetStats",
+            SetSystemMessagePolicy { .. } => "SetSystemMessagePolicy",
+            SetRetentionDirection { .. } => "SetRetentionDirection",
+            SetAutoconfigPattern { .. } => "SetAutoconfigPattern",
+            ChannelChanged { .. } => "ChannelChanged",
+            ThreadCreated { .. } => "ThreadCreated",
+            SetDeleteMessagesWithThreads { .. } => "SetDeleteMessagesWithThreads",
+        }
+    }
+
+    /// The slash command a command answers, if any. Those are deferred before being sent to the manager.
+    pub fn interaction(&self) -> Option<(&Context, &ApplicationCommandInteraction)> {
+        use Command::*;
+        match self {
+            SetLimit { context, interaction, .. }
+            | AdjustLimit { context, interaction, .. }
+            | SetMultipleLimits { context, interaction, .. }
+            | CopyConfig { context, interaction, .. }
+            | RemoveLimit { context, interaction, .. }
+            | GetStatus { context, interaction, .. }
+            | GetVersion { context, interaction, .. }
+            | GetChannelInfo { context, interaction, .. }
+            | TestDelete { context, interaction, .. }
+            | Reload { context, interaction, .. }
+            | DebugQueue { context, interaction, .. }
+            | Trim { context, interaction, .. }
+            | SetChannelAccess { context, interaction, .. }
+            | UpdateBlockedKeywords { context, interaction, .. }
+            | KeepMessage { context, interaction, .. }
+            | UpdateProtectedRoles { context, interaction, .. }
+            | TempRaiseLimit { context, interaction, .. }
+            | Snooze { context, interaction, .. }
+            | SetIdleUnmanage { context, interaction, .. }
+            | SetAnnounceChanges { context, interaction, .. }
+            | SetConfirmImportant { context, interaction, .. }
+            | SetPurgeOnBan { context, interaction, .. }
+            | SetDeletionReason { context, interaction, .. }
+            | SetFullnessWarning { context, interaction, .. }
+            | SetKeepOldest { context, interaction, .. }
+            | SetHeavyRule { context, interaction, .. }
+            | SetDeleteDuplicates { context, interaction, .. }
+            | SetAlwaysKeepLatest { context, interaction, .. }
+            | SetTombstone { context, interaction, .. }
+            | SetPinsCountTowardLimit { context, interaction, .. }
+            | SetIgnoredTypes { context, interaction, .. }
+            | SetTimezone { context, interaction, .. }
+            | SetVerbosity { context, interaction, .. }
+            | Leaderboard { context, interaction, .. }
+            | RecentDeletes { context, interaction, .. }
+            | AuditByUser { context, interaction, .. }
+            | ResetStats { context, interaction, .. }
+            | SetSystemMessagePolicy { context, interaction, .. }
+            | SetRetentionDirection { context, interaction, .. }
+            | SetAutoconfigPattern { context, interaction, .. }
+            | SetDeleteMessagesWithThreads { context, interaction, .. } => Some((context, interaction)),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Default)]
-struct MessageManager {
-    initialized: bool,
-    channel_queues: HashMap<ChannelId, CappedQueue>,
-    database: Option<Pool<Sqlite>>,
+/// Changes to a channel's list of blocked keywords
+pub enum KeywordAction {
+    Add(String),
+    Remove(String),
+    List,
 }
 
-pub struct MessageManagerReceiver {}
+/// Changes to a channel's list of protected roles
+pub enum RoleAction {
+    Add(RoleId),
+    Remove(RoleId),
+    List,
+}
 
-#[derive(FromRow)]
-struct ChannelLimitDatabaseEntry {
-    channel_id: String,
-    channel_limit: u32
+/// Whether a channel is explicitly allowed or denied from being managed
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ChannelAccess {
+    Allow,
+    Deny,
+    Clear,
 }
 
-impl MessageManagerReceiver {
-    pub fn run(&self, mut receiver: Receiver<Command>) {
-        async fn reply_deferred(interaction:&ApplicationCommandInteraction, context: &Context, content: String, _ephemeral: bool) {
-            if let Err(why) = interaction
-            .create_followup_message(context, |response| {
-                response
-                .content(content)
-            }).await
-            {
-                warn!("Cannot respond to slash command: {}", why);
-            }
-        }
+/// The outcome of a successful change to a channel's limit
+pub enum OperationReport {
+    Created { channel: ChannelId, limit: usize, deleted: usize, catching_up: bool, slow_fill_note: &'static str },
+    Initialized { channel: ChannelId, limit: usize },
+    Unchanged { channel: ChannelId, limit: usize },
+    Increased { channel: ChannelId, old_limit: usize, new_limit: usize, slow_fill_note: &'static str },
+    Decreased { channel: ChannelId, old_limit: usize, new_limit: usize, deleted: usize },
+    Removed { channel: ChannelId, old_limit: usize },
+}
 
-        let _manager = tokio::spawn(async move {
-            let mut message_manager: MessageManager = MessageManager {..Default::default()};
-            
-            // Start receiving messages
-            while let Some(cmd) = receiver.recv().await {
-                use Command::*;
-                match cmd {
-                    Initialize { context } => {message_manager.init(&context).await;}
-                    MessageReceived { context, message } => {message_manager.insert_message(&context, message, true).await;},
-                    MessageDeleted { context, channel_id, message_id, guild_id: _ } => {message_manager.remove_message(&context, message_id, &channel_id);},
-                    SetLimit { limit, context, interaction } => 
-                        {
-                            let content = message_manager.update_limit(&context, &interaction.channel_id, limit, false, Some(interaction.user.id)).await;
-                            reply_deferred(&interaction, &context, content, true).await;
-                        },
-                    RemoveLimit { context, interaction } => 
-                        {
-                            let content = message_manager.remove_limit(&interaction.channel_id, interaction.user.id).await;
-                            reply_deferred(&interaction, &context, content, true).await;
-                        },
-                    GetStatus { context, interaction } =>
-                        {
-                            let content = message_manager.get_status();
-                            reply_deferred(&interaction, &context, content, true).await;
-                        },
-                    ChannelPinsUpdated { context, channel } => {message_manager.on_pins_updated(&context, channel).await;},
-                    MessagesDeleted { context, channel_id, message_ids, guild_id: _ } => {message_manager.remove_messages(&context, message_ids, &channel_id);},
-                }
-            }
-        });
+impl OperationReport {
+    /// How many messages were deleted while applying the change
+    pub fn deleted(&self) -> usize {
+        match self {
+            OperationReport::Created { deleted, .. } | OperationReport::Decreased { deleted, .. } => *deleted,
+            _ => 0,
+        }
     }
 }
 
-impl MessageManager {
-    pub async fn init(&mut self, http: &Context) {
-        // Initiate a connection to the database file, creating the file if required.
-        let database = sqlx::sqlite::SqlitePoolOptions::new()
-                .max_connections(5)
-                .connect_with(
-                    sqlx::sqlite::SqliteConnectOptions::new()
-                        .filename("./database/database.sqlite")
-                        .create_if_missing(true),
-                )
-                .await
-                .expect("Couldn't connect to database");
-        
-        // Run migrations, which updates the database's schema to the latest version.
-        sqlx::migrate!("./migrations").run(&database).await.expect("Couldn't run database migrations");
+impl OperationReport {
+    /// Short confirmation, for guilds that asked for terse replies
+    pub fn terse(&self) -> String {
+        use OperationReport::*;
+        match self {
+            Created { channel, limit, .. } | Initialized { channel, limit } => format!("✅ <#{}>: {} messages", channel, limit),
+            Unchanged { channel, limit } => format!("<#{}> already keeps {} messages", channel, limit),
+            Increased { channel, old_limit, new_limit, .. } | Decreased { channel, old_limit, new_limit, .. } => format!("✅ <#{}>: {} → {} messages", channel, old_limit, new_limit),
+            Removed { channel, .. } => format!("✅ <#{}> is no longer autodeleted", channel),
+        }
+    }
+}
 
-        let query_result = sqlx::query_as::<_, ChannelLimitDatabaseEntry>("SELECT * FROM channel_limits").fetch_all(&database).await.unwrap();
-        debug!("Initializing {} queues from database", query_result.len());
-        for line in query_result {
-            if let Ok(chn) = line.channel_id.parse::<u64>() {
-                let init_result = self.update_limit(http, &ChannelId::from(chn), line.channel_limit as usize, true, None).await;
-                debug!("{}", init_result);
-            } else {
-                error!("Unparseable channel id in database: {}", line.channel_id);
-            }
+impl fmt::Display for OperationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use OperationReport::*;
+        match self {
+            Created { channel, limit, catching_up: true, .. } => write!(f, "Created limit {} for channel <#{}>! This channel has a long history, so I'm still catching up on older messages.", limit, channel),
+            Created { channel, limit, slow_fill_note, .. } => write!(f, "Created limit {} for channel <#{}>, and I'm already purging older messages!{}", limit, channel, slow_fill_note),
+            Initialized { channel, limit } => write!(f, "Initialized channel {} limit to {}", channel, limit),
+            Unchanged { channel, limit } => write!(f, "{} already is the limit for <#{}>!", limit, channel),
+            Increased { channel, old_limit, new_limit, slow_fill_note } => write!(f, "Okay, I increased the limit of <#{}> from {} to {}! Messages already deleted don't come back, new ones will fill the extra room.{}", channel, old_limit, new_limit, slow_fill_note),
+            Decreased { channel, old_limit, new_limit, .. } => write!(f, "Okay, I decreased the limit of <#{}> from {} to {}, and I'm already purging older messages!", channel, old_limit, new_limit),
+            Removed { channel, old_limit } => write!(f, "Removed limit ({}) from <#{}>", old_limit, channel),
         }
-        info!("Finished initializing queues from database");
+    }
+}
 
-        self.database = Some(database);
-        self.initialized = true;
+/// Why a change to a channel's limit could not be made
+pub enum ManagerError {
+    NotPermitted(ChannelId),
+    NotManaged(ChannelId),
+    NoLongerManaged(ChannelId),
+    ForumChannel(ChannelId),
+    // Categories, directories and the like have no messages at all
+    UnsupportedChannel(ChannelId, ChannelType),
+    // The history walk gave up after processing this many messages
+    HistoryIncomplete(ChannelId, usize, serenity::Error),
+}
 
+impl fmt::Display for ManagerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ManagerError::NotPermitted(channel) => write!(f, "<#{}> is not permitted to be autodeleted by this bot", channel),
+            ManagerError::NotManaged(channel) => write!(f, "<#{}> isn't being autodeleted", channel),
+            ManagerError::NoLongerManaged(channel) => write!(f, "<#{}> is no longer managed", channel),
+            ManagerError::ForumChannel(channel) => write!(f, "<#{}> is a forum, which has no messages of its own. Please run this inside one of its posts instead.", channel),
+            ManagerError::UnsupportedChannel(channel, kind) => write!(f, "<#{}> is a {} channel, which has no messages to autodelete", channel, kind.name()),
+            ManagerError::HistoryIncomplete(channel, processed, error) => write!(f, "Couldn't read the history of <#{}> after {} messages, please try again later ({})", channel, processed, error),
+        }
     }
+}
 
-    pub async fn on_pins_updated(&mut self, ctx: &Context, channel: ChannelId) {
-        let Some(cq) = self.channel_queues.get_mut(&channel) else { return; };
-        let Ok(updated_pins) = channel.pins(ctx).await else { return; };
-        // updated_pins.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+/// The user-facing message for the outcome of an operation, whichever it was
+fn outcome_message(result: &Result<OperationReport, ManagerError>) -> String {
+    match result {
+        Ok(report) => report.to_string(),
+        Err(error) => error.to_string(),
+    }
+}
 
-        let mut added_pins = VecDeque::with_capacity(CHANNEL_PIN_LIMIT);
-        let mut removed_pins = Vec::with_capacity(CHANNEL_PIN_LIMIT);
+/// How a channel handles system messages (joins, boosts, pins...)
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum SystemMessagePolicy {
+    /// Treated like any other message
+    #[default]
+    Normal,
+    /// Deleted as soon as they are posted
+    Delete,
+    /// Never deleted, and not counted toward the limit
+    Keep,
+}
 
-        // First we check for known pins missing from the channel
-        for existing_pin in cq.pins.iter() {
-            if !updated_pins.iter().any(|channel_pin| channel_pin.id == existing_pin.id) {
-                // If the updated pin list does not contain the known `existing_pin` then it was removed
-                removed_pins.push(existing_pin.clone());
-            }
+impl SystemMessagePolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "normal" => Some(SystemMessagePolicy::Normal),
+            "delete" => Some(SystemMessagePolicy::Delete),
+            "keep" => Some(SystemMessagePolicy::Keep),
+            _ => None,
         }
-        debug!("Removed {} pins", removed_pins.len());
+    }
 
-        // Then we check for new pins missing from the queue
-        for channel_pin in updated_pins.iter() {
-            if !cq.pins.iter().any(|existing_pin| channel_pin.id == existing_pin.id) {
-                // If the local pin list does not contain the new `channel_pin` then it was added
-                added_pins.push_back(channel_pin.id);
-            }
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SystemMessagePolicy::Normal => "normal",
+            SystemMessagePolicy::Delete => "delete",
+            SystemMessagePolicy::Keep => "keep",
         }
-        debug!("Added {} pins", added_pins.len());
+    }
+}
 
-        // We remove the newly-added pins (a.k.a. we retain the non-newly-added pins)
-        cq.queue.retain(|message| !added_pins.contains(&message.id));
+/// What a channel's limit measures
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum LimitKind {
+    /// How many messages are kept
+    #[default]
+    Count,
+    /// How many bytes of content are kept, on top of the message count
+    Bytes,
+}
 
-        // We re-add the newly-removed pins, sort them, and discard the excess
-        for message in cq.queue.drain(..) {
-            removed_pins.push(message);
+impl LimitKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "count" => Some(LimitKind::Count),
+            "bytes" => Some(LimitKind::Bytes),
+            _ => None,
         }
-        debug!("Temporary queue has {} messages (limit={})", removed_pins.len(), cq.limit);
-        removed_pins.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
-        
-        // Move it back from temporary Vec
-        cq.queue = VecDeque::from(removed_pins);
-        while cq.queue.len() > cq.limit {
-            if let Some(old_message) = cq.queue.pop_front() {
-                debug!("on_pins_updated: Popping and deleting last message (id={}; ts={}) (now {} vs {})", old_message.id, old_message.timestamp, cq.queue.len(), cq.limit);
-                if let Err(error) = old_message.delete(ctx).await {
-                    error!("Failed to delete message: {}", error);
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LimitKind::Count => "count",
+            LimitKind::Bytes => "bytes",
+        }
+    }
+}
+
+/// How much the bot says when a limit change succeeds. Errors are always shown in full.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verbosity {
+    #[default]
+    Verbose,
+    /// One line acknowledging the change
+    Terse,
+    /// Only a checkmark
+    Silent,
+}
+
+impl Verbosity {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "verbose" => Some(Verbosity::Verbose),
+            "terse" => Some(Verbosity::Terse),
+            "silent" => Some(Verbosity::Silent),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Verbosity::Verbose => "verbose",
+            Verbosity::Terse => "terse",
+            Verbosity::Silent => "silent",
+        }
+    }
+
+    /// The reply for an outcome whose full text is `content`; `report` is only given when the change succeeded
+    pub fn outcome_reply(&self, report: Option<&OperationReport>, content: String) -> String {
+        match (report, self) {
+            (Some(report), Verbosity::Terse) => report.terse(),
+            (Some(_), Verbosity::Silent) => SILENT_ACK.to_string(),
+            _ => content,
+        }
+    }
+}
+
+/// Which end of a full queue gives way.
+/// Either way, pinned, kept and protected messages are never deleted: pins only take room when they count toward
+/// the limit, and protected messages (oldest, kept or from a protected role) take none, so with `KeepOldest` they
+/// don't use up any of the slots of the first messages.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum RetentionDirection {
+    /// The oldest messages are deleted to make room for new ones
+    #[default]
+    KeepNewest,
+    /// New messages are deleted once the queue is full (e.g. "first N submissions" channels)
+    KeepOldest,
+}
+
+impl RetentionDirection {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "newest" => Some(RetentionDirection::KeepNewest),
+            "oldest" => Some(RetentionDirection::KeepOldest),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RetentionDirection::KeepNewest => "newest",
+            RetentionDirection::KeepOldest => "oldest",
+        }
+    }
+}
+
+/// What /leaderboard ranks the channels by
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum LeaderboardMetric {
+    /// Messages deleted by the bot
+    #[default]
+    Deleted,
+    /// How close the queue is to its limit
+    Fullness,
+    /// Messages per day, estimated from the tracked messages
+    Rate,
+}
+
+impl LeaderboardMetric {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "deleted" => Some(LeaderboardMetric::Deleted),
+            "fullness" => Some(LeaderboardMetric::Fullness),
+            "rate" => Some(LeaderboardMetric::Rate),
+            _ => None,
+        }
+    }
+}
+
+/// Message types that can be left out of the queues entirely, by the name used in /ignore-types
+const IGNORABLE_MESSAGE_TYPES: &[(&str, &[MessageType])] = &[
+    ("pins-added", &[MessageType::PinsAdd]),
+    ("thread-created", &[MessageType::ThreadCreated]),
+    ("member-join", &[MessageType::MemberJoin]),
+    ("boost", &[MessageType::NitroBoost, MessageType::NitroTier1, MessageType::NitroTier2, MessageType::NitroTier3]),
+    ("channel-follow", &[MessageType::ChannelFollowAdd]),
+    ("discovery", &[MessageType::GuildDiscoveryDisqualified, MessageType::GuildDiscoveryRequalified, MessageType::GuildDiscoveryGracePeriodInitialWarning, MessageType::GuildDiscoveryGracePeriodFinalWarning]),
+    ("invite-reminder", &[MessageType::GuildInviteReminder]),
+    ("automod", &[MessageType::AutoModerationAction]),
+    ("slash-command", &[MessageType::ChatInputCommand]),
+    ("context-menu", &[MessageType::ContextMenuCommand]),
+    ("reply", &[MessageType::InlineReply]),
+];
+// Pure noise, which guilds get unless they choose otherwise
+const DEFAULT_IGNORED_MESSAGE_TYPES: &[&str] = &["pins-added", "thread-created"];
+
+/// Parses an offset from UTC such as `UTC`, `UTC+2`, `GMT-05:00` or `+0530`
+pub fn parse_timezone(value: &str) -> Result<FixedOffset, String> {
+    let invalid = || format!("Unknown timezone \"{}\", please use an offset from UTC such as UTC, UTC+2, UTC-05:00 or UTC+05:30", value.trim());
+    let normalized = value.trim().to_uppercase();
+    let offset = normalized.strip_prefix("UTC").or_else(|| normalized.strip_prefix("GMT")).unwrap_or(&normalized);
+    if offset.is_empty() {
+        return Ok(FixedOffset::east_opt(0).expect("UTC is a valid offset"));
+    }
+    let (sign, offset) = match offset.split_at(1) {
+        ("+", offset) => (1, offset),
+        ("-", offset) => (-1, offset),
+        _ => return Err(invalid()),
+    };
+    let (hours, minutes) = match offset.split_once(':') {
+        Some(parts) => parts,
+        None if offset.len() == 4 => offset.split_at(2),
+        None => (offset, "0"),
+    };
+    let (Ok(hours), Ok(minutes)) = (hours.parse::<i32>(), minutes.parse::<i32>()) else { return Err(invalid()); };
+    if hours > 14 || minutes > 59 {
+        return Err(invalid());
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+}
+
+/// How timezones are shown and stored, e.g. `UTC` or `UTC+05:30`
+fn timezone_name(timezone: &FixedOffset) -> String {
+    let seconds = timezone.local_minus_utc();
+    if seconds == 0 {
+        return "UTC".to_string();
+    }
+    format!("UTC{}{:02}:{:02}", if seconds < 0 { '-' } else { '+' }, seconds.abs() / 3600, seconds.abs() % 3600 / 60)
+}
+
+/// Parses a comma-separated list of ignorable message type names, or `default`/`none`
+pub fn parse_ignored_types(value: &str) -> Result<Vec<&'static str>, String> {
+    match value.trim().to_lowercase().as_str() {
+        "default" => return Ok(DEFAULT_IGNORED_MESSAGE_TYPES.to_vec()),
+        "none" | "" => return Ok(Vec::new()),
+        _ => {},
+    }
+    let mut names = Vec::new();
+    for name in value.split(',').map(|name| name.trim().to_lowercase()).filter(|name| !name.is_empty()) {
+        let Some((known_name, _)) = IGNORABLE_MESSAGE_TYPES.iter().find(|(known_name, _)| *known_name == name) else {
+            let known_names: Vec<&str> = IGNORABLE_MESSAGE_TYPES.iter().map(|(known_name, _)| *known_name).collect();
+            return Err(format!("Unknown message type \"{}\", the types are: {}", name, known_names.join(", ")));
+        };
+        if !names.contains(known_name) {
+            names.push(*known_name);
+        }
+    }
+    Ok(names)
+}
+
+/// Whether the message was posted by Discord itself rather than by a user (or a command)
+fn is_system_message(kind: MessageType) -> bool {
+    !matches!(kind, MessageType::Regular | MessageType::InlineReply | MessageType::ChatInputCommand | MessageType::ContextMenuCommand)
+}
+
+/// Where to post a notice once a channel of the guild gets `threshold` percent full
+#[derive(Clone, Copy)]
+pub struct FullnessWarning {
+    pub log_channel: ChannelId,
+    pub threshold: u8,
+}
+
+/// Which messages are heavy (carrying large attachments or videos), and how many of them to keep
+#[derive(Clone, Copy)]
+pub struct HeavyRule {
+    pub limit: usize,
+    /// Size in bytes from which an attachment is heavy (0 for any size)
+    pub min_size: u64,
+    pub videos: bool,
+}
+
+impl HeavyRule {
+    fn matches(&self, msg: &Message) -> bool {
+        msg.attachments.iter().any(|attachment| {
+            (self.min_size > 0 && attachment.size >= self.min_size)
+                || (self.videos && attachment.content_type.as_deref().is_some_and(|content_type| content_type.starts_with("video/")))
+        })
+    }
+}
+
+/// The parts of a message needed to keep track of it, so queues can be persisted and restored
+#[derive(Clone)]
+pub struct TrackedMessage {
+    id: MessageId,
+    channel_id: ChannelId,
+    timestamp: Timestamp,
+    // Length of the content in bytes, for byte budgets
+    size: usize,
+    // Unknown for messages tracked before authors were persisted
+    author_id: Option<UserId>,
+    // Only known for messages seen since the bot started, attachments aren't persisted
+    attachments: Vec<AttachmentInfo>,
+}
+
+impl From<&Message> for TrackedMessage {
+    fn from(message: &Message) -> Self {
+        TrackedMessage { id: message.id, channel_id: message.channel_id, timestamp: message.timestamp, size: message.content.len(), author_id: Some(message.author.id), attachments: message.attachments.iter().map(AttachmentInfo::from).collect() }
+    }
+}
+
+/// A message the bot deleted, kept for the deletion log (as opposed to `channel_limit_edits`, which logs configuration changes)
+#[derive(Clone)]
+struct DeletedMessage {
+    id: MessageId,
+    author_id: Option<UserId>,
+    timestamp: Timestamp,
+    deleted_at: i64,
+}
+
+/// Deletes a message, or only logs it in dry-run mode (where it counts as deleted all the same).
+/// The reason shows up in the server's audit log.
+async fn delete_message(ctx: &Context, channel: ChannelId, message: MessageId, dry_run: bool, reason: Option<&str>) -> serenity::Result<()> {
+    if dry_run {
+        info!("WOULD DELETE message {} in {}", message, channel);
+        return Ok(());
+    }
+    auditlog::delete_message(ctx, channel, message, reason).await
+}
+
+impl TrackedMessage {
+    pub async fn delete(&self, ctx: &Context, dry_run: bool, reason: Option<&str>) -> serenity::Result<()> {
+        delete_message(ctx, self.channel_id, self.id, dry_run, reason).await
+    }
+
+    /// Replaces the content of one of our own messages with `TOMBSTONE`, dropping its embeds and attachments
+    pub async fn tombstone(&self, ctx: &Context, dry_run: bool) -> serenity::Result<()> {
+        if dry_run {
+            info!("WOULD TOMBSTONE message {} in {}", self.id, self.channel_id);
+            return Ok(());
+        }
+        self.channel_id.edit_message(ctx, self.id, |edit| edit.content(TOMBSTONE).set_embeds(Vec::new()).remove_all_attachments()).await.map(|_| ())
+    }
+
+    /// Orders messages by age. Bulk posts can share a timestamp, so ties are broken by ID
+    /// (snowflakes grow with creation time), keeping eviction order deterministic.
+    fn chronological_key(&self) -> (Timestamp, MessageId) {
+        (self.timestamp, self.id)
+    }
+
+    fn is_bulk_deletable(&self) -> bool {
+        Utc::now().timestamp() - self.timestamp.unix_timestamp() < BULK_DELETE_MAX_AGE_SECS
+    }
+}
+
+/// Deletes messages of one channel in a single request when Discord allows it, falling back to
+/// deleting them one by one (which is also how a failed bulk delete is retried).
+/// Returns the outcome of each message, in order.
+async fn delete_batch(ctx: &Context, messages: &[TrackedMessage], dry_run: bool, reason: Option<&str>, caller: &'static str) -> Vec<serenity::Result<()>> {
+    let channel = messages.first().map(|message| message.channel_id);
+    let bulk = messages.len() >= 2 && messages.len() <= BULK_DELETE_MAX
+        && messages.iter().all(|message| Some(message.channel_id) == channel && message.is_bulk_deletable());
+    if let (true, Some(channel)) = (bulk, channel) {
+        let result = if dry_run {
+            for message in messages {
+                info!("WOULD DELETE message {} in {}", message.id, channel);
+            }
+            Ok(())
+        } else {
+            let ids: Vec<MessageId> = messages.iter().map(|message| message.id).collect();
+            auditlog::delete_messages(ctx, channel, &ids, reason).await
+        };
+        match result {
+            Ok(()) => return std::iter::repeat_n((), messages.len()).map(Ok).collect(),
+            Err(error) => warn!("{}: Cannot bulk delete {} messages in {}, deleting them one by one: {}", caller, messages.len(), channel, error),
+        }
+    }
+
+    let mut results = Vec::with_capacity(messages.len());
+    for message in messages {
+        results.push(message.delete(ctx, dry_run, reason).await);
+    }
+    results
+}
+
+/// Inserts a message at its chronological position, after any message it ties with
+fn insert_chronologically(queue: &mut VecDeque<TrackedMessage>, message: TrackedMessage) {
+    let index = queue.partition_point(|queued| queued.chronological_key() <= message.chronological_key());
+    queue.insert(index, message);
+}
+
+struct DeleteJob {
+    ctx: Context,
+    // Several messages are bulk deleted when possible
+    messages: Vec<TrackedMessage>,
+    // Whether our own messages are edited into a tombstone rather than deleted
+    tombstone: bool,
+    caller: &'static str,
+}
+
+/// Hands deletions off to a pool of workers, so a slow delete never holds up the command loop.
+/// Messages leave their queue when handed off, and are only recorded as deleted once a worker
+/// reports back with `Command::DeletionFinished`.
+#[derive(Clone)]
+struct Deleter {
+    // Unbounded, since the command loop must never wait on workers that are themselves waiting on it
+    jobs: UnboundedSender<DeleteJob>,
+}
+
+impl Deleter {
+    fn spawn(workers: usize, dry_run: bool, archive: Option<AttachmentArchive>, reasons: DeletionReasons, results: Sender<Command>) -> Self {
+        let (jobs, receiver) = mpsc::unbounded_channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for worker in 0..workers {
+            tokio::spawn(run_delete_worker(worker, receiver.clone(), results.clone(), dry_run, archive.clone(), reasons.clone()));
+        }
+        Deleter { jobs }
+    }
+
+    fn submit(&self, ctx: &Context, message: TrackedMessage, caller: &'static str) {
+        self.submit_batch(ctx, vec![message], false, caller);
+    }
+
+    /// Hands off messages of one channel to be deleted together, see `delete_batch`
+    fn submit_batch(&self, ctx: &Context, messages: Vec<TrackedMessage>, tombstone: bool, caller: &'static str) {
+        if messages.is_empty() {
+            return;
+        }
+        if let Err(error) = self.jobs.send(DeleteJob { ctx: ctx.clone(), messages, tombstone, caller }) {
+            let ids: Vec<String> = error.0.messages.iter().map(|message| message.id.to_string()).collect();
+            error!("{}: Delete workers are gone, cannot delete messages {}", caller, ids.join(", "));
+        }
+    }
+}
+
+async fn run_delete_worker(worker: usize, jobs: Arc<Mutex<UnboundedReceiver<DeleteJob>>>, results: Sender<Command>, dry_run: bool, archive: Option<AttachmentArchive>, reasons: DeletionReasons) {
+    loop {
+        let Some(job) = jobs.lock().await.recv().await else { break; };
+        // Attachments can't be fetched once the message is gone, but failing to preserve them never holds up the deletion
+        if let Some(archive) = archive.as_ref() {
+            for message in job.messages.iter() {
+                if let Err(why) = archive.preserve_attachments(message.channel_id, message.id, message.author_id, &message.attachments).await {
+                    warn!("{}: Cannot preserve the attachments of message {}: {}", job.caller, message.id, why);
+                }
+            }
+        }
+        // Edited tombstones count as deleted, they are gone from the queue all the same
+        let own_id = job.ctx.cache.current_user_id();
+        let (tombstoned, deleted): (Vec<TrackedMessage>, Vec<TrackedMessage>) = job.messages.into_iter()
+            .partition(|message| job.tombstone && message.author_id == Some(own_id));
+        let mut outcomes = Vec::with_capacity(tombstoned.len() + deleted.len());
+        for message in tombstoned {
+            let result = message.tombstone(&job.ctx, dry_run).await;
+            outcomes.push((message, result));
+        }
+        // Jobs only hold messages of one channel
+        let reason = deleted.first().and_then(|message| reasons.for_channel(&job.ctx, message.channel_id));
+        let deletions = delete_batch(&job.ctx, &deleted, dry_run, reason.as_deref(), job.caller).await;
+        outcomes.extend(deleted.into_iter().zip(deletions));
+        for (message, result) in outcomes {
+            if results.send(Command::DeletionFinished { message, caller: job.caller, result }).await.is_err() {
+                debug!("Delete worker {} stopped", worker);
+                return;
+            }
+        }
+    }
+    debug!("Delete worker {} stopped", worker);
+}
+
+#[derive(Clone)]
+pub struct CappedQueue {
+    queue: VecDeque<TrackedMessage>,
+    pins: VecDeque<TrackedMessage>,
+    limit: usize,
+    deleted: usize,
+    pins_count_toward_limit: bool,
+    keep_oldest: usize,
+    protected_oldest: HashSet<MessageId>,
+    // Messages saved with /keep, which are never deleted nor counted
+    kept: HashSet<MessageId>,
+    system_message_policy: SystemMessagePolicy,
+    delete_duplicates: bool,
+    // The newest message is never evicted, even if that leaves the queue one over its capacity
+    always_keep_latest: bool,
+    // Our own messages past the limit are edited into a tombstone instead of deleted, see `expire`
+    tombstone: bool,
+    // Otherwise messages that started a thread are never deleted nor counted, so the conversation isn't lost
+    delete_messages_with_threads: bool,
+    // Messages seen starting a thread, left alone like kept messages (not persisted, history walks find them again)
+    threaded: HashSet<MessageId>,
+    // Content and timestamp of each author's latest message, to spot duplicates
+    last_by_author: HashMap<UserId, (String, Timestamp)>,
+    // Heavy messages are tracked in both queues, and are deleted once either is full
+    heavy: VecDeque<TrackedMessage>,
+    heavy_rule: Option<HeavyRule>,
+    deletion_bucket: Option<DeletionBucket>,
+    // Nothing is deleted until then (in milliseconds), messages are only tracked
+    snoozed_until: Option<i64>,
+    // Set while the bot can't delete messages here, so deletes aren't attempted (and logged) over and over
+    degraded: bool,
+    limit_kind: LimitKind,
+    byte_budget: usize,
+    retention: RetentionDirection,
+    // Whether the fullness warning was posted since the queue last went above its threshold
+    warning_fired: bool,
+    deleter: Deleter,
+    // The latest deletions, newest last
+    deletion_log: VecDeque<DeletedMessage>,
+}
+
+impl CappedQueue {
+    /// Applies a channel's settings as stored in the database (everything but the limit itself)
+    fn apply_settings(&mut self, channel: &ChannelId, line: &ChannelLimitDatabaseEntry, message_content_available: bool) {
+        self.pins_count_toward_limit = line.pins_count_toward_limit;
+        self.keep_oldest = line.keep_oldest as usize;
+        self.delete_duplicates = line.delete_duplicates;
+        self.always_keep_latest = line.always_keep_latest;
+        self.tombstone = line.tombstone;
+        self.delete_messages_with_threads = line.delete_messages_with_threads;
+        self.snoozed_until = line.snoozed_until;
+        match LimitKind::parse(&line.limit_kind) {
+            Some(LimitKind::Bytes) if !message_content_available => {
+                warn!("Message content is unavailable, limiting {} by message count instead of bytes", channel);
+            },
+            Some(limit_kind) => {
+                self.limit_kind = limit_kind;
+                self.byte_budget = line.byte_budget as usize;
+            },
+            None => error!("Unknown limit kind in database for {}: {}", channel, line.limit_kind),
+        }
+        self.heavy_rule = if line.heavy_limit > 0 {
+            Some(HeavyRule { limit: line.heavy_limit as usize, min_size: line.heavy_min_size as u64, videos: line.heavy_videos })
+        } else {
+            None
+        };
+        self.system_message_policy = SystemMessagePolicy::parse(&line.system_message_policy).unwrap_or_else(|| {
+            error!("Unknown system message policy in database for {}: {}", channel, line.system_message_policy);
+            SystemMessagePolicy::Normal
+        });
+        if let Some(direction) = RetentionDirection::parse(&line.retention_direction) {
+            self.retention = direction;
+        }
+    }
+}
+
+/// The smallest snowflake created at this moment, anything posted later has a greater ID
+fn snowflake_now() -> MessageId {
+    MessageId::from(((Utc::now().timestamp_millis() - DISCORD_EPOCH_MS) as u64) << 22)
+}
+
+/// Replays `messages` posts into a queue limited to `limit` messages, evicting the oldest like `CappedQueue::evict_excess`
+/// does with the default settings (no pins, no deletion rate, no byte budget), and describes the outcome.
+/// Nothing is deleted, it only projects what a channel would go through.
+pub fn simulate(limit: usize, messages: usize) -> String {
+    if limit == 0 {
+        return format!("With an ephemeral limit, all {} messages would be deleted as they are posted, and the channel would stay empty", messages);
+    }
+    let mut queue = VecDeque::with_capacity(limit);
+    let mut deleted = 0;
+    let mut first_deletion = None;
+    for message in 1..=messages {
+        queue.push_back(message);
+        while queue.len() > limit {
+            queue.pop_front();
+            deleted += 1;
+            first_deletion.get_or_insert(message);
+        }
+    }
+    debug_assert_eq!(deleted + queue.len(), messages);
+
+    let mut builder = Builder::default();
+    builder.append(format!("Simulating {} messages at a limit of {}:\n", messages, limit));
+    builder.append(format!("- Deleted: {}\n", deleted));
+    builder.append(format!("- Kept: {} ({:.0}% full)\n", queue.len(), 100.0 * queue.len() as f64 / limit as f64));
+    match first_deletion {
+        Some(first) => builder.append(format!("- The first deletion happens on message {}, then every new message deletes the oldest one (steady state: {} kept)\n", first, limit)),
+        None => builder.append(format!("- Nothing is deleted, {} more messages fit before the first deletion\n", limit - queue.len())),
+    };
+    builder.string().unwrap()
+}
+
+/// Token bucket capping how many messages a queue deletes per minute
+#[derive(Clone)]
+struct DeletionBucket {
+    rate_per_minute: usize,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl DeletionBucket {
+    fn new(rate_per_minute: usize) -> Self {
+        DeletionBucket { rate_per_minute, tokens: rate_per_minute as f64, refilled_at: Instant::now() }
+    }
+
+    /// Takes a token if one is available, refilling the bucket for the time elapsed since the last refill
+    fn try_take(&mut self) -> bool {
+        let now = Instant::now();
+        let refill = now.duration_since(self.refilled_at).as_secs_f64() * self.rate_per_minute as f64 / 60.0;
+        self.tokens = (self.tokens + refill).min(self.rate_per_minute as f64);
+        self.refilled_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl CappedQueue {
+    /// A `deletion_rate` of 0 deletes messages as fast as they come
+    fn new(limit: usize, deletion_rate: usize, deleter: Deleter) -> Self {
+        CappedQueue {
+            queue: VecDeque::with_capacity(limit),
+            pins: VecDeque::with_capacity(CHANNEL_PIN_LIMIT),
+            limit,
+            deleted: 0,
+            pins_count_toward_limit: false,
+            keep_oldest: 0,
+            protected_oldest: HashSet::new(),
+            kept: HashSet::new(),
+            system_message_policy: SystemMessagePolicy::Normal,
+            delete_duplicates: false,
+            always_keep_latest: false,
+            tombstone: false,
+            delete_messages_with_threads: false,
+            threaded: HashSet::new(),
+            last_by_author: HashMap::new(),
+            heavy: VecDeque::new(),
+            heavy_rule: None,
+            deletion_bucket: if deletion_rate > 0 { Some(DeletionBucket::new(deletion_rate)) } else { None },
+            snoozed_until: None,
+            degraded: false,
+            limit_kind: LimitKind::Count,
+            byte_budget: 0,
+            retention: RetentionDirection::KeepNewest,
+            warning_fired: false,
+            deleter,
+            deletion_log: VecDeque::new(),
+        }
+    }
+
+    /// Counts a deleted message and adds it to the deletion log, forgetting the oldest entries past the retention
+    fn record_deletion(&mut self, message: &TrackedMessage) {
+        self.deleted += 1;
+        self.deletion_log.push_back(DeletedMessage { id: message.id, author_id: message.author_id, timestamp: message.timestamp, deleted_at: Utc::now().timestamp_millis() });
+        while self.deletion_log.len() > DELETION_LOG_RETENTION {
+            self.deletion_log.pop_front();
+        }
+    }
+
+    /// How full the queue is, from 0 to 1
+    fn usage(&self) -> f64 {
+        if self.is_ephemeral() {
+            return 0.0;
+        }
+        (self.queue.len() as f64) / (self.limit as f64)
+    }
+
+    /// Whether the channel keeps no messages at all
+    fn is_ephemeral(&self) -> bool {
+        self.limit == 0
+    }
+
+    /// Total content length of the tracked messages
+    fn tracked_bytes(&self) -> usize {
+        self.queue.iter().map(|message| message.size).sum()
+    }
+
+    fn is_snoozed(&self) -> bool {
+        self.snoozed_until.is_some()
+    }
+
+    /// Whether deleting is on hold, because the channel is snoozed or the bot lacks permissions
+    fn is_halted(&self) -> bool {
+        self.is_snoozed() || self.degraded
+    }
+
+    /// Suffix for status lines, with the remaining snooze time and missing permissions
+    fn status_note(&self) -> String {
+        let mut note = if self.kept.is_empty() { String::new() } else { format!(" • {} kept", self.kept.len()) };
+        if let Some(snoozed_until) = self.snoozed_until {
+            note.push_str(&format!(" • snoozed, resuming <t:{}:R>", snoozed_until / 1000));
+        }
+        if self.degraded {
+            note.push_str(" • ⚠️ missing Manage Messages permission");
+        }
+        note
+    }
+
+    /// Whether the deletion rate allows deleting one more message right now
+    fn may_delete(&mut self) -> bool {
+        self.deletion_bucket.as_mut().is_none_or(|bucket| bucket.try_take())
+    }
+
+    /// How many unpinned messages can be kept.
+    /// When pins count toward the limit, limits below the pin count (at most `CHANNEL_PIN_LIMIT`) keep no unpinned messages at all.
+    fn capacity(&self) -> usize {
+        let capacity = if self.pins_count_toward_limit {
+            self.limit.saturating_sub(self.pins.len())
+        } else {
+            self.limit
+        };
+        debug_assert!(capacity <= self.limit, "capacity {} is above the limit {}", capacity, self.limit);
+        capacity
+    }
+
+    /// Hands the oldest messages (or the newest, when keeping the oldest) off for deletion until the queue (and the heavy
+    /// messages queue) fits within its capacity, returning how many were handed off (they are only counted as deleted once done).
+    /// When the deletion rate runs out, the queue stays over capacity until a later call catches up.
+    fn evict_excess(&mut self, ctx: &Context, caller: &'static str) -> usize {
+        let mut evicted = 0;
+        if self.is_halted() {
+            debug!("{}: Queue is snoozed or degraded, leaving {} excess messages for later", caller, self.queue.len().saturating_sub(self.capacity()));
+            return evicted;
+        }
+        // Byte budgets apply on top of the message limit
+        let byte_budget = if self.limit_kind == LimitKind::Bytes { self.byte_budget } else { usize::MAX };
+        let mut tracked_bytes = self.tracked_bytes();
+        while self.queue.len() > self.capacity() || tracked_bytes > byte_budget {
+            if !self.may_delete() {
+                debug!("{}: Deletion rate exceeded, leaving {} excess messages ({} excess bytes) for later", caller, self.queue.len().saturating_sub(self.capacity()), tracked_bytes.saturating_sub(byte_budget));
+                return evicted;
+            }
+            if self.always_keep_latest && self.queue.len() <= 1 {
+                debug!("{}: Keeping the newest message, {} over capacity", caller, self.queue.len().saturating_sub(self.capacity()));
+                break;
+            }
+            let popped = match self.retention {
+                RetentionDirection::KeepNewest => self.queue.pop_front(),
+                // The one before the newest goes instead
+                RetentionDirection::KeepOldest if self.always_keep_latest => self.queue.remove(self.queue.len() - 2),
+                RetentionDirection::KeepOldest => self.queue.pop_back(),
+            };
+            let Some(old_message) = popped else {
+                error!("{}: Queue is full but failed to pop message", caller);
+                break;
+            };
+            // The running total is the sum of the queued sizes, so it covers every message still in the queue
+            debug_assert!(tracked_bytes >= old_message.size);
+            tracked_bytes = tracked_bytes.saturating_sub(old_message.size);
+            debug!("{}: Popping and deleting {} message (id={}; ts={}) (now {} vs {})", caller, self.retention.as_str(), old_message.id, old_message.timestamp, self.queue.len(), self.capacity());
+            self.heavy.retain(|message| message.id != old_message.id);
+            self.expire(ctx, old_message, caller);
+            evicted += 1;
+        }
+
+        let heavy_limit = self.heavy_rule.map_or(usize::MAX, |rule| rule.limit);
+        while self.heavy.len() > heavy_limit {
+            if !self.may_delete() {
+                debug!("{}: Deletion rate exceeded, leaving {} excess heavy messages for later", caller, self.heavy.len().saturating_sub(heavy_limit));
+                return evicted;
+            }
+            let candidate = match self.retention {
+                RetentionDirection::KeepNewest => self.heavy.front(),
+                RetentionDirection::KeepOldest => self.heavy.back(),
+            };
+            if self.always_keep_latest && candidate.map(|message| message.id) == self.queue.back().map(|message| message.id) {
+                debug!("{}: Keeping the newest message, which is heavy", caller);
+                break;
+            }
+            let popped = match self.retention {
+                RetentionDirection::KeepNewest => self.heavy.pop_front(),
+                RetentionDirection::KeepOldest => self.heavy.pop_back(),
+            };
+            let Some(old_message) = popped else { break; };
+            debug!("{}: Popping and deleting heavy message (id={}; ts={}) (now {} vs {})", caller, old_message.id, old_message.timestamp, self.heavy.len(), heavy_limit);
+            self.queue.retain(|message| message.id != old_message.id);
+            self.expire(ctx, old_message, caller);
+            evicted += 1;
+        }
+        evicted
+    }
+
+    /// What a purge of the messages older than the queue must skip
+    fn purge_exemptions(&self) -> PurgeExemptions {
+        PurgeExemptions {
+            messages: self.protected_oldest.union(&self.kept).copied().collect(),
+            delete_messages_with_threads: self.delete_messages_with_threads,
+            ..Default::default()
+        }
+    }
+
+    /// Whether a history walk keeping the oldest messages can swap messages in itself, rather than going through
+    /// `insert_message` and `evict_excess` for each one: only plain count limits, deleting at full speed, qualify.
+    fn walk_can_displace(&self) -> bool {
+        self.retention == RetentionDirection::KeepOldest && self.limit_kind == LimitKind::Count && self.heavy_rule.is_none()
+            && !self.always_keep_latest && self.deletion_bucket.is_none() && !self.is_halted()
+    }
+
+    /// While walking the history from newest to oldest with the queue full, the walked message is older than
+    /// everything tracked: it takes the place of the newest tracked message, which is returned for deletion.
+    fn displace_newest(&mut self, message: TrackedMessage) -> Option<TrackedMessage> {
+        self.queue.push_front(message);
+        self.queue.pop_back()
+    }
+
+    /// Puts the queues back in chronological order and drops entries tracked more than once (keeping the first),
+    /// so the room freed by a larger limit isn't taken up twice by the same message. Returns how many were dropped.
+    fn merge_duplicates(&mut self) -> usize {
+        fn merge(queue: &mut VecDeque<TrackedMessage>) -> usize {
+            let before = queue.len();
+            let mut seen = HashSet::with_capacity(before);
+            queue.retain(|message| seen.insert(message.id));
+            queue.make_contiguous().sort_by_key(TrackedMessage::chronological_key);
+            before - queue.len()
+        }
+        let merged = merge(&mut self.queue);
+        merge(&mut self.heavy);
+        merged
+    }
+
+    /// Hands off a message past the limit. With tombstones on, our own messages are edited into one rather
+    /// than deleted; Discord only lets bots edit their own messages, so everyone else's are deleted as usual.
+    fn expire(&self, ctx: &Context, message: TrackedMessage, caller: &'static str) {
+        self.deleter.submit_batch(ctx, vec![message], self.tombstone, caller);
+    }
+
+    /// Whether the message repeats its author's previous message within a short window, remembering it otherwise.
+    /// Messages that are out of the window can't be repeated anymore, so they are forgotten.
+    fn is_duplicate(&mut self, msg: &Message) -> bool {
+        let now = msg.timestamp.unix_timestamp();
+        self.last_by_author.retain(|_, (_, timestamp)| now - timestamp.unix_timestamp() <= DUPLICATE_WINDOW_SECS);
+        // Without the message content intent (or for attachment-only messages) the content is empty, which says nothing
+        if msg.content.trim().is_empty() {
+            self.last_by_author.remove(&msg.author.id);
+            return false;
+        }
+        if let Some((content, _)) = self.last_by_author.get(&msg.author.id) {
+            if *content == msg.content {
+                return true;
+            }
+        }
+        self.last_by_author.insert(msg.author.id, (msg.content.clone(), msg.timestamp));
+        false
+    }
+
+    /// How many messages are posted per day, based on the tracked messages
+    fn messages_per_day(&self) -> Option<f64> {
+        let oldest = self.queue.front()?;
+        let elapsed_days = (Utc::now().timestamp() - oldest.timestamp.unix_timestamp()) as f64 / 86400.0;
+        if elapsed_days <= 0.0 {
+            return None;
+        }
+        Some(self.queue.len() as f64 / elapsed_days)
+    }
+
+    /// Estimates how many days it will take for the queue to fill up, based on the rate of the tracked messages
+    fn estimated_days_to_fill(&self) -> Option<f64> {
+        let remaining = self.capacity().saturating_sub(self.queue.len());
+        if remaining == 0 {
+            return Some(0.0);
+        }
+        Some(remaining as f64 / self.messages_per_day()?)
+    }
+
+    /// Informational note to append to replies when the limit will take very long to be reached
+    fn slow_fill_note(&self) -> &'static str {
+        match self.estimated_days_to_fill() {
+            Some(days) if days > SLOW_FILL_WARNING_DAYS => {
+                debug!("Queue would take an estimated {:.0} days to fill (limit={})", days, self.limit);
+                " At the current rate this limit may rarely trigger deletions."
+            },
+            _ => "",
+        }
+    }
+}
+
+/// A temporarily raised limit waiting to be restored
+#[derive(Clone, Copy)]
+struct ScheduledRevert {
+    original_limit: usize,
+    raised_limit: usize,
+    revert_at: i64,
+}
+
+#[derive(Default)]
+struct MessageManager {
+    initialized: bool,
+    channel_queues: HashMap<ChannelId, CappedQueue>,
+    database: Option<Pool<Sqlite>>,
+    limit_cooldown: Duration,
+    last_limit_change: HashMap<ChannelId, Instant>,
+    allowed_channels: HashSet<ChannelId>,
+    denied_channels: HashSet<ChannelId>,
+    blocked_keywords: HashMap<ChannelId, Vec<String>>,
+    protected_roles: HashMap<ChannelId, Vec<RoleId>>,
+    autoconfig_patterns: HashMap<GuildId, Vec<(String, usize)>>,
+    auto_configured_channels: HashSet<ChannelId>,
+    schema_version: Option<i64>,
+    context: Option<Context>,
+    scheduled_reverts: HashMap<ChannelId, ScheduledRevert>,
+    channel_names: HashMap<ChannelId, (String, Instant)>,
+    // Recently fetched pins, so bursts of pin events don't each hit the API
+    pins_cache: HashMap<ChannelId, (Vec<Message>, Instant)>,
+    // Channels whose pins are fetched again once their cache expires, as the cache may predate the last event of a burst
+    pins_refetches: HashSet<ChannelId>,
+    // Lets the manager send itself commands later on
+    commands: Option<Sender<Command>>,
+    // Pin changes already applied to the local pins, whose pins update events need no reconciliation:
+    // how many events are expected per channel, and until when
+    expected_pin_changes: HashMap<ChannelId, (usize, Instant)>,
+    // Protected message IDs loaded on startup, waiting for their channel's queue to be created
+    pending_protected_oldest: HashMap<ChannelId, HashSet<MessageId>>,
+    // Kept message IDs loaded on startup, likewise
+    pending_kept: HashMap<ChannelId, HashSet<MessageId>>,
+    // Retention directions loaded on startup, so the history walk already keeps the right end
+    pending_retention: HashMap<ChannelId, RetentionDirection>,
+    last_activity: HashMap<ChannelId, Instant>,
+    unmanage_after_idle: HashMap<GuildId, Duration>,
+    announce_changes: HashSet<GuildId>,
+    // Guilds that turned off the confirmation before configuring their important channels
+    skip_important_confirmation: HashSet<GuildId>,
+    // Guilds deleting the tracked messages of members as they get banned
+    purge_on_ban: HashSet<GuildId>,
+    fullness_warnings: HashMap<GuildId, FullnessWarning>,
+    // Guilds missing from here ignore `DEFAULT_IGNORED_MESSAGE_TYPES`
+    ignored_types: HashMap<GuildId, Vec<&'static str>>,
+    // Guilds missing from here show times in UTC
+    timezones: HashMap<GuildId, FixedOffset>,
+    verbosities: HashMap<GuildId, Verbosity>,
+    // Our own announcements, which are never tracked
+    announcements: HashSet<MessageId>,
+    config_webhook: Option<ConfigWebhook>,
+    require_database: bool,
+    purge_summary_dm: bool,
+    database_path: PathBuf,
+    deletion_rate: usize,
+    keep_stale_channels: bool,
+    seed_config: Option<SeedConfig>,
+    // Without the message content intent every message is empty, so byte budgets can't be measured
+    message_content_available: bool,
+    audit_sample_size: usize,
+    phantoms_reclaimed: usize,
+    // Set while the sampled messages are being checked, so audits don't pile up behind rate limits
+    auditing: bool,
+    // Deletions are only logged, everything else behaves as if they happened
+    dry_run: bool,
+    // Started along with the manager, before any command is handled
+    deleter: Option<Deleter>,
+    // Whether to post in the guild's log channel when a message is deleted right after being unpinned
+    unpin_deletion_notices: bool,
+    // Unpinned messages handed off for deletion, awaiting the outcome to post their notice
+    unpinned_deletions: HashSet<MessageId>,
+    // Roles of recently seen members, see `member_roles`
+    member_cache: MemberCache,
+    // Deletions recorded since the queues were last persisted, which persists them early once
+    // there are `persist_batch_size` of them (0 to only persist periodically)
+    unpersisted_deletions: usize,
+    persist_batch_size: usize,
+    // Shared with the delete workers
+    deletion_reasons: DeletionReasons,
+    backups: Option<DatabaseBackups>,
+}
+
+pub struct MessageManagerReceiver {
+    pub limit_cooldown: Duration,
+    pub config_webhook: Option<ConfigWebhook>,
+    pub require_database: bool,
+    pub purge_summary_dm: bool,
+    pub database_path: PathBuf,
+    pub deletion_rate: usize,
+    pub keep_stale_channels: bool,
+    pub seed_config: Option<SeedConfig>,
+    pub audit_sample_size: usize,
+    pub dry_run: bool,
+    pub delete_workers: usize,
+    pub unpin_deletion_notices: bool,
+    pub member_cache_size: usize,
+    pub attachment_archive: Option<AttachmentArchive>,
+    pub slow_command_threshold: Duration,
+    pub persist_batch_size: usize,
+    pub deletion_reasons: DeletionReasons,
+    pub backups: Option<DatabaseBackups>,
+}
+
+#[derive(FromRow)]
+struct ChannelLimitDatabaseEntry {
+    channel_id: String,
+    channel_limit: u32,
+    pins_count_toward_limit: bool,
+    keep_oldest: u32,
+    system_message_policy: String,
+    delete_duplicates: bool,
+    heavy_limit: u32,
+    heavy_min_size: i64,
+    heavy_videos: bool,
+    snoozed_until: Option<i64>,
+    limit_kind: String,
+    byte_budget: i64,
+    retention_direction: String,
+    always_keep_latest: bool,
+    tombstone: bool,
+    delete_messages_with_threads: bool,
+    // Set when the channel was managed because its name matched an autoconfig pattern
+    auto_configured: bool,
+    // Missing for rows written before it was recorded, until the channel is seen again
+    guild_id: Option<String>,
+}
+
+#[derive(FromRow)]
+struct GuildSettingsDatabaseEntry {
+    guild_id: String,
+    unmanage_after_idle: Option<i64>,
+    announce_changes: bool,
+    warning_threshold: u8,
+    warning_channel: Option<String>,
+    ignored_message_types: Option<String>,
+    timezone: Option<String>,
+    verbosity: String,
+    confirm_important_channels: bool,
+    purge_on_ban: bool,
+    deletion_reason: Option<String>,
+}
+
+#[derive(FromRow)]
+struct ChannelLimitEditDatabaseEntry {
+    channel_id: String,
+    channel_limit: i64,
+    created_at: String,
+    guild_id: Option<String>,
+}
+
+#[derive(FromRow)]
+struct ChannelStatsDatabaseEntry {
+    channel_id: String,
+    deleted_count: i64,
+}
+
+#[derive(FromRow)]
+struct DeletionLogDatabaseEntry {
+    channel_id: String,
+    message_id: String,
+    author_id: Option<String>,
+    timestamp: String,
+    deleted_at: i64,
+}
+
+#[derive(FromRow)]
+struct ProtectedMessageDatabaseEntry {
+    channel_id: String,
+    message_id: String,
+}
+
+#[derive(FromRow)]
+struct ChannelAccessDatabaseEntry {
+    channel_id: String,
+    allowed: bool,
+}
+
+#[derive(FromRow)]
+struct BlockedKeywordDatabaseEntry {
+    channel_id: String,
+    keyword: String,
+}
+
+#[derive(FromRow)]
+struct ProtectedRoleDatabaseEntry {
+    channel_id: String,
+    role_id: String,
+}
+
+#[derive(FromRow)]
+struct AutoconfigPatternDatabaseEntry {
+    guild_id: String,
+    pattern: String,
+    channel_limit: u32,
+}
+
+#[derive(FromRow)]
+struct ScheduledRevertDatabaseEntry {
+    channel_id: String,
+    original_limit: u32,
+    raised_limit: u32,
+    revert_at: i64,
+}
+
+#[derive(FromRow)]
+struct TrackedMessageDatabaseEntry {
+    message_id: String,
+    timestamp: String,
+    size: i64,
+    author_id: Option<String>,
+}
+
+/// Whether the error is SQLite reporting the database as busy or locked
+fn is_database_busy(error: &sqlx::Error) -> bool {
+    let sqlx::Error::Database(db_error) = error else { return false; };
+    // Extended result codes keep the primary code in the lower byte (SQLITE_BUSY=5, SQLITE_LOCKED=6)
+    match db_error.code().and_then(|code| code.parse::<u32>().ok()) {
+        Some(code) => matches!(code & 0xff, 5 | 6),
+        None => false,
+    }
+}
+
+/// Matches a name against a simple glob, where `*` matches any run of characters and `?` matches a single one
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` seen, and the name position it was tried against
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the last `*` swallow one more character and try again
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// The latest successfully applied migration, if any
+async fn applied_schema_version(db: &Pool<Sqlite>) -> Option<i64> {
+    // The migrations table does not exist yet on a fresh database
+    sqlx::query_scalar::<_, Option<i64>>("SELECT MAX(version) FROM _sqlx_migrations WHERE success = 1")
+        .fetch_one(db).await
+        .ok()
+        .flatten()
+}
+
+fn format_schema_version(version: Option<i64>) -> String {
+    version.map_or("none".to_string(), |version| version.to_string())
+}
+
+/// What a background purge leaves alone, as the channel's queue had it when the purge was started
+#[derive(Clone, Debug, Default)]
+struct PurgeExemptions {
+    // The protected oldest and kept messages
+    messages: HashSet<MessageId>,
+    delete_messages_with_threads: bool,
+    protected_roles: Vec<RoleId>,
+}
+
+impl PurgeExemptions {
+    /// Whether the message is skipped, the same way a history walk skips it
+    fn exempts(&self, ctx: &Context, message: &Message) -> bool {
+        message.pinned || message.kind == MessageType::ThreadStarterMessage || self.messages.contains(&message.id)
+            // Deleting them would orphan the conversation
+            || (message.thread.is_some() && !self.delete_messages_with_threads)
+            || self.has_protected_role(ctx, message)
+    }
+
+    /// Like `MessageManager::has_protected_role`, but straight from the serenity cache as the purge has no member cache
+    fn has_protected_role(&self, ctx: &Context, message: &Message) -> bool {
+        if self.protected_roles.is_empty() {
+            return false;
+        }
+        let author_roles = match message.member.as_ref() {
+            Some(member) => member.roles.clone(),
+            None => {
+                let guild_id = message.guild_id.or_else(|| ctx.cache.guild_channel(message.channel_id).map(|channel| channel.guild_id));
+                guild_id.and_then(|guild_id| ctx.cache.member(guild_id, message.author.id)).map(|member| member.roles).unwrap_or_default()
+            },
+        };
+        author_roles.iter().any(|role| self.protected_roles.contains(role))
+    }
+}
+
+/// Deletes every (unpinned) message older than `before`, page by page, except the `exemptions`.
+/// Runs outside of the manager so a huge backlog doesn't hold up other commands.
+/// Once done, the summary is sent to the `requester` interaction, or (if allowed) by DM when the interaction already expired.
+async fn purge_older_than(ctx: Context, channel: ChannelId, mut before: MessageId, exemptions: PurgeExemptions, requester: Option<(ApplicationCommandInteraction, bool)>, dry_run: bool, reason: Option<String>) {
+    let started_at = Instant::now();
+    let mut deleted_count = 0;
+    loop {
+        let messages = match channel.messages(&ctx, |retriever| retriever.before(before).limit(100)).await {
+            Ok(messages) => messages,
+            Err(error) => {
+                error!("purge_older_than: Failed to fetch messages: {}", error);
+                break;
+            }
+        };
+        let fetched_count = messages.len();
+        for message in messages {
+            before = before.min(message.id);
+            if exemptions.exempts(&ctx, &message) {
+                continue;
+            }
+            match delete_message(&ctx, channel, message.id, dry_run, reason.as_deref()).await {
+                Ok(_) => deleted_count += 1,
+                Err(error) if is_message_gone(&error) => debug!("purge_older_than: Message {} was already deleted", message.id),
+                Err(error) => error!("purge_older_than: Failed to delete message: {}", error),
+            }
+        }
+        if fetched_count < 100 {
+            break;
+        }
+    }
+    info!("Finished catching up on {}, deleted {} older messages", channel, deleted_count);
+
+    let Some((interaction, dm_allowed)) = requester else { return; };
+    let summary = format!("Finished catching up on <#{}>: deleted {} older messages in {} minutes", channel, deleted_count, started_at.elapsed().as_secs() / 60);
+    // Interaction tokens only last 15 minutes, after which follow-ups fail
+    let followup = interaction.create_followup_message(&ctx, |response| response.content(&summary).ephemeral(true)).await;
+    let Err(why) = followup else { return; };
+    debug!("Cannot send purge summary as a follow-up: {}", why);
+    if !dm_allowed {
+        return;
+    }
+    if let Err(why) = interaction.user.direct_message(&ctx, |message| message.content(&summary)).await {
+        // Users may have DMs from server members disabled
+        warn!("Cannot DM purge summary to {}: {}", interaction.user.id, why);
+    }
+}
+
+/// Deletes the message posted by /testdelete after a moment, the same way tracked messages are deleted,
+/// and tells the requester how it went
+async fn delete_test_message(ctx: Context, message: Message, interaction: ApplicationCommandInteraction, dry_run: bool, reason: Option<String>) {
+    tokio::time::sleep(TEST_DELETE_DELAY).await;
+    let content = match TrackedMessage::from(&message).delete(&ctx, dry_run, reason.as_deref()).await {
+        Ok(_) if dry_run => format!("Dry run is enabled, so the test message in <#{}> was only logged as deleted", message.channel_id),
+        Ok(_) => format!("Deleted the test message in <#{}>, the bot can delete messages here", message.channel_id),
+        Err(error) if is_message_gone(&error) => format!("The test message in <#{}> was deleted by someone else first, please try again", message.channel_id),
+        Err(error) => {
+            warn!("Cannot delete test message {} in {}: {}", message.id, message.channel_id, error);
+            format!("Failed to delete the test message in <#{}>: {}", message.channel_id, error)
+        },
+    };
+    if let Err(why) = interaction.create_followup_message(&ctx, |response| response.content(content).ephemeral(true)).await {
+        warn!("Cannot respond to slash command: {}", why);
+    }
+}
+
+/// Stores a channel's limit, and who set it for the audit trail (nobody for changes the bot makes on its own)
+async fn update_limit_db(channel: &ChannelId, guild_id: Option<GuildId>, new_limit: usize, user_id: Option<UserId>, db_ref: Option<&Pool<Sqlite>>) -> Result<(), ()> {
+    if let Some(db) = db_ref {
+        // Upsert so the channel's other settings are kept (and its guild, if it can't be resolved now)
+        let result_limit = retry_write(move || sqlx::query("INSERT INTO channel_limits (channel_id, channel_limit, guild_id) VALUES (?, ?, ?) ON CONFLICT(channel_id) DO UPDATE SET channel_limit=excluded.channel_limit, auto_configured=0, guild_id=COALESCE(excluded.guild_id, channel_limits.guild_id)")
+            .bind(channel.to_string())
+            .bind(new_limit as u32)
+            .bind(guild_id.map(|guild_id| guild_id.to_string()))
+            .execute(db)).await
+            .map_err(|error| error!("Failed to update channel limit: {}", error))?;
+        debug!("DB update affected {:?} rows", result_limit.rows_affected());
+
+        let Some(user_id) = user_id else {
+            debug!("Limit of {} changed by no one in particular, not recording an edit", channel);
+            return Ok(());
+        };
+        let timestamp = Utc::now().timestamp_millis();
+        let result_audit = retry_write(move || sqlx::query("INSERT INTO channel_limit_edits (user_id, channel_id, channel_limit, created_at, guild_id) VALUES (?,?,?,?,?)")
+            .bind(user_id.to_string())
+            .bind(channel.to_string())
+            .bind(new_limit as u32)
+            .bind(timestamp)
+            .bind(guild_id.map(|guild_id| guild_id.to_string()))
+            .execute(db)).await
+            .map_err(|error| error!("Failed to insert channel limit edit: {}", error))?;
+        debug!("DB update affected {:?} rows", result_audit.rows_affected());
+        Ok(())
+    } else {
+        error!("Database is not initialized");
+        Err(())
+    }
+}
+
+/// Opens the database file, creating it if required. Writers wait on each other for a while rather than failing
+/// right away, and WAL journaling lets reads go on during writes.
+async fn connect_database(path: &Path) -> Result<Pool<Sqlite>, sqlx::Error> {
+    sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(
+            sqlx::sqlite::SqliteConnectOptions::new()
+                .filename(path)
+                .create_if_missing(true)
+                .busy_timeout(DB_BUSY_TIMEOUT)
+                .journal_mode(SqliteJournalMode::Wal),
+        )
+        .await
+}
+
+/// Runs a write query, retrying a few times if the database is busy
+async fn retry_write<F, Fut>(mut write: F) -> Result<SqliteQueryResult, sqlx::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<SqliteQueryResult, sqlx::Error>>,
+{
+    let mut attempt = 1;
+    loop {
+        match write().await {
+            Err(error) if attempt < DB_WRITE_ATTEMPTS && is_database_busy(&error) => {
+                warn!("Database is busy, retrying write (attempt {}/{}): {}", attempt, DB_WRITE_ATTEMPTS, error);
+                tokio::time::sleep(DB_RETRY_DELAY * attempt).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Deletes everything persisted about the channels in a single transaction, recording the removals as limit edits
+async fn delete_channel_rows(db: &Pool<Sqlite>, channels: &[ChannelId], user_id: UserId) -> Result<(), sqlx::Error> {
+    let timestamp = Utc::now().timestamp_millis();
+    let mut transaction = db.begin().await?;
+    for channel in channels.iter() {
+        // Before the limit row goes, as the guild is taken from it
+        sqlx::query("INSERT INTO channel_limit_edits (user_id, channel_id, channel_limit, created_at, guild_id) VALUES (?,?,?,?,(SELECT guild_id FROM channel_limits WHERE channel_id=?))")
+            .bind(user_id.to_string())
+            .bind(channel.to_string())
+            .bind(0_u32)
+            .bind(timestamp)
+            .bind(channel.to_string())
+            .execute(&mut transaction).await?;
+        for table in ["channel_limits", "tracked_messages", "protected_messages", "kept_messages", "channel_stats", "deletion_log"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE channel_id=?", table))
+                .bind(channel.to_string())
+                .execute(&mut transaction).await?;
+        }
+    }
+    transaction.commit().await
+}
+
+/// Zeroes the deletion counters of the channels in a single transaction
+async fn reset_stats_rows(db: &Pool<Sqlite>, channels: &[ChannelId]) -> Result<(), sqlx::Error> {
+    let mut transaction = db.begin().await?;
+    for channel in channels.iter() {
+        sqlx::query("UPDATE channel_stats SET deleted_count=0 WHERE channel_id=?")
+            .bind(channel.to_string())
+            .execute(&mut transaction).await?;
+    }
+    transaction.commit().await
+}
+
+/// The guild a channel belongs to, from the cache or else fetched
+async fn guild_of(ctx: &Context, channel: &ChannelId) -> Option<GuildId> {
+    if let Some(Channel::Guild(guild_channel)) = channel.to_channel_cached(&ctx.cache) {
+        return Some(guild_channel.guild_id);
+    }
+    match channel.to_channel(ctx).await {
+        Ok(Channel::Guild(guild_channel)) => Some(guild_channel.guild_id),
+        _ => None,
+    }
+}
+
+/// Fills in the guild of a channel's rows written before guilds were recorded
+async fn backfill_guild_id(db: &Pool<Sqlite>, channel: &ChannelId, guild_id: GuildId) {
+    for table in ["channel_limits", "channel_limit_edits"] {
+        match sqlx::query(&format!("UPDATE {} SET guild_id=? WHERE channel_id=? AND guild_id IS NULL", table))
+            .bind(guild_id.to_string())
+            .bind(channel.to_string())
+            .execute(db).await {
+            Ok(result) => debug!("Backfilled the guild of {} rows of {} in {}", result.rows_affected(), channel, table),
+            Err(error) => warn!("Failed to backfill the guild of {} in {}: {}", channel, table, error),
+        }
+    }
+}
+
+/// Whether the request may succeed if tried again: rate limits, server errors and failed connections
+fn is_transient(error: &serenity::Error) -> bool {
+    let serenity::Error::Http(http_error) = error else { return false; };
+    match http_error.status_code() {
+        Some(status) => status.as_u16() == 429 || status.is_server_error(),
+        None => true,
+    }
+}
+
+/// Whether Discord reports the channel as deleted or out of the bot's reach (rather than failing for another reason)
+fn is_channel_gone(error: &serenity::Error) -> bool {
+    let serenity::Error::Http(http_error) = error else { return false; };
+    matches!(http_error.status_code().map(|status| status.as_u16()), Some(403) | Some(404))
+}
+
+/// Whether the error is Discord reporting the message as deleted
+fn is_message_gone(error: &serenity::Error) -> bool {
+    let serenity::Error::Http(http_error) = error else { return false; };
+    http_error.status_code().map(|status| status.as_u16()) == Some(404)
+}
+
+/// Replaces the persisted tracked messages, statistics and deletion log of a channel with the contents of its queue
+async fn persist_queue(transaction: &mut Transaction<'_, Sqlite>, channel: &ChannelId, cq: &CappedQueue) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT OR REPLACE INTO channel_stats VALUES (?,?)")
+        .bind(channel.to_string())
+        .bind(cq.deleted as i64)
+        .execute(&mut *transaction).await?;
+    sqlx::query("DELETE FROM tracked_messages WHERE channel_id=?")
+        .bind(channel.to_string())
+        .execute(&mut *transaction).await?;
+    for message in cq.queue.iter() {
+        sqlx::query("INSERT INTO tracked_messages VALUES (?,?,?,?,?)")
+            .bind(channel.to_string())
+            .bind(message.id.to_string())
+            .bind(message.timestamp.to_string())
+            .bind(message.size as i64)
+            .bind(message.author_id.map(|author_id| author_id.to_string()))
+            .execute(&mut *transaction).await?;
+    }
+    // Only the entries still in memory are kept, which enforces the retention
+    sqlx::query("DELETE FROM deletion_log WHERE channel_id=?")
+        .bind(channel.to_string())
+        .execute(&mut *transaction).await?;
+    for entry in cq.deletion_log.iter() {
+        sqlx::query("INSERT OR REPLACE INTO deletion_log VALUES (?,?,?,?,?)")
+            .bind(channel.to_string())
+            .bind(entry.id.to_string())
+            .bind(entry.author_id.map(|author_id| author_id.to_string()))
+            .bind(entry.timestamp.to_string())
+            .bind(entry.deleted_at)
+            .execute(&mut *transaction).await?;
+    }
+    Ok(())
+}
+
+impl MessageManagerReceiver {
+    /// Deletion outcomes are reported back through `sender`, the same channel the events come in on
+    pub fn run(&self, receiver: Receiver<Command>, sender: Sender<Command>) {
+        async fn reply_deferred(interaction:&ApplicationCommandInteraction, context: &Context, content: String, _ephemeral: bool) {
+            if let Err(why) = interaction
+            .create_followup_message(context, |response| {
+                response
+                .content(content)
+            }).await
+            {
+                warn!("Cannot respond to slash command: {}", why);
+            }
+        }
+
+        /// Replies with the outcome of a limit change as the guild asked; `report` is only given on success
+        async fn reply_deferred_outcome(interaction: &ApplicationCommandInteraction, context: &Context, verbosity: Verbosity, report: Option<&OperationReport>, content: String) {
+            reply_deferred(interaction, context, verbosity.outcome_reply(report, content), true).await;
+        }
+
+        /// Asks the user to confirm with a button, whose id says what is being confirmed
+        async fn reply_deferred_confirmation(interaction: &ApplicationCommandInteraction, context: &Context, content: String, custom_id: String, label: &str) {
+            if let Err(why) = interaction
+            .create_followup_message(context, |response| {
+                response
+                .content(content)
+                .components(|components| components.create_action_row(|row| row.create_button(|button| button
+                    .custom_id(custom_id)
+                    .label(label)
+                    .style(ButtonStyle::Danger))))
+            }).await
+            {
+                warn!("Cannot respond to slash command: {}", why);
+            }
+        }
+
+        /// Only the first follow-up takes its visibility from the deferral, later ones need `ephemeral` to match it
+        async fn reply_deferred_embeds(interaction:&ApplicationCommandInteraction, context: &Context, embeds: Vec<CreateEmbed>, ephemeral: bool) {
+            if let Err(why) = interaction
+            .create_followup_message(context, |response| {
+                response
+                .add_embeds(embeds)
+                .ephemeral(ephemeral)
+            }).await
+            {
+                warn!("Cannot respond to slash command: {}", why);
+            }
+        }
+
+        /// Handles commands until the command channel closes. A manager started after a crash first initializes again
+        /// with the context of the last `Command::Initialize`, picking up from what was last persisted.
+        async fn manage(mut message_manager: MessageManager, receiver: Arc<Mutex<Receiver<Command>>>, last_initialize: Arc<Mutex<Option<(Context, bool)>>>, slow_command_threshold: Duration) {
+            let restored = last_initialize.lock().await.clone();
+            if let Some((context, message_content_available)) = restored {
+                message_manager.message_content_available = message_content_available;
+                message_manager.init(&context).await;
+            }
+
+            // Start receiving messages
+            loop {
+                let Some(cmd) = receiver.lock().await.recv().await else { break; };
+                use Command::*;
+                // Measured until the reply is sent, except for replies sent later by spawned tasks
+                let command_name = cmd.name();
+                let started_at = Instant::now();
+                match cmd {
+                    Initialize { context, message_content_available } =>
+                        {
+                            *last_initialize.lock().await = Some((context.clone(), message_content_available));
+                            message_manager.message_content_available = message_content_available;
+                            message_manager.init(&context).await;
+                        },
+                    MessageReceived { context, message } => {message_manager.insert_message(&context, message, true).await;},
+                    TextCommandReceived { command, context, message } => {message_manager.run_text_command(&context, &message, command).await;},
+                    DeletionFinished { message, caller, result } => {message_manager.on_deletion_finished(message, caller, result).await;},
+                    MessageDeleted { context, channel_id, message_id, guild_id: _ } => {message_manager.remove_message(&context, message_id, &channel_id);},
+                    SetLimit { channel, limit, byte_budget, context, interaction } => 
+                        {
+                            let verbosity = message_manager.verbosity(interaction.guild_id);
+                            let target_check = if channel == interaction.channel_id { Ok(()) } else { message_manager.check_target_channel(&context, interaction.guild_id, &channel, true).await };
+                            // Asked before the cooldown starts, so confirming isn't held up by it
+                            let warning = match target_check {
+                                Ok(()) => message_manager.important_channel_warning(&context, interaction.guild_id, &channel).await,
+                                Err(_) => None,
+                            };
+                            if let Some(warning) = warning {
+                                let content = format!("{} Setting a limit will delete messages there. Are you sure?", warning);
+                                reply_deferred_confirmation(&interaction, &context, content, configure::confirm_id(channel, limit, byte_budget), "Configure anyway").await;
+                            } else {
+                                let (report, content) = match (target_check, message_manager.check_cooldown(&channel)) {
+                                    (Err(why), _) => (None, why),
+                                    (_, Some(remaining)) => (None, format!("Please wait {} seconds before changing the limit of <#{}> again.", remaining, channel)),
+                                    (Ok(()), None) => message_manager.configure_channel(&context, &channel, limit, byte_budget, interaction.user.id, Some(&interaction)).await,
+                                };
+                                reply_deferred_outcome(&interaction, &context, verbosity, report.as_ref(), content).await;
+                            }
+                        },
+                    ConfirmSetLimit { channel, limit, byte_budget, context, interaction } =>
+                        {
+                            let verbosity = message_manager.verbosity(interaction.guild_id);
+                            // The channel could have moved or the bot lost access since the prompt
+                            let target_check = if channel == interaction.channel_id { Ok(()) } else { message_manager.check_target_channel(&context, interaction.guild_id, &channel, true).await };
+                            let (report, content) = match (target_check, message_manager.check_cooldown(&channel)) {
+                                (Err(why), _) => (None, why),
+                                (_, Some(remaining)) => (None, format!("Please wait {} seconds before changing the limit of <#{}> again.", remaining, channel)),
+                                (Ok(()), None) => message_manager.configure_channel(&context, &channel, limit, byte_budget, interaction.user.id, None).await,
+                            };
+                            // Replace the confirmation prompt, dropping its button
+                            let content = verbosity.outcome_reply(report.as_ref(), content);
+                            if let Err(why) = interaction
+                                .edit_original_interaction_response(&context, |response| response.content(content).components(|components| components))
+                                .await
+                            {
+                                warn!("Cannot respond to button: {}", why);
+                            }
+                        },
+                    AdjustLimit { delta, context, interaction } =>
+                        {
+                            let verbosity = message_manager.verbosity(interaction.guild_id);
+                            let (report, content) = match message_manager.check_cooldown(&interaction.channel_id) {
+                                Some(remaining) => (None, format!("Please wait {} seconds before changing this channel's limit again.", remaining)),
+                                None => message_manager.adjust_limit(&context, &interaction.channel_id, delta, interaction.user.id).await,
+                            };
+                            reply_deferred_outcome(&interaction, &context, verbosity, report.as_ref(), content).await;
+                        },
+                    AutocompleteChannels { input, guild_id, context, interaction } =>
+                        {
+                            let suggestions = message_manager.autocomplete_channels(&context, guild_id, &input).await;
+                            if let Err(why) = interaction.create_autocomplete_response(&context.http, |response| {
+                                for (name, value) in suggestions {
+                                    response.add_string_choice(name, value);
+                                }
+                                response
+                            }).await {
+                                warn!("Cannot respond to autocomplete: {}", why);
+                            }
+                        },
+                    CopyConfig { source, target, context, interaction } =>
+                        {
+                            let content = message_manager.copy_config(&context, interaction.guild_id, &source, &target, interaction.user.id).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetMultipleLimits { limit, channels, context, interaction } =>
+                        {
+                            let content = message_manager.set_multiple_limits(&context, interaction.guild_id, &channels, limit, interaction.user.id).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    RemoveLimit { channel, context, interaction } => 
+                        {
+                            let verbosity = message_manager.verbosity(interaction.guild_id);
+                            let target_check = if channel == interaction.channel_id { Ok(()) } else { message_manager.check_target_channel(&context, interaction.guild_id, &channel, false).await };
+                            let (report, content) = match (target_check, message_manager.check_cooldown(&channel)) {
+                                (Err(why), _) => (None, why),
+                                (_, Some(remaining)) => (None, format!("Please wait {} seconds before changing the limit of <#{}> again.", remaining, channel)),
+                                (Ok(()), None) => {
+                                    let result = message_manager.remove_limit(&channel, interaction.user.id).await;
+                                    let content = outcome_message(&result);
+                                    (result.ok(), content)
+                                },
+                            };
+                            reply_deferred_outcome(&interaction, &context, verbosity, report.as_ref(), content).await;
+                        },
+                    RemoveAllLimits { guild_id, context, interaction } =>
+                        {
+                            let content = message_manager.remove_all_limits(&context, &guild_id, interaction.user.id).await;
+                            // Replace the confirmation prompt, dropping its button
+                            if let Err(why) = interaction
+                                .edit_original_interaction_response(&context, |response| response.content(content).components(|components| components))
+                                .await
+                            {
+                                warn!("Cannot respond to button: {}", why);
+                            }
+                        },
+                    GetStatus { text: true, public, context, interaction } =>
+                        {
+                            let names = message_manager.resolve_channel_names(&context, interaction.guild_id).await;
+                            let content = message_manager.get_status(&names);
+                            reply_deferred(&interaction, &context, content, !public).await;
+                        },
+                    GetStatus { text: false, public, context, interaction } =>
+                        {
+                            let names = message_manager.resolve_channel_names(&context, interaction.guild_id).await;
+                            for embeds in message_manager.get_status_embeds(&names).chunks(STATUS_EMBEDS_PER_MESSAGE) {
+                                reply_deferred_embeds(&interaction, &context, embeds.to_vec(), !public).await;
+                            }
+                        },
+                    GetVersion { uptime, context, interaction } =>
+                        {
+                            let content = message_manager.version(uptime);
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    GetChannelInfo { channel, context, interaction } =>
+                        {
+                            let name = message_manager.channel_name(&context, &channel).await;
+                            let content = message_manager.channel_info(&channel, &name);
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    TestDelete { context, interaction } =>
+                        {
+                            match message_manager.post_test_message(&context, &interaction.channel_id).await {
+                                Ok(message) => {
+                                    let reason = message_manager.deletion_reasons.for_channel(&context, interaction.channel_id);
+                                    tokio::spawn(delete_test_message(context, message, interaction, message_manager.dry_run, reason));
+                                },
+                                Err(content) => reply_deferred(&interaction, &context, content, true).await,
+                            }
+                        },
+                    Reload { context, interaction } =>
+                        {
+                            let content = message_manager.reload(&context, interaction.user.id).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    DebugQueue { channel, context, interaction } =>
+                        {
+                            let content = message_manager.debug_queue(&channel);
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    Trim { count, context, interaction } =>
+                        {
+                            let content = message_manager.trim(&context, &interaction.channel_id, count, Some(&interaction)).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    ChannelPinsUpdated { context, channel } => {message_manager.on_pins_updated(&context, channel).await;},
+                    RefetchPins { context, channel } => {message_manager.on_pins_refetch(&context, channel).await;},
+                    MessagesDeleted { context, channel_id, message_ids, guild_id: _ } => {message_manager.remove_messages(&context, message_ids, &channel_id);},
+                    PersistQueues => {message_manager.persist_queues().await;},
+                    BackupDatabase => {message_manager.backup_database();},
+                    Flush { done } =>
+                        {
+                            message_manager.persist_queues().await;
+                            let _ = done.send(());
+                        },
+                    SetChannelAccess { channel, access, context, interaction } =>
+                        {
+                            let content = message_manager.set_channel_access(&channel, access).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    TempRaiseLimit { limit, minutes, context, interaction } =>
+                        {
+                            let content = match message_manager.check_cooldown(&interaction.channel_id) {
+                                Some(remaining) => format!("Please wait {} seconds before changing this channel's limit again.", remaining),
+                                None => message_manager.temp_raise_limit(&context, &interaction.channel_id, limit, minutes, interaction.user.id).await,
+                            };
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    ApplyScheduledReverts => {message_manager.apply_scheduled_reverts().await;},
+                    Snooze { duration, context, interaction } =>
+                        {
+                            let content = message_manager.snooze(&context, &interaction.channel_id, duration).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    ResumeSnoozed => {message_manager.resume_snoozed().await;},
+                    CheckPermissions => {message_manager.check_permissions().await;},
+                    MemberUpdated { guild_id, user_id } => {message_manager.on_member_updated(guild_id, user_id);},
+                    UserBanned { guild_id, user_id, context } => {message_manager.on_user_banned(&context, guild_id, user_id).await;},
+                    SetDeletionReason { guild_id, reason, context, interaction } =>
+                        {
+                            let content = message_manager.set_deletion_reason(&guild_id, reason).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetPurgeOnBan { guild_id, enabled, context, interaction } =>
+                        {
+                            let content = message_manager.set_purge_on_ban(&guild_id, enabled).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    RolesUpdated { guild_id } => {message_manager.on_roles_updated(guild_id);},
+                    AuditQueues => {message_manager.audit_queues();},
+                    DropPhantoms { phantoms } => {message_manager.drop_phantoms(phantoms);},
+                    EvictBacklog => {message_manager.evict_backlog().await;},
+                    UnmanageIdleChannels => {message_manager.unmanage_idle_channels().await;},
+                    SetIdleUnmanage { guild_id, days, context, interaction } =>
+                        {
+                            let content = message_manager.set_idle_unmanage(&guild_id, days).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetAnnounceChanges { guild_id, enabled, context, interaction } =>
+                        {
+                            let content = message_manager.set_announce_changes(&guild_id, enabled).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetConfirmImportant { guild_id, enabled, context, interaction } =>
+                        {
+                            let content = message_manager.set_confirm_important(&guild_id, enabled).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetFullnessWarning { guild_id, warning, context, interaction } =>
+                        {
+                            let content = message_manager.set_fullness_warning(&guild_id, warning).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetKeepOldest { count, context, interaction } =>
+                        {
+                            let content = message_manager.set_keep_oldest(&context, &interaction.channel_id, count).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetHeavyRule { rule, context, interaction } =>
+                        {
+                            let content = message_manager.set_heavy_rule(&context, &interaction.channel_id, rule).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetDeleteDuplicates { enabled, context, interaction } =>
+                        {
+                            let content = message_manager.set_delete_duplicates(&interaction.channel_id, enabled).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetAlwaysKeepLatest { enabled, context, interaction } =>
+                        {
+                            let content = message_manager.set_always_keep_latest(&context, &interaction.channel_id, enabled).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetTombstone { enabled, context, interaction } =>
+                        {
+                            let content = message_manager.set_tombstone(&interaction.channel_id, enabled).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetPinsCountTowardLimit { enabled, context, interaction } =>
+                        {
+                            let content = message_manager.set_pins_count_toward_limit(&context, &interaction.channel_id, enabled).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetIgnoredTypes { guild_id, types, context, interaction } =>
+                        {
+                            let content = message_manager.set_ignored_types(&guild_id, types).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetVerbosity { guild_id, verbosity, context, interaction } =>
+                        {
+                            let content = message_manager.set_verbosity(&guild_id, verbosity).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetTimezone { guild_id, timezone, context, interaction } =>
+                        {
+                            let content = message_manager.set_timezone(&guild_id, timezone).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    Leaderboard { metric, count, context, interaction } =>
+                        {
+                            let content = message_manager.leaderboard(metric, count);
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    RecentDeletes { channel, count, context, interaction } =>
+                        {
+                            let content = message_manager.recent_deletes(&channel, count);
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    AuditByUser { user_id, page, context, interaction } =>
+                        {
+                            let content = message_manager.audit_by_user(&context, interaction.guild_id, user_id, page).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    ResetStats { all, context, interaction } =>
+                        {
+                            let channel = if all { None } else { Some(interaction.channel_id) };
+                            let content = message_manager.reset_stats(&context, interaction.guild_id, channel).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetSystemMessagePolicy { policy, context, interaction } =>
+                        {
+                            let content = message_manager.set_system_message_policy(&interaction.channel_id, policy).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetRetentionDirection { direction, context, interaction } =>
+                        {
+                            let content = message_manager.set_retention_direction(&interaction.channel_id, direction).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    SetAutoconfigPattern { guild_id, pattern, limit, context, interaction } =>
+                        {
+                            let content = message_manager.set_autoconfig_pattern(&context, &guild_id, pattern, limit).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    ChannelChanged { context, channel } => {message_manager.on_channel_changed(&context, &channel).await;},
+                    ThreadCreated { parent, thread } => {message_manager.on_thread_created(&parent, thread);},
+                    SetDeleteMessagesWithThreads { delete, context, interaction } =>
+                        {
+                            let content = message_manager.set_delete_messages_with_threads(&interaction.channel_id, delete).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    UpdateBlockedKeywords { action, context, interaction } =>
+                        {
+                            let content = message_manager.update_blocked_keywords(&interaction.channel_id, action).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    KeepMessage { channel, message, keep, context, interaction } =>
+                        {
+                            let target_check = if channel == interaction.channel_id { Ok(()) } else { message_manager.check_target_channel(&context, interaction.guild_id, &channel, false).await };
+                            let content = if let Err(reason) = target_check {
+                                reason
+                            } else if keep {
+                                message_manager.keep_message(&context, &channel, message).await
+                            } else {
+                                message_manager.unkeep_message(&context, &channel, message).await
+                            };
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                    UpdateProtectedRoles { action, context, interaction } =>
+                        {
+                            let content = message_manager.update_protected_roles(&context, &interaction.channel_id, action).await;
+                            reply_deferred(&interaction, &context, content, true).await;
+                        },
+                }
+                let elapsed = started_at.elapsed();
+                debug!("Handled {} in {:?}", command_name, elapsed);
+                if !slow_command_threshold.is_zero() && elapsed > slow_command_threshold {
+                    warn!("{} took {:?} to handle (over the {:?} threshold)", command_name, elapsed, slow_command_threshold);
+                }
+            }
+        }
+        let limit_cooldown = self.limit_cooldown;
+        let config_webhook = self.config_webhook.clone();
+        let require_database = self.require_database;
+        let purge_summary_dm = self.purge_summary_dm;
+        let database_path = self.database_path.clone();
+        let deletion_rate = self.deletion_rate;
+        let keep_stale_channels = self.keep_stale_channels;
+        let seed_config = self.seed_config.clone();
+        let audit_sample_size = self.audit_sample_size;
+        let dry_run = self.dry_run;
+        let unpin_deletion_notices = self.unpin_deletion_notices;
+        let deletion_reasons = self.deletion_reasons.clone();
+        let backups = self.backups.clone();
+        let commands = sender.clone();
+        let deleter = Some(Deleter::spawn(self.delete_workers, dry_run, self.attachment_archive.clone(), deletion_reasons.clone(), sender));
+        let member_cache_size = self.member_cache_size;
+        let slow_command_threshold = self.slow_command_threshold;
+        let persist_batch_size = self.persist_batch_size;
+        let receiver = Arc::new(Mutex::new(receiver));
+        let last_initialize = Arc::new(Mutex::new(None));
+        // Restarts the manager when it panics, rather than leaving the bot connected but doing nothing
+        tokio::spawn(supervise(move |restarts| {
+            // The config file was already applied by the first manager
+            let seed_config = if restarts == 0 { seed_config.clone() } else { None };
+            let message_manager: MessageManager = MessageManager {limit_cooldown, config_webhook: config_webhook.clone(), require_database, purge_summary_dm, database_path: database_path.clone(), deletion_rate, keep_stale_channels, seed_config, audit_sample_size, dry_run, deleter: deleter.clone(), unpin_deletion_notices, member_cache: MemberCache::new(member_cache_size), persist_batch_size, deletion_reasons: deletion_reasons.clone(), backups: backups.clone(), commands: Some(commands.clone()), ..Default::default()};
+            manage(message_manager, receiver.clone(), last_initialize.clone(), slow_command_threshold)
+        }, MANAGER_STABLE_PERIOD));
+    }
+}
+
+/// Runs the manager `start` makes (given how many times it was restarted) until it returns, starting a new one when it panics.
+/// Once it crashed `MANAGER_MAX_RESTARTS` times in a row, each time before `stable_period`, the command channel is dropped
+/// along with it, and commands sent from then on are answered as degraded.
+async fn supervise<F, Fut>(mut start: F, stable_period: Duration) where F: FnMut(u32) -> Fut, Fut: Future<Output = ()> + Send + 'static {
+    let mut restarts = 0;
+    loop {
+        let started_at = Instant::now();
+        match tokio::spawn(start(restarts)).await {
+            Ok(()) => break,
+            Err(error) => {
+                if started_at.elapsed() >= stable_period {
+                    restarts = 0;
+                }
+                if restarts >= MANAGER_MAX_RESTARTS {
+                    error!("Message manager crashed {} times, giving up: {}", restarts + 1, error);
+                    break;
+                }
+                restarts += 1;
+                error!("Message manager crashed, restarting it in {:?} ({}/{}): {}", MANAGER_RESTART_DELAY, restarts, MANAGER_MAX_RESTARTS, error);
+                tokio::time::sleep(MANAGER_RESTART_DELAY).await;
+            },
+        }
+    }
+}
+
+impl MessageManager {
+    pub async fn init(&mut self, http: &Context) {
+        // Initiate a connection to the database file, creating the file if required.
+        let database = connect_database(&self.database_path).await;
+        let database = match database {
+            Ok(database) => database,
+            Err(error) if self.require_database => panic!("Couldn't connect to database {}: {}", self.database_path.display(), error),
+            Err(error) => {
+                // Channels can still be managed, they just won't survive a restart
+                error!("!!! Couldn't connect to database, running in memory-only mode: no configuration will be persisted! ({})", error);
+                self.context = Some(http.clone());
+                self.initialized = true;
+                self.apply_seed_config(http).await;
+                return;
+            }
+        };
+
+        // Run migrations, which updates the database's schema to the latest version.
+        // They are embedded at build time, so the working directory doesn't matter here.
+        let migrator = sqlx::migrate!("./migrations");
+        let target_version = migrator.iter().map(|migration| migration.version).max();
+        let current_version = applied_schema_version(&database).await;
+        info!("Database schema is at version {} (target {})", format_schema_version(current_version), format_schema_version(target_version));
+        if let Err(error) = migrator.run(&database).await {
+            match &error {
+                MigrateError::VersionMismatch(version) => error!("Migration {} was edited after being applied (checksum mismatch)", version),
+                MigrateError::VersionMissing(version) => error!("Migration {} was applied but is missing from the migrations directory", version),
+                MigrateError::Dirty(version) => error!("Migration {} was partially applied and needs to be fixed manually", version),
+                _ => {
+                    // The failing migration is the first one after the last applied version
+                    let applied_version = applied_schema_version(&database).await;
+                    let failed_migration = migrator.iter().find(|migration| Some(migration.version) > applied_version);
+                    match failed_migration {
+                        Some(migration) => error!("Migration {} ({}) failed: {}", migration.version, migration.description, error),
+                        None => error!("Migrations failed: {}", error),
+                    }
+                },
+            }
+            panic!("Couldn't run database migrations: {}", error);
+        }
+        self.schema_version = applied_schema_version(&database).await;
+        info!("Database schema is now at version {}", format_schema_version(self.schema_version));
+
+        // Load the allowlist/denylist before creating any queue
+        match sqlx::query_as::<_, ChannelAccessDatabaseEntry>("SELECT * FROM channel_access").fetch_all(&database).await {
+            Ok(entries) => {
+                for entry in entries {
+                    let Ok(chn) = entry.channel_id.parse::<u64>() else {
+                        error!("Unparseable channel id in database: {}", entry.channel_id);
+                        continue;
+                    };
+                    if entry.allowed {
+                        self.allowed_channels.insert(ChannelId::from(chn));
+                    } else {
+                        self.denied_channels.insert(ChannelId::from(chn));
+                    }
+                }
+                debug!("Loaded {} allowed and {} denied channels", self.allowed_channels.len(), self.denied_channels.len());
+            },
+            Err(error) => error!("Couldn't load channel access list from database: {}", error),
+        };
+
+        match sqlx::query_as::<_, BlockedKeywordDatabaseEntry>("SELECT * FROM blocked_keywords").fetch_all(&database).await {
+            Ok(entries) => {
+                for entry in entries {
+                    let Ok(chn) = entry.channel_id.parse::<u64>() else {
+                        error!("Unparseable channel id in database: {}", entry.channel_id);
+                        continue;
+                    };
+                    self.blocked_keywords.entry(ChannelId::from(chn)).or_default().push(entry.keyword);
+                }
+                debug!("Loaded blocked keywords for {} channels", self.blocked_keywords.len());
+            },
+            Err(error) => error!("Couldn't load blocked keywords from database: {}", error),
+        };
+
+        match sqlx::query_as::<_, ProtectedRoleDatabaseEntry>("SELECT * FROM protected_roles").fetch_all(&database).await {
+            Ok(entries) => {
+                for entry in entries {
+                    let (Ok(chn), Ok(role)) = (entry.channel_id.parse::<u64>(), entry.role_id.parse::<u64>()) else {
+                        error!("Unparseable protected role in database: {} ({})", entry.role_id, entry.channel_id);
+                        continue;
+                    };
+                    self.protected_roles.entry(ChannelId::from(chn)).or_default().push(RoleId::from(role));
+                }
+                debug!("Loaded protected roles for {} channels", self.protected_roles.len());
+            },
+            Err(error) => error!("Couldn't load protected roles from database: {}", error),
+        };
+
+        match sqlx::query_as::<_, AutoconfigPatternDatabaseEntry>("SELECT * FROM autoconfig_patterns").fetch_all(&database).await {
+            Ok(entries) => {
+                for entry in entries {
+                    let Ok(guild) = entry.guild_id.parse::<u64>() else {
+                        error!("Unparseable guild id in database: {}", entry.guild_id);
+                        continue;
+                    };
+                    self.autoconfig_patterns.entry(GuildId::from(guild)).or_default().push((entry.pattern, entry.channel_limit as usize));
+                }
+                debug!("Loaded autoconfig patterns for {} guilds", self.autoconfig_patterns.len());
+            },
+            Err(error) => error!("Couldn't load autoconfig patterns from database: {}", error),
+        };
+
+        match sqlx::query_as::<_, ScheduledRevertDatabaseEntry>("SELECT * FROM scheduled_reverts").fetch_all(&database).await {
+            Ok(entries) => {
+                for entry in entries {
+                    let Ok(chn) = entry.channel_id.parse::<u64>() else {
+                        error!("Unparseable channel id in database: {}", entry.channel_id);
+                        continue;
+                    };
+                    let revert = ScheduledRevert { original_limit: entry.original_limit as usize, raised_limit: entry.raised_limit as usize, revert_at: entry.revert_at };
+                    self.scheduled_reverts.insert(ChannelId::from(chn), revert);
+                }
+                debug!("Loaded {} scheduled reverts", self.scheduled_reverts.len());
+            },
+            Err(error) => error!("Couldn't load scheduled reverts from database: {}", error),
+        };
+
+        match sqlx::query_as::<_, ProtectedMessageDatabaseEntry>("SELECT * FROM protected_messages").fetch_all(&database).await {
+            Ok(entries) => {
+                for entry in entries {
+                    let (Ok(chn), Ok(msg)) = (entry.channel_id.parse::<u64>(), entry.message_id.parse::<u64>()) else {
+                        error!("Unparseable protected message in database: {} ({})", entry.message_id, entry.channel_id);
+                        continue;
+                    };
+                    self.pending_protected_oldest.entry(ChannelId::from(chn)).or_default().insert(MessageId::from(msg));
+                }
+                debug!("Loaded protected messages for {} channels", self.pending_protected_oldest.len());
+            },
+            Err(error) => error!("Couldn't load protected messages from database: {}", error),
+        };
+
+        match sqlx::query_as::<_, ProtectedMessageDatabaseEntry>("SELECT * FROM kept_messages").fetch_all(&database).await {
+            Ok(entries) => {
+                for entry in entries {
+                    let (Ok(chn), Ok(msg)) = (entry.channel_id.parse::<u64>(), entry.message_id.parse::<u64>()) else {
+                        error!("Unparseable kept message in database: {} ({})", entry.message_id, entry.channel_id);
+                        continue;
+                    };
+                    self.pending_kept.entry(ChannelId::from(chn)).or_default().insert(MessageId::from(msg));
+                }
+                debug!("Loaded kept messages for {} channels", self.pending_kept.len());
+            },
+            Err(error) => error!("Couldn't load kept messages from database: {}", error),
+        };
+
+        match sqlx::query_as::<_, GuildSettingsDatabaseEntry>("SELECT * FROM guild_settings").fetch_all(&database).await {
+            Ok(entries) => {
+                for entry in entries {
+                    let Ok(guild) = entry.guild_id.parse::<u64>() else {
+                        error!("Unparseable guild id in database: {}", entry.guild_id);
+                        continue;
+                    };
+                    if let Some(idle_secs) = entry.unmanage_after_idle {
+                        self.unmanage_after_idle.insert(GuildId::from(guild), Duration::from_secs(idle_secs as u64));
+                    }
+                    if entry.announce_changes {
+                        self.announce_changes.insert(GuildId::from(guild));
+                    }
+                    if !entry.confirm_important_channels {
+                        self.skip_important_confirmation.insert(GuildId::from(guild));
+                    }
+                    if entry.purge_on_ban {
+                        self.purge_on_ban.insert(GuildId::from(guild));
+                    }
+                    if entry.deletion_reason.is_some() {
+                        self.deletion_reasons.set(GuildId::from(guild), entry.deletion_reason);
+                    }
+                    match entry.warning_channel.as_ref().map(|warning_channel| warning_channel.parse::<u64>()) {
+                        Some(Ok(log_channel)) if entry.warning_threshold > 0 => {
+                            let warning = FullnessWarning { log_channel: ChannelId::from(log_channel), threshold: entry.warning_threshold };
+                            self.fullness_warnings.insert(GuildId::from(guild), warning);
+                        },
+                        Some(Err(_)) => error!("Unparseable warning channel id in database: {:?}", entry.warning_channel),
+                        _ => {},
+                    }
+                    if let Some(ignored_message_types) = entry.ignored_message_types.as_ref() {
+                        match parse_ignored_types(ignored_message_types) {
+                            Ok(types) => {
+                                self.ignored_types.insert(GuildId::from(guild), types);
+                            },
+                            Err(error) => error!("Invalid ignored message types in database for {}: {}", guild, error),
+                        }
+                    }
+                    if let Some(timezone) = entry.timezone.as_ref() {
+                        match parse_timezone(timezone) {
+                            Ok(timezone) => {
+                                self.timezones.insert(GuildId::from(guild), timezone);
+                            },
+                            Err(error) => error!("Invalid timezone in database for {}: {}", guild, error),
+                        }
+                    }
+                    match Verbosity::parse(&entry.verbosity) {
+                        Some(Verbosity::Verbose) => {},
+                        Some(verbosity) => {
+                            self.verbosities.insert(GuildId::from(guild), verbosity);
+                        },
+                        None => error!("Unknown verbosity in database for {}: {}", guild, entry.verbosity),
+                    }
+                }
+                debug!("Loaded idle unmanage settings for {} guilds and announcement settings for {} guilds", self.unmanage_after_idle.len(), self.announce_changes.len());
+            },
+            Err(error) => error!("Couldn't load guild settings from database: {}", error),
+        };
+
+        let mut deleted_counts = HashMap::new();
+        match sqlx::query_as::<_, ChannelStatsDatabaseEntry>("SELECT * FROM channel_stats").fetch_all(&database).await {
+            Ok(entries) => {
+                for entry in entries {
+                    let Ok(chn) = entry.channel_id.parse::<u64>() else {
+                        error!("Unparseable channel id in database: {}", entry.channel_id);
+                        continue;
+                    };
+                    deleted_counts.insert(ChannelId::from(chn), entry.deleted_count as usize);
+                }
+                debug!("Loaded statistics for {} channels", deleted_counts.len());
+            },
+            Err(error) => error!("Couldn't load channel statistics from database: {}", error),
+        };
+
+        let mut deletion_logs: HashMap<ChannelId, VecDeque<DeletedMessage>> = HashMap::new();
+        match sqlx::query_as::<_, DeletionLogDatabaseEntry>("SELECT * FROM deletion_log ORDER BY deleted_at").fetch_all(&database).await {
+            Ok(entries) => {
+                for entry in entries {
+                    let (Ok(chn), Ok(msg), Ok(timestamp)) = (entry.channel_id.parse::<u64>(), entry.message_id.parse::<u64>(), Timestamp::parse(&entry.timestamp)) else {
+                        error!("Unparseable deletion log entry in database: {} ({})", entry.message_id, entry.channel_id);
+                        continue;
+                    };
+                    let author_id = entry.author_id.as_ref().and_then(|author_id| author_id.parse::<u64>().ok()).map(UserId::from);
+                    deletion_logs.entry(ChannelId::from(chn)).or_default().push_back(DeletedMessage { id: MessageId::from(msg), author_id, timestamp, deleted_at: entry.deleted_at });
+                }
+                debug!("Loaded deletion logs for {} channels", deletion_logs.len());
+            },
+            Err(error) => error!("Couldn't load deletion logs from database: {}", error),
+        };
+
+        let query_result = match sqlx::query_as::<_, ChannelLimitDatabaseEntry>("SELECT * FROM channel_limits").fetch_all(&database).await {
+            Ok(entries) => entries,
+            Err(error) => {
+                error!("Couldn't load channel limits from database: {}", error);
+                Vec::new()
+            }
+        };
+        debug!("Initializing {} queues from database", query_result.len());
+        let mut stale_channels = Vec::new();
+        for line in query_result {
+            if let Ok(chn) = line.channel_id.parse::<u64>() {
+                let channel = ChannelId::from(chn);
+                if !self.is_channel_permitted(&channel) {
+                    warn!("Skipping channel {} which is no longer permitted to be managed", channel);
+                    continue;
+                }
+                match channel.to_channel(http).await {
+                    Err(error) if is_channel_gone(&error) => {
+                        warn!("Channel {} was deleted or can no longer be seen: {}", channel, error);
+                        stale_channels.push(channel);
+                        continue;
+                    },
+                    // Anything else may well be temporary, so try to restore the channel anyway
+                    Err(error) => debug!("Cannot check channel {}: {}", channel, error),
+                    Ok(Channel::Guild(guild_channel)) if line.guild_id.is_none() => backfill_guild_id(&database, &channel, guild_channel.guild_id).await,
+                    Ok(_) => {},
+                }
+                if line.auto_configured {
+                    self.auto_configured_channels.insert(channel);
+                }
+                match RetentionDirection::parse(&line.retention_direction) {
+                    Some(direction) => {
+                        self.pending_retention.insert(channel, direction);
+                    },
+                    None => error!("Unknown retention direction in database for {}: {}", channel, line.retention_direction),
+                }
+                let init_result = match self.restore_queue(http, &channel, line.channel_limit as usize, &database).await {
+                    Some(restore_result) => restore_result,
+                    // Nothing was persisted for this channel, so walk its history instead
+                    None => outcome_message(&self.update_limit(http, &channel, line.channel_limit as usize, true, None, None).await),
+                };
+                debug!("{}", init_result);
+                if let Some(cq) = self.channel_queues.get_mut(&channel) {
+                    cq.deleted += deleted_counts.get(&channel).copied().unwrap_or(0);
+                    // Anything deleted while restoring is newer than the persisted log
+                    let mut deletion_log = deletion_logs.remove(&channel).unwrap_or_default();
+                    deletion_log.extend(cq.deletion_log.drain(..));
+                    cq.deletion_log = deletion_log;
+                    cq.apply_settings(&channel, &line, self.message_content_available);
+                    cq.evict_excess(http, "init");
+                }
+            } else {
+                error!("Unparseable channel id in database: {}", line.channel_id);
+            }
+        }
+        info!("Finished initializing queues from database");
+        if !stale_channels.is_empty() {
+            if self.keep_stale_channels {
+                info!("Kept {} stale channel limits", stale_channels.len());
+            } else {
+                match delete_channel_rows(&database, &stale_channels, http.cache.current_user_id()).await {
+                    Ok(()) => info!("Pruned {} stale channel limits", stale_channels.len()),
+                    Err(error) => error!("Failed to prune stale channel limits: {}", error),
+                }
+            }
+        }
+        self.pending_protected_oldest.clear();
+        self.pending_kept.clear();
+        self.pending_retention.clear();
+
+        self.database = Some(database);
+        self.context = Some(http.clone());
+        self.initialized = true;
+
+        // Reverts and snoozes that came due while we were offline are applied right away
+        self.apply_scheduled_reverts().await;
+        self.resume_snoozed().await;
+
+        // Pick up channels created or renamed into a pattern while we were offline
+        let pattern_guilds: Vec<GuildId> = self.autoconfig_patterns.keys().cloned().collect();
+        for guild_id in pattern_guilds {
+            self.apply_autoconfig_patterns(http, &guild_id).await;
+        }
+
+        self.apply_seed_config(http).await;
+    }
+
+    /// Reconciles the queues with `channel_limits`, for when it was edited behind our back:
+    /// channels that appeared are set up (walking their history), changed limits are applied
+    /// and channels that disappeared stop being managed. The other settings are re-read too.
+    pub async fn reload(&mut self, ctx: &Context, user_id: UserId) -> String {
+        let Some(db) = self.database.clone() else {
+            error!("Database is not initialized");
+            return "Database is not initialized, please try again later".to_string();
+        };
+        let entries = match sqlx::query_as::<_, ChannelLimitDatabaseEntry>("SELECT * FROM channel_limits").fetch_all(&db).await {
+            Ok(entries) => entries,
+            Err(error) => {
+                error!("Couldn't load channel limits from database: {}", error);
+                return "Failed to read the channel limits, nothing was changed".to_string();
+            }
+        };
+
+        let mut lines = Vec::new();
+        let mut stored = HashSet::new();
+        let (mut added, mut updated) = (0, 0);
+        for line in entries {
+            let Ok(chn) = line.channel_id.parse::<u64>() else {
+                error!("Unparseable channel id in database: {}", line.channel_id);
+                continue;
+            };
+            let channel = ChannelId::from(chn);
+            stored.insert(channel);
+            let limit = line.channel_limit as usize;
+            let old_limit = self.channel_queues.get(&channel).map(|cq| cq.limit);
+            if old_limit.is_none() {
+                if let Some(direction) = RetentionDirection::parse(&line.retention_direction) {
+                    // Applied before the walk, so it keeps the right end of the history
+                    self.pending_retention.insert(channel, direction);
+                }
+            }
+            if old_limit != Some(limit) {
+                let result = self.update_limit(ctx, &channel, limit, false, Some(user_id), None).await;
+                match (&result, old_limit) {
+                    (Ok(_), None) => added += 1,
+                    (Ok(_), Some(_)) => updated += 1,
+                    (Err(_), _) => {},
+                }
+                lines.push(outcome_message(&result));
+            }
+            let message_content_available = self.message_content_available;
+            if let Some(cq) = self.channel_queues.get_mut(&channel) {
+                cq.apply_settings(&channel, &line, message_content_available);
+                cq.evict_excess(ctx, "reload");
+            }
+        }
+        self.pending_retention.clear();
+
+        let removed: Vec<ChannelId> = self.channel_queues.keys().filter(|channel| !stored.contains(channel)).cloned().collect();
+        for channel in removed.iter() {
+            // Its rows are already gone, so only what is in memory is dropped
+            let Some(old_cq) = self.channel_queues.remove(channel) else { continue; };
+            self.pins_cache.remove(channel);
+            self.expected_pin_changes.remove(channel);
+            if let Some(webhook) = self.config_webhook.as_ref() {
+                webhook.notify(channel, Some(old_cq.limit), None, Some(user_id));
+            }
+            lines.push(format!("Removed the limit of <#{}>", channel));
+        }
+
+        info!("Reloaded channel limits for {}: {} added, {} updated, {} removed", user_id, added, updated, removed.len());
+        let mut content = format!("Reloaded channel limits: {} added, {} updated, {} removed", added, updated, removed.len());
+        for line in lines {
+            if content.len() + line.len() + 3 > MESSAGE_LENGTH_LIMIT {
+                content.push_str("\n...");
+                break;
+            }
+            content.push_str("\n- ");
+            content.push_str(&line);
+        }
+        content
+    }
+
+    /// Applies the limits from `CONFIG_FILE`, on top of those restored from the database
+    async fn apply_seed_config(&mut self, http: &Context) {
+        let Some(seed_config) = self.seed_config.take() else { return; };
+        let user_id = http.cache.current_user_id();
+        let mut applied = 0;
+        for (channel, limit) in seed_config.limits.iter() {
+            if !seed_config.override_existing && self.channel_queues.contains_key(channel) {
+                debug!("Channel {} is already configured, ignoring its limit from the config file", channel);
+                continue;
+            }
+            let result = self.update_limit(http, channel, *limit, false, Some(user_id), None).await;
+            if result.is_ok() {
+                applied += 1;
+            }
+            info!("Config file: {}", outcome_message(&result));
+        }
+        info!("Applied {} of {} channel limits from the config file", applied, seed_config.limits.len());
+    }
+
+    /// Restores a channel's queue from its persisted tracked messages, then catches up on messages sent while offline.
+    /// Returns `None` if nothing was persisted for the channel.
+    async fn restore_queue(&mut self, ctx: &Context, channel: &ChannelId, limit: usize, db: &Pool<Sqlite>) -> Option<String> {
+        let entries = match sqlx::query_as::<_, TrackedMessageDatabaseEntry>("SELECT message_id, timestamp, size, author_id FROM tracked_messages WHERE channel_id=?")
+            .bind(channel.to_string())
+            .fetch_all(db).await {
+            Ok(entries) => entries,
+            Err(error) => {
+                error!("Couldn't load tracked messages for {}: {}", channel, error);
+                return None;
+            }
+        };
+        if entries.is_empty() {
+            return None;
+        }
+
+        let mut tracked_messages = Vec::with_capacity(limit);
+        for entry in entries {
+            let author_id = entry.author_id.as_ref().and_then(|author_id| author_id.parse::<u64>().ok()).map(UserId::from);
+            match (entry.message_id.parse::<u64>(), Timestamp::parse(&entry.timestamp)) {
+                (Ok(id), Ok(timestamp)) => tracked_messages.push(TrackedMessage { id: MessageId::from(id), channel_id: *channel, timestamp, size: entry.size as usize, author_id, attachments: Vec::new() }),
+                _ => error!("Unparseable tracked message in database: {} ({})", entry.message_id, entry.timestamp),
+            }
+        }
+        tracked_messages.sort_by_key(TrackedMessage::chronological_key);
+        let restored_count = tracked_messages.len();
+        let newest_message = tracked_messages.last().map(|message| message.id);
+
+        // Messages deleted while we were offline are only pruned once we fail to delete them
+        let mut new_queue = CappedQueue::new(limit, self.deletion_rate, self.deleter());
+        new_queue.queue = VecDeque::from(tracked_messages);
+        new_queue.protected_oldest = self.pending_protected_oldest.remove(channel).unwrap_or_default();
+        new_queue.kept = self.pending_kept.remove(channel).unwrap_or_default();
+        new_queue.retention = self.pending_retention.remove(channel).unwrap_or_default();
+        self.channel_queues.insert(*channel, new_queue);
+
+        match channel.pins(ctx).await {
+            Ok(pinned_messages) => {
+                for pinned_message in pinned_messages {
+                    self.insert_pin(ctx, pinned_message);
+                }
+            },
+            Err(error) => {
+                error!("Uh oh! Error: {}", error);
+            },
+        };
+
+        // Catch up on anything sent after the newest message we know about
+        if let Some(mut after) = newest_message {
+            loop {
+                let mut new_messages = match channel.messages(ctx, |retriever| retriever.after(after).limit(100)).await {
+                    Ok(new_messages) => new_messages,
+                    Err(error) => {
+                        error!("Uh oh! Error: {}", error);
+                        break;
+                    }
+                };
+                new_messages.sort_by_key(|a| a.id);
+                let fetched_count = new_messages.len();
+                for message in new_messages {
+                    after = message.id;
+                    if message.pinned {
+                        continue;
+                    }
+                    self.insert_message(ctx, message, true).await;
+                }
+                if fetched_count < 100 {
+                    break;
+                }
+            }
+        }
+
+        Some(format!("Restored channel {} limit to {} ({} tracked messages)", channel, limit, restored_count))
+    }
+
+    /// Saves every queue's tracked messages, statistics and deletion log so they can be restored on the next startup.
+    /// They are only kept in memory in between, and all written in a single transaction.
+    pub async fn persist_queues(&mut self) {
+        if !self.initialized {
+            return;
+        }
+        let Some(db) = self.database.as_ref() else {
+            debug!("Persistence is disabled, not persisting queues");
+            return;
+        };
+        let mut transaction = match db.begin().await {
+            Ok(transaction) => transaction,
+            Err(error) => {
+                error!("Failed to persist queues: {}", error);
+                return;
+            }
+        };
+        for (channel, cq) in self.channel_queues.iter() {
+            if let Err(error) = persist_queue(&mut transaction, channel, cq).await {
+                error!("Failed to persist queue for {}: {}", channel, error);
+            }
+        }
+        match transaction.commit().await {
+            Ok(()) => {
+                debug!("Persisted {} queues ({} deletions since last time)", self.channel_queues.len(), self.unpersisted_deletions);
+                self.unpersisted_deletions = 0;
+            },
+            Err(error) => error!("Failed to persist queues: {}", error),
+        }
+    }
+
+    /// Fetches the channel's pins, reusing the last fetch if it is recent enough
+    async fn fetch_pins(&mut self, ctx: &Context, channel: &ChannelId) -> serenity::Result<Vec<Message>> {
+        if let Some((pins, fetched_at)) = self.pins_cache.get(channel) {
+            if fetched_at.elapsed() < PINS_CACHE_TTL {
+                debug!("Reusing cached pins of {}", channel);
+                return Ok(pins.clone());
+            }
+        }
+        let pins = channel.pins(ctx).await?;
+        self.pins_cache.insert(*channel, (pins.clone(), Instant::now()));
+        Ok(pins)
+    }
+
+    pub async fn on_pins_updated(&mut self, ctx: &Context, channel: ChannelId) {
+        if !self.channel_queues.contains_key(&channel) { return; }
+        if self.take_expected_pin_change(&channel) {
+            debug!("Pins update of {} was expected, skipping reconciliation", channel);
+            return;
+        }
+        self.reconcile_pins(ctx, channel).await;
+    }
+
+    /// The trailing fetch of a burst of pin events, whose cache has just expired
+    pub async fn on_pins_refetch(&mut self, ctx: &Context, channel: ChannelId) {
+        self.pins_refetches.remove(&channel);
+        if !self.channel_queues.contains_key(&channel) { return; }
+        self.reconcile_pins(ctx, channel).await;
+    }
+
+    /// Fetches the pins again once the cache expires, unless that is already planned
+    fn schedule_pins_refetch(&mut self, ctx: &Context, channel: ChannelId) {
+        let (Some(commands), Some((_, fetched_at))) = (self.commands.clone(), self.pins_cache.get(&channel)) else { return; };
+        if !self.pins_refetches.insert(channel) {
+            return;
+        }
+        let delay = PINS_CACHE_TTL.saturating_sub(fetched_at.elapsed());
+        let context = ctx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if commands.send(Command::RefetchPins { context, channel }).await.is_err() {
+                debug!("Manager is gone, dropping the pins refetch of {}", channel);
+            }
+        });
+    }
+
+    async fn reconcile_pins(&mut self, ctx: &Context, channel: ChannelId) {
+        // Pins changed since the cached fetch would otherwise only be noticed with the next event
+        if self.pins_cache.get(&channel).is_some_and(|(_, fetched_at)| fetched_at.elapsed() < PINS_CACHE_TTL) {
+            self.schedule_pins_refetch(ctx, channel);
+        }
+        let Ok(updated_pins) = self.fetch_pins(ctx, &channel).await else { return; };
+        if updated_pins.len() > CHANNEL_PIN_LIMIT {
+            warn!("Channel {} has {} pins, more than the expected maximum of {}", channel, updated_pins.len(), CHANNEL_PIN_LIMIT);
+        }
+        let Some(cq) = self.channel_queues.get_mut(&channel) else { return; };
+        // updated_pins.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+
+        let mut added_pins = VecDeque::with_capacity(CHANNEL_PIN_LIMIT);
+        let mut removed_pins: Vec<TrackedMessage> = Vec::with_capacity(CHANNEL_PIN_LIMIT);
+
+        // First we check for known pins missing from the channel
+        for existing_pin in cq.pins.iter() {
+            if !updated_pins.iter().any(|channel_pin| channel_pin.id == existing_pin.id) && !cq.protected_oldest.contains(&existing_pin.id) && !cq.kept.contains(&existing_pin.id) {
+                // If the updated pin list does not contain the known `existing_pin` then it was removed
+                // (unless it is one of the protected oldest or kept messages, which never go back in the queue)
+                removed_pins.push(existing_pin.clone());
+            }
+        }
+        debug!("Removed {} pins", removed_pins.len());
+        let unpinned: Vec<MessageId> = removed_pins.iter().map(|message| message.id).collect();
+
+        // Then we check for new pins missing from the queue
+        for channel_pin in updated_pins.iter() {
+            if !cq.pins.iter().any(|existing_pin| channel_pin.id == existing_pin.id) {
+                // If the local pin list does not contain the new `channel_pin` then it was added
+                added_pins.push_back(channel_pin.id);
+            }
+        }
+        debug!("Added {} pins", added_pins.len());
+
+        // We remove the newly-added pins (a.k.a. we retain the non-newly-added pins)
+        cq.queue.retain(|message| !added_pins.contains(&message.id));
+        cq.heavy.retain(|message| !added_pins.contains(&message.id));
+
+        // We re-add the newly-removed pins, sort them, and discard the excess
+        for message in cq.queue.drain(..) {
+            removed_pins.push(message);
+        }
+        debug!("Temporary queue has {} messages (limit={})", removed_pins.len(), cq.limit);
+        removed_pins.sort_by_key(TrackedMessage::chronological_key);
+        
+        // Move it back from temporary Vec
+        cq.queue = VecDeque::from(removed_pins);
+        cq.pins = updated_pins.iter().map(TrackedMessage::from).collect();
+        debug!("Local pins list now has {} items", cq.pins.len());
+
+        cq.evict_excess(ctx, "on_pins_updated");
+
+        // Unpinned messages that went straight out of the queue are being deleted because of the unpin
+        if self.unpin_deletion_notices {
+            for message_id in unpinned {
+                if !cq.queue.iter().any(|message| message.id == message_id) {
+                    self.unpinned_deletions.insert(message_id);
+                }
+            }
+        }
+    }
+
+    pub async fn insert_message(&mut self, ctx: &Context, msg: Message, push_back: bool) {
+        if self.has_protected_role(ctx, &msg) {
+            debug!("Ignoring message {} from {}, who has a protected role", msg.id, msg.author.id);
+            return;
+        }
+        // Blocked keywords are purged on sight, before the message ever reaches the queue
+        if self.is_blocked(&msg) {
+            debug!("Message {} (channel={}) contains a blocked keyword, deleting it", msg.id, msg.channel_id);
+            self.deleter().submit(ctx, TrackedMessage::from(&msg), "insert_message (blocked keyword)");
+            return;
+        }
+
+        if self.announcements.remove(&msg.id) {
+            debug!("Ignoring our own announcement {}", msg.id);
+            return;
+        }
+        if self.is_ignored_type(ctx, &msg) {
+            debug!("Ignoring message {} of ignored type {:?}", msg.id, msg.kind);
+            return;
+        }
+        let Some(cq) = self.channel_queues.get_mut(&msg.channel_id) else {return};
+        self.last_activity.insert(msg.channel_id, Instant::now());
+
+        // Ideally this should not be executed in threads but...
+        // debug!("insert_message {:#?}", msg);
+        match msg.kind {
+            MessageType::ThreadStarterMessage => {
+                debug!("Ignoring message {} of type {:?}", msg.id, msg.kind);
+                return;
+            }
+            _ => {}
+        }
+        if cq.protected_oldest.contains(&msg.id) || cq.kept.contains(&msg.id) {
+            debug!("Ignoring protected message {}", msg.id);
+            return;
+        }
+        if !cq.delete_messages_with_threads && (msg.thread.is_some() || cq.threaded.contains(&msg.id)) {
+            debug!("Ignoring message {} which started a thread", msg.id);
+            cq.threaded.insert(msg.id);
+            return;
+        }
+        if is_system_message(msg.kind) {
+            match cq.system_message_policy {
+                SystemMessagePolicy::Normal => {},
+                SystemMessagePolicy::Keep => {
+                    debug!("Ignoring system message {} of type {:?}", msg.id, msg.kind);
+                    return;
+                },
+                // Snoozed and degraded channels track it like any other message instead
+                SystemMessagePolicy::Delete if cq.is_halted() => {},
+                SystemMessagePolicy::Delete => {
+                    debug!("Deleting system message {} of type {:?}", msg.id, msg.kind);
+                    cq.deleter.submit(ctx, TrackedMessage::from(&msg), "insert_message (system message)");
+                    return;
+                },
+            }
+        }
+        // Only live messages are checked, history walks go from newest to oldest
+        if push_back && cq.delete_duplicates && !cq.is_halted() && cq.is_duplicate(&msg) {
+            debug!("Deleting duplicate message {} from {}", msg.id, msg.author.id);
+            cq.deleter.submit(ctx, TrackedMessage::from(&msg), "insert_message (duplicate)");
+            return;
+        }
+
+        // Nothing is kept, so the message goes right away unless deleting is on hold
+        // (or it must stay as the newest, then it's queued and pushes the previous one out)
+        if cq.is_ephemeral() && !cq.always_keep_latest {
+            if msg.author.id == ctx.cache.current_user_id() {
+                debug!("Ignoring our own message {} in ephemeral channel", msg.id);
+                return;
+            }
+            if !cq.is_halted() && cq.may_delete() {
+                debug!("Deleting message {} from ephemeral channel {}", msg.id, msg.channel_id);
+                cq.expire(ctx, TrackedMessage::from(&msg), "insert_message (ephemeral)");
+                return;
+            }
+            // Otherwise it's queued like any excess message, and deleted once deleting resumes
+        }
+
+        // Commands are handled one at a time, so messages sent while a history walk is running are only
+        // received once it is done, and get inserted against the final limit. The walk may already have
+        // picked up the newest of them though, which must not be tracked twice.
+        if push_back && cq.queue.iter().rev().any(|message| message.id == msg.id) {
+            debug!("Message {} is already tracked", msg.id);
+            return;
+        }
+
+        let is_heavy = cq.heavy_rule.is_some_and(|rule| rule.matches(&msg));
+        let is_out_of_order = cq.queue.back().is_some_and(|newest| newest.chronological_key() > (msg.timestamp, msg.id));
+        if push_back && is_out_of_order {
+            // Replayed (e.g. on reconnection) or late messages can be older than what is tracked,
+            // and eviction relies on the queue being in chronological order
+            debug!("Insert out of order message {} (channel={}; ts={}) chronologically", msg.id, msg.channel_id, msg.timestamp);
+            insert_chronologically(&mut cq.queue, TrackedMessage::from(&msg));
+            if is_heavy {
+                insert_chronologically(&mut cq.heavy, TrackedMessage::from(&msg));
+            }
+        } else if push_back {
+            cq.queue.push_back(TrackedMessage::from(&msg));
+            if is_heavy {
+                cq.heavy.push_back(TrackedMessage::from(&msg));
+            }
+        } else {
+            cq.queue.push_front(TrackedMessage::from(&msg));
+            if is_heavy {
+                cq.heavy.push_front(TrackedMessage::from(&msg));
+            }
+        }
+        debug!("Pushed new message (now {} vs {})", cq.queue.len(), cq.capacity());
+
+        // If queue is now over capacity, remove the oldest message and delete it
+        cq.evict_excess(ctx, "insert_message");
+
+        // History walks fill the queue all at once, only live messages should warn
+        if push_back {
+            self.check_fullness(ctx, &msg).await;
+        }
+    }
+
+    /// Runs a (validated) text command, replying to its message
+    pub async fn run_text_command(&mut self, ctx: &Context, message: &Message, command: TextCommand) {
+        let channel = message.channel_id;
+        let verbosity = self.verbosity(message.guild_id);
+        let (report, content) = match command {
+            // Text commands can't ask for a confirmation, so important channels are left to `/configure`
+            TextCommand::Configure(limit) => match (self.important_channel_warning(ctx, message.guild_id, &channel).await, self.check_cooldown(&channel)) {
+                (Some(warning), _) => (None, format!("{} Setting a limit will delete messages there, please use `/configure` if you are sure.", warning)),
+                (None, Some(remaining)) => (None, format!("Please wait {} seconds before changing this channel's limit again.", remaining)),
+                (None, None) => {
+                    let result = self.update_limit(ctx, &channel, limit as usize, false, Some(message.author.id), None).await;
+                    if let Ok(report) = &result {
+                        info!("Limit of {} set to {} by {}, deleting {} messages", channel, limit, message.author.id, report.deleted());
+                    }
+                    let content = outcome_message(&result);
+                    (result.ok(), content)
+                },
+            },
+            TextCommand::Remove => match self.check_cooldown(&channel) {
+                Some(remaining) => (None, format!("Please wait {} seconds before changing this channel's limit again.", remaining)),
+                None => {
+                    let result = self.remove_limit(&channel, message.author.id).await;
+                    let content = outcome_message(&result);
+                    (result.ok(), content)
+                },
+            },
+            TextCommand::Status => {
+                let names = self.resolve_channel_names(ctx, message.guild_id).await;
+                (None, self.get_status(&names))
+            },
+            TextCommand::Info => {
+                let name = self.channel_name(ctx, &channel).await;
+                (None, self.channel_info(&channel, &name))
+            },
+        };
+        // Messages can be reacted to, so silent guilds get no reply at all
+        if report.is_some() && verbosity == Verbosity::Silent {
+            if let Err(why) = message.react(ctx, ReactionType::Unicode(SILENT_ACK.to_string())).await {
+                warn!("Cannot react to text command: {}", why);
+            }
+            return;
+        }
+        match message.reply(ctx, verbosity.outcome_reply(report.as_ref(), content)).await {
+            // The reply is ours to leave alone, like announcements
+            Ok(reply) => {
+                self.announcements.insert(reply.id);
+            },
+            Err(why) => warn!("Cannot respond to text command: {}", why),
+        }
+    }
+
+    fn deleter(&self) -> Deleter {
+        self.deleter.clone().expect("Delete workers are started with the manager")
+    }
+
+    /// Records the outcome of a handed off deletion. Messages that failed for a transient reason go back
+    /// in their queue, so a later eviction tries again; other failures are only logged, as before the handoff.
+    /// A message someone else deleted first is gone as intended, it's only not counted as ours.
+    async fn on_deletion_finished(&mut self, message: TrackedMessage, caller: &'static str, result: serenity::Result<()>) {
+        if self.unpinned_deletions.remove(&message.id) && result.is_ok() {
+            self.post_unpin_deletion_notice(&message).await;
+        }
+        let Some(cq) = self.channel_queues.get_mut(&message.channel_id) else {
+            match result {
+                Err(error) if is_message_gone(&error) => debug!("{}: Message {} was already deleted", caller, message.id),
+                Err(error) => error!("{}: Failed to delete message: {}", caller, error),
+                Ok(_) => {},
+            }
+            return;
+        };
+        match result {
+            Ok(_) => {
+                cq.record_deletion(&message);
+                self.unpersisted_deletions += 1;
+                if self.persist_batch_size > 0 && self.unpersisted_deletions >= self.persist_batch_size {
+                    self.persist_queues().await;
+                }
+            },
+            Err(error) if is_message_gone(&error) => {
+                debug!("{}: Message {} was already deleted", caller, message.id);
+                // Its creation may have been handled after the handoff, tracking it again
+                cq.queue.retain(|tracked| tracked.id != message.id);
+                cq.heavy.retain(|tracked| tracked.id != message.id);
+            },
+            Err(error) if is_transient(&error) && !cq.queue.iter().any(|tracked| tracked.id == message.id) => {
+                warn!("{}: Failed to delete message {}, it will be retried: {}", caller, message.id, error);
+                insert_chronologically(&mut cq.queue, message);
+            },
+            Err(error) => error!("{}: Failed to delete message: {}", caller, error),
+        }
+    }
+
+    /// Tells the guild's log channel (the one fullness warnings go to) that an unpinned message went because of the limit
+    async fn post_unpin_deletion_notice(&mut self, message: &TrackedMessage) {
+        let Some(ctx) = self.context.clone() else { return; };
+        let Some(guild_id) = ctx.cache.guild_channel(message.channel_id).map(|channel| channel.guild_id) else { return; };
+        let Some(warning) = self.fullness_warnings.get(&guild_id).copied() else {
+            debug!("No log channel in {} for the deletion of unpinned message {}", guild_id, message.id);
+            return;
+        };
+        let author = message.author_id.map_or("an unknown author".to_string(), |author_id| format!("<@{}>", author_id));
+        let content = format!("A recently unpinned message by {} was removed from <#{}> because the channel is over its limit", author, message.channel_id);
+        match warning.log_channel.say(&ctx, content).await {
+            Ok(notice) => {
+                self.announcements.insert(notice.id);
+            },
+            Err(error) => warn!("Cannot post unpin deletion notice for {} in {}: {}", message.channel_id, warning.log_channel, error),
+        }
+    }
+
+    /// Whether the message's type is ignored in its guild, in which case it's neither tracked nor deleted
+    fn is_ignored_type(&self, ctx: &Context, msg: &Message) -> bool {
+        // Messages fetched from the history don't carry their guild
+        let guild_id = msg.guild_id.or_else(|| ctx.cache.guild_channel(msg.channel_id).map(|channel| channel.guild_id));
+        let names = guild_id.and_then(|guild_id| self.ignored_types.get(&guild_id)).map_or(DEFAULT_IGNORED_MESSAGE_TYPES, |names| names.as_slice());
+        IGNORABLE_MESSAGE_TYPES.iter().any(|(name, kinds)| names.contains(name) && kinds.contains(&msg.kind))
+    }
+
+    /// Replaces the timezone the guild's times are shown in
+    pub async fn set_timezone(&mut self, guild_id: &GuildId, timezone: FixedOffset) -> String {
+        let Some(db) = self.database.as_ref() else {
+            error!("Database is not initialized");
+            return "Database is not initialized, please try again later".to_string();
+        };
+        let name = timezone_name(&timezone);
+        let stored = name.clone();
+        let result = retry_write(move || sqlx::query("INSERT INTO guild_settings (guild_id, timezone) VALUES (?, ?) ON CONFLICT(guild_id) DO UPDATE SET timezone=excluded.timezone")
+            .bind(guild_id.to_string())
+            .bind(stored.clone())
+            .execute(db)).await;
+        if let Err(error) = result {
+            error!("Failed to update guild settings: {}", error);
+            return "Failed to update the timezone".to_string();
+        }
+
+        self.timezones.insert(*guild_id, timezone);
+        format!("Times are now shown in {} (currently {})", name, timezone.from_utc_datetime(&Utc::now().naive_utc()).format("%Y-%m-%d %H:%M"))
+    }
+
+    pub async fn set_verbosity(&mut self, guild_id: &GuildId, verbosity: Verbosity) -> String {
+        let Some(db) = self.database.as_ref() else {
+            error!("Database is not initialized");
+            return "Database is not initialized, please try again later".to_string();
+        };
+        let result = retry_write(move || sqlx::query("INSERT INTO guild_settings (guild_id, verbosity) VALUES (?, ?) ON CONFLICT(guild_id) DO UPDATE SET verbosity=excluded.verbosity")
+            .bind(guild_id.to_string())
+            .bind(verbosity.as_str())
+            .execute(db)).await;
+        if let Err(error) = result {
+            error!("Failed to update guild settings: {}", error);
+            return "Failed to update the verbosity".to_string();
+        }
+
+        self.verbosities.insert(*guild_id, verbosity);
+        match verbosity {
+            Verbosity::Verbose => "Limit changes will be confirmed in full".to_string(),
+            Verbosity::Terse => "Limit changes will be confirmed in one short line".to_string(),
+            Verbosity::Silent => "Limit changes will only be confirmed with a checkmark (errors are still shown in full)".to_string(),
+        }
+    }
+
+    /// How much to say when a limit change succeeds in this guild (verbose outside of guilds)
+    pub fn verbosity(&self, guild_id: Option<GuildId>) -> Verbosity {
+        guild_id.and_then(|guild_id| self.verbosities.get(&guild_id).copied()).unwrap_or_default()
+    }
+
+    /// Shows a Unix timestamp (in seconds) as an absolute time in the timezone of the channel's guild
+    fn local_time(&self, channel: &ChannelId, timestamp: i64) -> String {
+        let guild_id = self.context.as_ref().and_then(|ctx| ctx.cache.guild_channel(channel)).map(|channel| channel.guild_id);
+        let timezone = guild_id.and_then(|guild_id| self.timezones.get(&guild_id).copied()).unwrap_or(FixedOffset::east_opt(0).expect("UTC is a valid offset"));
+        match timezone.timestamp_opt(timestamp, 0).single() {
+            Some(time) => format!("{} {}", time.format("%Y-%m-%d %H:%M"), timezone_name(&timezone)),
+            None => format!("<t:{}:f>", timestamp),
+        }
+    }
+
+    /// Replaces the guild's ignored message types
+    pub async fn set_ignored_types(&mut self, guild_id: &GuildId, types: Vec<&'static str>) -> String {
+        let Some(db) = self.database.as_ref() else {
+            error!("Database is not initialized");
+            return "Database is not initialized, please try again later".to_string();
+        };
+        let joined = types.join(",");
+        let result = retry_write(move || sqlx::query("INSERT INTO guild_settings (guild_id, ignored_message_types) VALUES (?, ?) ON CONFLICT(guild_id) DO UPDATE SET ignored_message_types=excluded.ignored_message_types")
+            .bind(guild_id.to_string())
+            .bind(joined.clone())
+            .execute(db)).await;
+        if let Err(error) = result {
+            error!("Failed to update guild settings: {}", error);
+            return "Failed to update the ignored message types".to_string();
+        }
+
+        let content = if types.is_empty() {
+            "Every kind of message is now tracked".to_string()
+        } else {
+            format!("These kinds of messages are now never tracked nor deleted: {}", types.join(", "))
+        };
+        self.ignored_types.insert(*guild_id, types);
+        content
+    }
+
+    /// Posts the guild's fullness warning the first time the channel goes above the threshold
+    async fn check_fullness(&mut self, ctx: &Context, msg: &Message) {
+        let Some(warning) = msg.guild_id.and_then(|guild_id| self.fullness_warnings.get(&guild_id).copied()) else { return; };
+        let Some(cq) = self.channel_queues.get_mut(&msg.channel_id) else { return; };
+        let usage = cq.usage();
+        if usage * 100.0 < warning.threshold as f64 {
+            cq.warning_fired = false;
+            return;
+        }
+        if cq.warning_fired {
+            return;
+        }
+        cq.warning_fired = true;
+
+        let content = format!("<#{}> is nearing its autodelete limit ({} / {} messages, {:.0}% full)", msg.channel_id, cq.queue.len(), cq.limit, usage * 100.0);
+        match warning.log_channel.say(ctx, content).await {
+            Ok(message) => {
+                self.announcements.insert(message.id);
+            },
+            Err(error) => warn!("Cannot post fullness warning for {} in {}: {}", msg.channel_id, warning.log_channel, error),
+        }
+    }
+
+    pub fn remove_message(&mut self, _ctx: &Context, msg_id: MessageId, channel_id: &ChannelId) {
+        let Some(cq) = self.channel_queues.get_mut(channel_id) else {return};
+        self.pins_cache.remove(channel_id);
+        cq.queue.retain(|message| message.id != msg_id);
+        cq.heavy.retain(|message| message.id != msg_id);
+        let pin_count = cq.pins.len();
+        cq.pins.retain(|message| message.id != msg_id);
+        let removed_pins = pin_count.saturating_sub(cq.pins.len());
+        debug!("Queue after remove_message len={}", cq.queue.len());
+        debug!("Pins after remove_message len={}", cq.pins.len());
+        self.expect_pin_changes(channel_id, removed_pins);
+    }
+
+    pub fn remove_messages(&mut self, _ctx: &Context, msg_ids: Vec<MessageId>, channel_id: &ChannelId) {
+        let Some(cq) = self.channel_queues.get_mut(channel_id) else {return};
+        self.pins_cache.remove(channel_id);
+        cq.queue.retain(|message| !msg_ids.contains(&message.id));
+        cq.heavy.retain(|message| !msg_ids.contains(&message.id));
+        let pin_count = cq.pins.len();
+        cq.pins.retain(|message| !msg_ids.contains(&message.id));
+        let removed_pins = pin_count.saturating_sub(cq.pins.len());
+        debug!("Queue after remove_messages len={}", cq.queue.len());
+        debug!("Pins after remove_messages len={}", cq.pins.len());
+        self.expect_pin_changes(channel_id, removed_pins);
+    }
+
+    /// Records pin changes that were just applied locally, so their pins update events don't trigger a reconciliation
+    fn expect_pin_changes(&mut self, channel: &ChannelId, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let pending = match self.expected_pin_changes.get(channel) {
+            Some((pending, expires_at)) if *expires_at > Instant::now() => *pending,
+            _ => 0,
+        };
+        debug!("Expecting {} pins update events for {}", pending + count, channel);
+        self.expected_pin_changes.insert(*channel, (pending + count, Instant::now() + EXPECTED_PIN_CHANGE_TTL));
+    }
+
+    /// Consumes one expected pins update event of the channel, if any is still pending
+    fn take_expected_pin_change(&mut self, channel: &ChannelId) -> bool {
+        let Some((pending, expires_at)) = self.expected_pin_changes.remove(channel) else { return false; };
+        if expires_at <= Instant::now() {
+            return false;
+        }
+        if pending > 1 {
+            self.expected_pin_changes.insert(*channel, (pending - 1, expires_at));
+        }
+        true
+    }
+
+    pub fn insert_pin(&mut self, _ctx: &Context, msg: Message) {
+        let Some(cq) = self.channel_queues.get_mut(&msg.channel_id) else {return};
+        self.pins_cache.remove(&msg.channel_id);
+        if cq.pins.len() == CHANNEL_PIN_LIMIT {
+            warn!("Channel {} now has more pins than the expected maximum of {}", msg.channel_id, CHANNEL_PIN_LIMIT);
+        }
+
+        if cq.pins.is_empty() {
+            // Simply insert it
+            debug!("Insert new pin {} (channel={}; ts={}) at the front", msg.id, msg.channel_id, msg.timestamp);
+            cq.pins.push_front(TrackedMessage::from(&msg));
+            return;
+        }
+
+        // Insert in the correct chronological position
+        let mut index = 0;
+        for queued_msg in cq.pins.iter() {
+            if queued_msg.chronological_key() > (msg.timestamp, msg.id) {
+                debug!("Insert new pin {} (channel={}; ts={}) at idx={} (was msg {}; ts={})", msg.id, msg.channel_id, msg.timestamp, index, queued_msg.id, queued_msg.timestamp);
+                cq.pins.insert(index, TrackedMessage::from(&msg));
+                return;
+            }
+            index += 1;
+        }
+        
+        // If its still not added, we can assume it is the newest
+        debug!("Insert new pin {} (channel={}; ts={}) at the back (now len={})", msg.id, msg.channel_id, msg.timestamp, cq.pins.len());
+        cq.pins.push_back(TrackedMessage::from(&msg));
+    }
+
+    /// The channel's queue, or the standard reply for channels that aren't being autodeleted
+    fn managed_queue(&self, channel: &ChannelId) -> Result<&CappedQueue, String> {
+        self.channel_queues.get(channel).ok_or_else(|| ManagerError::NotManaged(*channel).to_string())
+    }
+
+    fn managed_queue_mut(&mut self, channel: &ChannelId) -> Result<&mut CappedQueue, String> {
+        self.channel_queues.get_mut(channel).ok_or_else(|| ManagerError::NotManaged(*channel).to_string())
+    }
+
+    /// Resolves a channel's name through the cache (falling back to fetching it), remembering it for a while
+    pub async fn channel_name(&mut self, ctx: &Context, channel: &ChannelId) -> String {
+        if let Some((name, resolved_at)) = self.channel_names.get(channel) {
+            if resolved_at.elapsed() < CHANNEL_NAME_TTL {
+                return name.clone();
+            }
+        }
+
+        let resolved = match channel.to_channel_cached(&ctx.cache) {
+            Some(resolved) => Ok(resolved),
+            None => channel.to_channel(ctx).await,
+        };
+        let name = match resolved {
+            Ok(Channel::Guild(guild_channel)) => format!("#{}", guild_channel.name),
+            Ok(Channel::Category(category)) => category.name,
+            Ok(Channel::Private(private_channel)) => format!("DM with {}", private_channel.recipient.name),
+            Ok(_) => channel.to_string(),
+            Err(error) => {
+                // Deleted channels (or ones we can no longer see) are not cached, so we try again next time
+                debug!("Cannot resolve name of channel {}: {}", channel, error);
+                return format!("{} (unknown)", channel);
+            }
+        };
+        self.channel_names.insert(*channel, (name.clone(), Instant::now()));
+        name
+    }
+
+    /// Suggests managed channels whose name starts with the word being typed, as (name, value) choices.
+    /// Each value is the whole input with that word replaced by the channel's mention.
+    /// Only the managed channels of the guild the command is used in are suggested
+    pub async fn autocomplete_channels(&mut self, ctx: &Context, guild_id: Option<GuildId>, input: &str) -> Vec<(String, String)> {
+        let Some(guild_id) = guild_id else { return Vec::new(); };
+        let guild_channels: HashSet<ChannelId> = match guild_id.channels(ctx).await {
+            Ok(channels) => channels.into_keys().collect(),
+            Err(error) => {
+                warn!("Cannot list the channels of {} to autocomplete: {}", guild_id, error);
+                return Vec::new();
+            },
+        };
+
+        // Finished mentions are kept as they are, only a trailing partial name is completed
+        let (head, word) = match input.rfind(char::is_whitespace) {
+            Some(index) => input.split_at(index + 1),
+            None => ("", input),
+        };
+        let (head, prefix) = if word.starts_with("<#") {
+            (input.to_string(), String::new())
+        } else {
+            (head.to_string(), word.trim_start_matches('#').to_lowercase())
+        };
+        let head = if head.is_empty() || head.ends_with(char::is_whitespace) { head } else { format!("{} ", head) };
+        let already_listed = parse_channels(&head);
+
+        let mut suggestions = Vec::new();
+        for (channel, name) in self.resolve_channel_names(ctx, Some(guild_id)).await {
+            if !guild_channels.contains(&channel) || already_listed.contains(&channel) || !name.trim_start_matches('#').to_lowercase().starts_with(&prefix) {
+                continue;
+            }
+            let value = format!("{}<#{}>", head, channel);
+            if value.chars().count() <= AUTOCOMPLETE_VALUE_MAX_LENGTH {
+                suggestions.push((name, value));
+            }
+        }
+        suggestions.sort();
+        suggestions.truncate(AUTOCOMPLETE_MAX_CHOICES);
+        suggestions
+    }
+
+    /// The managed channels of a guild
+    async fn managed_channels_of(&self, ctx: &Context, guild_id: GuildId) -> Vec<ChannelId> {
+        let mut channels = Vec::new();
+        for channel in self.channel_queues.keys() {
+            if guild_of(ctx, channel).await == Some(guild_id) {
+                channels.push(*channel);
+            }
+        }
+        channels
+    }
+
+    /// Resolves the names of the managed channels of a guild (none outside of one), so other guilds' aren't shown
+    pub async fn resolve_channel_names(&mut self, ctx: &Context, guild_id: Option<GuildId>) -> HashMap<ChannelId, String> {
+        let Some(guild_id) = guild_id else { return HashMap::new(); };
+        let channels = self.managed_channels_of(ctx, guild_id).await;
+        let mut names = HashMap::with_capacity(channels.len());
+        for channel in channels {
+            let name = self.channel_name(ctx, &channel).await;
+            names.insert(channel, name);
+        }
+        names
+    }
+
+    pub fn channel_info(&self, channel: &ChannelId, name: &str) -> String {
+        let cq = match self.managed_queue(channel) {
+            Ok(cq) => cq,
+            Err(not_managed) => return not_managed,
+        };
+        let mut builder = Builder::default();
+        builder.append(format!("Autodelete status for {}:\n", name));
+        let ephemeral_note = match (cq.is_ephemeral(), cq.always_keep_latest) {
+            (true, false) => " (every new message is deleted right away)",
+            (true, true) => " (only the newest message stays)",
+            (false, _) => "",
+        };
+        builder.append(format!("- Limit: {} messages{}\n", cq.limit, ephemeral_note));
+        builder.append(format!("- Tracked messages: {} ({:.0}% full)\n", cq.queue.len(), cq.usage() * 100.0));
+        if cq.limit_kind == LimitKind::Bytes {
+            builder.append(format!("- Tracked content: {} / {} bytes\n", cq.tracked_bytes(), cq.byte_budget));
+        }
+        builder.append(format!("- Pinned messages: {}{}\n", cq.pins.len(), if cq.pins_count_toward_limit { " (counting toward the limit)" } else { "" }));
+        builder.append(format!("- Protected oldest messages: {}\n", cq.protected_oldest.len()));
+        builder.append(format!("- Kept messages: {}\n", cq.kept.len()));
+        builder.append(format!("- System messages: {}\n", cq.system_message_policy.as_str()));
+        builder.append(format!("- Keeping the {} messages\n", cq.retention.as_str()));
+        if cq.always_keep_latest {
+            builder.append("- The newest message is never deleted\n");
+        }
+        if !cq.delete_messages_with_threads {
+            builder.append(format!("- Messages with a thread (kept): {}\n", cq.threaded.len()));
+        }
+        if cq.tombstone {
+            builder.append(format!("- The bot's own expired messages are replaced with \"{}\"\n", TOMBSTONE));
+        }
+        if let Some(snoozed_until) = cq.snoozed_until {
+            builder.append(format!("- Snoozed, resuming <t:{}:R> ({})\n", snoozed_until / 1000, self.local_time(channel, snoozed_until / 1000)));
+        }
+        if cq.degraded {
+            builder.append("- ⚠️ Missing the Manage Messages permission, nothing is deleted until it is granted again\n");
+        }
+        match cq.queue.front() {
+            Some(oldest) => builder.append(format!("- Oldest tracked message: <t:{}:R> ({})\n", oldest.timestamp.unix_timestamp(), self.local_time(channel, oldest.timestamp.unix_timestamp()))),
+            None => builder.append("- Oldest tracked message: none\n"),
+        }
+        builder.append(format!("- Deleted messages: {}\n", cq.deleted));
+        builder.string().unwrap()
+    }
+
+    /// Changes a channel's limit by `delta` messages, staying within the allowed range
+    /// Returns the report when the change was made, along with the full reply
+    pub async fn adjust_limit(&mut self, ctx: &Context, channel: &ChannelId, delta: i64, user_id: UserId) -> (Option<OperationReport>, String) {
+        let cq = match self.managed_queue(channel) {
+            Ok(cq) => cq,
+            Err(not_managed) => return (None, not_managed),
+        };
+        let requested = cq.limit as i64 + delta;
+        let limit = requested.clamp(QUEUE_LIMIT_MIN, QUEUE_LIMIT_MAX);
+        let result = self.update_limit(ctx, channel, limit as usize, false, Some(user_id), None).await;
+        let content = outcome_message(&result);
+        let content = if limit != requested {
+            format!("{}\nThe new limit was clamped to {} (limits should be between {} and {})", content, limit, QUEUE_LIMIT_MIN, QUEUE_LIMIT_MAX)
+        } else {
+            content
+        };
+        (result.ok(), content)
+    }
+
+    /// Whether the channel exists and the bot can read and delete messages there
+    async fn check_manageable(&self, ctx: &Context, channel: &ChannelId) -> Result<(), String> {
+        let guild_channel = match channel.to_channel(ctx).await {
+            Ok(Channel::Guild(guild_channel)) => guild_channel,
+            Ok(_) => return Err("not a server channel".to_string()),
+            Err(error) => {
+                debug!("Cannot fetch channel {}: {}", channel, error);
+                return Err("channel not found".to_string());
+            }
+        };
+        match guild_channel.permissions_for_user(&ctx.cache, ctx.cache.current_user_id()) {
+            Ok(permissions) if permissions.read_message_history() && permissions.manage_messages() => Ok(()),
+            Ok(_) => Err("missing permissions to read history or delete messages".to_string()),
+            Err(error) => {
+                debug!("Cannot compute permissions in {}: {}", channel, error);
+                Err("cannot check permissions".to_string())
+            }
+        }
+    }
+
+    /// Says why a channel is one whose messages are usually meant to stay: the guild's rules or system
+    /// channel, or an announcement channel. Guilds can turn this off, and channels already managed
+    /// are not asked about again.
+    pub async fn important_channel_warning(&self, ctx: &Context, guild_id: Option<GuildId>, channel: &ChannelId) -> Option<String> {
+        let guild_id = guild_id?;
+        if self.skip_important_confirmation.contains(&guild_id) || self.channel_queues.contains_key(channel) {
+            return None;
+        }
+        let special_channels = match ctx.cache.guild_field(guild_id, |guild| (guild.rules_channel_id, guild.system_channel_id)) {
+            Some(special_channels) => Some(special_channels),
+            None => guild_id.to_partial_guild(ctx).await.ok().map(|guild| (guild.rules_channel_id, guild.system_channel_id)),
+        };
+        match special_channels {
+            Some((Some(rules), _)) if rules == *channel => return Some(format!("<#{}> is this server's rules channel.", channel)),
+            Some((_, Some(system))) if system == *channel => return Some(format!("<#{}> is this server's system channel.", channel)),
+            _ => {},
+        }
+        match self.channel_kind(ctx, channel).await {
+            Some(ChannelType::News) => Some(format!("<#{}> is an announcement channel.", channel)),
+            _ => None,
+        }
+    }
+
+    /// Sets a channel's limit then its byte budget, as `/configure` does
+    pub async fn configure_channel(&mut self, ctx: &Context, channel: &ChannelId, limit: usize, byte_budget: Option<usize>, user_id: UserId, requester: Option<&ApplicationCommandInteraction>) -> (Option<OperationReport>, String) {
+        match self.update_limit(ctx, channel, limit, false, Some(user_id), requester).await {
+            Ok(report) => {
+                info!("Limit of {} set to {} by {}, deleting {} messages", channel, limit, user_id, report.deleted());
+                let budget_note = self.set_byte_budget(ctx, channel, byte_budget).await;
+                let content = format!("{}{}", report, budget_note);
+                (Some(report), content)
+            },
+            Err(error) => (None, error.to_string()),
+        }
+    }
+
+    /// Checks a channel picked in a command's options belongs to the guild the command was run in,
+    /// and, when it is to be managed, that the bot can read and delete there
+    pub async fn check_target_channel(&self, ctx: &Context, guild_id: Option<GuildId>, channel: &ChannelId, manage: bool) -> Result<(), String> {
+        let target_guild = match channel.to_channel(ctx).await {
+            Ok(Channel::Guild(guild_channel)) => Some(guild_channel.guild_id),
+            _ => None,
+        };
+        if guild_id.is_none() || target_guild != guild_id {
+            return Err(format!("<#{}> is not a channel of this server", channel));
+        }
+        if manage {
+            self.check_manageable(ctx, channel).await.map_err(|reason| format!("Cannot configure <#{}>: {}", channel, reason))?;
+        }
+        Ok(())
+    }
+
+    /// Applies the same limit to several channels, one after the other to avoid bursts of API calls
+    /// Channels of other servers are refused, like any other channel that cannot be managed
+    pub async fn set_multiple_limits(&mut self, ctx: &Context, guild_id: Option<GuildId>, channels: &[ChannelId], limit: usize, user_id: UserId) -> String {
+        let mut succeeded = 0;
+        let mut builder = Builder::default();
+        for channel in channels {
+            let result = if let Err(reason) = self.check_target_channel(ctx, guild_id, channel, true).await {
+                format!("❌ {}", reason)
+            } else if !self.is_channel_permitted(channel) {
+                format!("❌ <#{}>: not permitted to be autodeleted by this bot", channel)
+            } else if let Some(remaining) = self.check_cooldown(channel) {
+                format!("❌ <#{}>: please wait {} seconds before changing its limit again", channel, remaining)
+            } else {
+                match self.update_limit(ctx, channel, limit, false, Some(user_id), None).await {
+                    Ok(report) => {
+                        succeeded += 1;
+                        format!("✅ {}", report)
+                    },
+                    Err(error) => format!("❌ <#{}>: {}", channel, error),
+                }
+            };
+            builder.append(format!("{}\n", result));
+        }
+        builder.append(format!("Configured {} of {} channels", succeeded, channels.len()));
+        builder.string().unwrap()
+    }
+
+    /// Applies the limit and every per-channel setting of `source` to `target`
+    pub async fn copy_config(&mut self, ctx: &Context, guild_id: Option<GuildId>, source: &ChannelId, target: &ChannelId, user_id: UserId) -> String {
+        // Both ends must belong to the server the command was run in
+        if let Err(reason) = self.check_target_channel(ctx, guild_id, source, false).await {
+            return reason;
+        }
+        if let Err(reason) = self.check_target_channel(ctx, guild_id, target, true).await {
+            return reason;
+        }
+        let cq = match self.managed_queue(source) {
+            Ok(cq) => cq.clone(),
+            Err(not_managed) => return not_managed,
+        };
+        if let Some(remaining) = self.check_cooldown(target) {
+            return format!("Please wait {} seconds before changing the limit of <#{}> again.", remaining, target);
+        }
+
+        // A new target must walk its history keeping the same end as the source
+        if !self.channel_queues.contains_key(target) {
+            self.pending_retention.insert(*target, cq.retention);
+        }
+        let report = match self.update_limit(ctx, target, cq.limit, false, Some(user_id), None).await {
+            Ok(report) => report,
+            Err(error) => return error.to_string(),
+        };
+        self.set_pins_count_toward_limit(ctx, target, cq.pins_count_toward_limit).await;
+        self.set_keep_oldest(ctx, target, cq.keep_oldest).await;
+        self.set_system_message_policy(target, cq.system_message_policy).await;
+        self.set_retention_direction(target, cq.retention).await;
+        self.set_delete_duplicates(target, cq.delete_duplicates).await;
+        self.set_always_keep_latest(ctx, target, cq.always_keep_latest).await;
+        self.set_tombstone(target, cq.tombstone).await;
+        self.set_delete_messages_with_threads(target, cq.delete_messages_with_threads).await;
+        self.set_heavy_rule(ctx, target, cq.heavy_rule).await;
+        let byte_budget = if cq.limit_kind == LimitKind::Bytes { Some(cq.byte_budget) } else { None };
+        self.set_byte_budget(ctx, target, byte_budget).await;
+
+        let yes_no = |enabled: bool| if enabled { "yes" } else { "no" };
+        let mut builder = Builder::default();
+        builder.append(format!("Copied the configuration of <#{}> to <#{}>\n", source, target));
+        builder.append(format!("{}\n", report));
+        builder.append(format!("- Pins count toward the limit: {}\n", yes_no(cq.pins_count_toward_limit)));
+        builder.append(format!("- Protected oldest messages: {}\n", cq.keep_oldest));
+        builder.append(format!("- System messages: {}\n", cq.system_message_policy.as_str()));
+        builder.append(format!("- Keeping the {} messages\n", cq.retention.as_str()));
+        builder.append(format!("- Duplicates deleted: {}\n", yes_no(cq.delete_duplicates)));
+        builder.append(format!("- Newest message always kept: {}\n", yes_no(cq.always_keep_latest)));
+        builder.append(format!("- Own messages tombstoned: {}\n", yes_no(cq.tombstone)));
+        builder.append(format!("- Messages with a thread deleted: {}\n", yes_no(cq.delete_messages_with_threads)));
+        match cq.heavy_rule {
+            Some(rule) => builder.append(format!("- Heavy messages kept: {}\n", rule.limit)),
+            None => builder.append("- Heavy messages kept: no separate limit\n"),
+        }
+        match byte_budget {
+            Some(byte_budget) => builder.append(format!("- Byte budget: {} bytes\n", byte_budget)),
+            None => builder.append("- Byte budget: none\n"),
+        }
+        builder.string().unwrap()
+    }
+
+    /// Posts the /testdelete message, which is one of our announcements so it is never tracked
+    pub async fn post_test_message(&mut self, ctx: &Context, channel: &ChannelId) -> Result<Message, String> {
+        match channel.say(ctx, "Autodelete test message, it will be deleted in a moment").await {
+            Ok(message) => {
+                self.announcements.insert(message.id);
+                Ok(message)
+            },
+            Err(error) => {
+                warn!("Cannot post test message in {}: {}", channel, error);
+                Err(format!("Failed to post the test message in <#{}>: {}", channel, error))
+            },
+        }
+    }
+
+    /// Dumps the raw contents of a channel's queue, for diagnostics
+    pub fn debug_queue(&self, channel: &ChannelId) -> String {
+        let cq = match self.managed_queue(channel) {
+            Ok(cq) => cq,
+            Err(not_managed) => return not_managed,
+        };
+        let describe = |message: Option<&TrackedMessage>| message.map_or("none".to_string(), |message| format!("{} ({})", message.id, message.timestamp));
+        let mut builder = Builder::default();
+        builder.append(format!("Queue of <#{}>:\n", channel));
+        builder.append(format!("- Length: {} (limit {}, capacity {})\n", cq.queue.len(), cq.limit, cq.capacity()));
+        builder.append(format!("- Pins: {}\n", cq.pins.len()));
+        builder.append(format!("- Oldest: {}\n", describe(cq.queue.front())));
+        builder.append(format!("- Newest: {}\n", describe(cq.queue.back())));
+        builder.append(format!("- Oldest {} tracked:\n", DEBUG_QUEUE_SAMPLES.min(cq.queue.len())));
+        for message in cq.queue.iter().take(DEBUG_QUEUE_SAMPLES) {
+            builder.append(format!("  - {}\n", describe(Some(message))));
+        }
+        let mut content = builder.string().unwrap();
+        if content.len() > MESSAGE_LENGTH_LIMIT {
+            let mut end = MESSAGE_LENGTH_LIMIT - 3;
+            while !content.is_char_boundary(end) {
+                end -= 1;
+            }
+            content.truncate(end);
+            content.push_str("...");
+        }
+        content
+    }
+
+    /// Raises a channel's limit and schedules the previous one to be restored after `minutes`
+    pub async fn temp_raise_limit(&mut self, ctx: &Context, channel: &ChannelId, limit: usize, minutes: i64, user_id: UserId) -> String {
+        let cq = match self.managed_queue(channel) {
+            Ok(cq) => cq,
+            Err(not_managed) => return not_managed,
+        };
+        if limit <= cq.limit {
+            return format!("The temporary limit should be higher than the current limit ({})", cq.limit);
+        }
+        // Raising again while raised still reverts to the limit from before the first raise
+        let original_limit = self.scheduled_reverts.get(channel).map_or(cq.limit, |revert| revert.original_limit);
+
+        let content = match self.update_limit(ctx, channel, limit, false, Some(user_id), None).await {
+            Ok(report) => report.to_string(),
+            Err(error) => return error.to_string(),
+        };
+
+        let revert = ScheduledRevert { original_limit, raised_limit: limit, revert_at: Utc::now().timestamp_millis() + minutes * 60 * 1000 };
+        self.scheduled_reverts.insert(*channel, revert);
+        if let Some(db) = self.database.as_ref() {
+            match retry_write(move || sqlx::query("INSERT OR REPLACE INTO scheduled_reverts VALUES (?, ?, ?, ?)")
+                .bind(channel.to_string())
+                .bind(revert.original_limit as u32)
+                .bind(revert.raised_limit as u32)
+                .bind(revert.revert_at)
+                .execute(db)).await {
+                Ok(result) => debug!("DB update affected {:?} rows", result.rows_affected()),
+                Err(error) => error!("Failed to insert scheduled revert: {}", error),
+            }
+        } else {
+            error!("Database is not initialized");
+        }
+
+        format!("{}\nThe limit will go back to {} <t:{}:R> ({})", content, original_limit, revert.revert_at / 1000, self.local_time(channel, revert.revert_at / 1000))
+    }
+
+    /// Catches up on deletions held back by the deletion rate
+    pub async fn evict_backlog(&mut self) {
+        let Some(ctx) = self.context.clone() else { return; };
+        for cq in self.channel_queues.values_mut() {
+            cq.evict_excess(&ctx, "evict_backlog");
+        }
+    }
+
+    /// Restores the original limit of every temporary raise that came due
+    pub async fn apply_scheduled_reverts(&mut self) {
+        let Some(ctx) = self.context.clone() else { return; };
+        let now = Utc::now().timestamp_millis();
+        let due: Vec<(ChannelId, ScheduledRevert)> = self.scheduled_reverts.iter()
+            .filter(|(_, revert)| revert.revert_at <= now)
+            .map(|(channel, revert)| (*channel, *revert))
+            .collect();
+
+        for (channel, revert) in due {
+            self.scheduled_reverts.remove(&channel);
+            if let Some(db) = self.database.as_ref() {
+                if let Err(error) = retry_write(move || sqlx::query("DELETE FROM scheduled_reverts WHERE channel_id=?").bind(channel.to_string()).execute(db)).await {
+                    error!("Failed to delete scheduled revert: {}", error);
+                }
+            }
+
+            // Skip channels that were removed or changed manually in the meantime
+            match self.channel_queues.get(&channel) {
+                Some(cq) if cq.limit == revert.raised_limit => {
+                    let result = outcome_message(&self.update_limit(&ctx, &channel, revert.original_limit, false, Some(ctx.cache.current_user_id()), None).await);
+                    info!("Reverted temporary limit of {}: {}", channel, result);
+                },
+                _ => info!("Dropping scheduled revert of {} as its limit changed in the meantime", channel),
+            }
+        }
+    }
+
+    /// Switches the channel between a plain message limit and a byte budget (on top of the message limit).
+    /// Returns a note to append to the reply.
+    async fn set_byte_budget(&mut self, ctx: &Context, channel: &ChannelId, byte_budget: Option<usize>) -> String {
+        let (limit_kind, byte_budget, note) = match byte_budget {
+            Some(_) if !self.message_content_available => (LimitKind::Count, 0, "\nMessage content is unavailable to the bot, so only the message count is limited".to_string()),
+            Some(byte_budget) => (LimitKind::Bytes, byte_budget, format!("\nOlder messages are also deleted once they add up to more than {} bytes", byte_budget)),
+            None => (LimitKind::Count, 0, String::new()),
+        };
+        // Borrowing just the queues, as the database is needed while holding on to the queue
+        let Some(cq) = self.channel_queues.get_mut(channel) else {
+            return ManagerError::NoLongerManaged(*channel).to_string();
+        };
+        if cq.limit_kind == limit_kind && cq.byte_budget == byte_budget {
+            return note;
+        }
+        cq.limit_kind = limit_kind;
+        cq.byte_budget = byte_budget;
+
+        if let Some(db) = self.database.as_ref() {
+            match retry_write(move || sqlx::query("UPDATE channel_limits SET limit_kind=?, byte_budget=? WHERE channel_id=?")
+                .bind(limit_kind.as_str())
+                .bind(byte_budget as i64)
+                .bind(channel.to_string())
+                .execute(db)).await {
+                Ok(result) => debug!("DB update affected {:?} rows", result.rows_affected()),
+                Err(error) => error!("Failed to update limit kind: {}", error),
+            }
+        } else {
+            error!("Database is not initialized");
+        }
+
+        match cq.evict_excess(ctx, "set_byte_budget") {
+            0 => note,
+            deleted => format!("{} ({} more messages deleted)", note, deleted),
+        }
+    }
+
+    /// Stops deleting messages in the channel for `duration`, or resumes right away when `None`
+    pub async fn snooze(&mut self, ctx: &Context, channel: &ChannelId, duration: Option<Duration>) -> String {
+        let Some(duration) = duration else {
+            return match self.managed_queue(channel) {
+                Ok(cq) if cq.is_snoozed() => {
+                    self.resume_channel(ctx, channel).await;
+                    format!("Autodelete in <#{}> has resumed", channel)
+                },
+                Ok(_) => format!("Autodelete in <#{}> isn't snoozed", channel),
+                Err(not_managed) => not_managed,
+            };
+        };
+        // Borrowing just the queues, as the database is needed while holding on to the queue
+        let Some(cq) = self.channel_queues.get_mut(channel) else {
+            return ManagerError::NotManaged(*channel).to_string();
+        };
+        let snoozed_until = Utc::now().timestamp_millis() + duration.as_millis() as i64;
+        cq.snoozed_until = Some(snoozed_until);
+
+        if let Some(db) = self.database.as_ref() {
+            match retry_write(move || sqlx::query("UPDATE channel_limits SET snoozed_until=? WHERE channel_id=?")
+                .bind(snoozed_until)
+                .bind(channel.to_string())
+                .execute(db)).await {
+                Ok(result) => debug!("DB update affected {:?} rows", result.rows_affected()),
+                Err(error) => error!("Failed to update snoozed_until: {}", error),
+            }
+        } else {
+            error!("Database is not initialized");
+        }
+
+        format!("Autodelete in <#{}> is snoozed, it will resume <t:{}:R> ({})", channel, snoozed_until / 1000, self.local_time(channel, snoozed_until / 1000))
+    }
+
+    /// Lifts the channel's snooze and deletes whatever went over the limit in the meantime
+    async fn resume_channel(&mut self, ctx: &Context, channel: &ChannelId) {
+        let Some(cq) = self.channel_queues.get_mut(channel) else { return; };
+        cq.snoozed_until = None;
+        if let Some(db) = self.database.as_ref() {
+            match retry_write(move || sqlx::query("UPDATE channel_limits SET snoozed_until=NULL WHERE channel_id=?")
+                .bind(channel.to_string())
+                .execute(db)).await {
+                Ok(result) => debug!("DB update affected {:?} rows", result.rows_affected()),
+                Err(error) => error!("Failed to clear snoozed_until: {}", error),
+            }
+        }
+        let deleted = cq.evict_excess(ctx, "resume_channel");
+        info!("Resumed autodelete in {} ({} messages deleted)", channel, deleted);
+    }
+
+    /// Marks queues as degraded while the bot can't delete messages in their channel, and restores them once it can again.
+    /// Channels missing from the cache are left as they are.
+    pub async fn check_permissions(&mut self) {
+        let Some(ctx) = self.context.clone() else { return; };
+        let user_id = ctx.cache.current_user_id();
+        for (channel, cq) in self.channel_queues.iter_mut() {
+            let Some(guild_channel) = ctx.cache.guild_channel(*channel) else {
+                debug!("Channel {} is not cached, cannot check permissions", channel);
+                continue;
+            };
+            let can_delete = match guild_channel.permissions_for_user(&ctx.cache, user_id) {
+                Ok(permissions) => permissions.manage_messages(),
+                Err(error) => {
+                    debug!("Cannot compute permissions in {}: {}", channel, error);
+                    continue;
+                }
+            };
+            if cq.degraded == can_delete {
+                cq.degraded = !can_delete;
+                if cq.degraded {
+                    warn!("Lost the Manage Messages permission in {}, deletions are on hold", channel);
+                } else {
+                    info!("Regained the Manage Messages permission in {}, resuming deletions", channel);
+                    // Catch up on whatever went over the limit in the meantime
+                    cq.evict_excess(&ctx, "check_permissions");
+                }
+            }
+        }
+    }
+
+    /// Resumes every channel whose snooze ran out
+    pub async fn resume_snoozed(&mut self) {
+        let Some(ctx) = self.context.clone() else { return; };
+        let now = Utc::now().timestamp_millis();
+        let due: Vec<ChannelId> = self.channel_queues.iter()
+            .filter(|(_, cq)| cq.snoozed_until.is_some_and(|snoozed_until| snoozed_until <= now))
+            .map(|(channel, _)| *channel)
+            .collect();
+        for channel in due {
+            self.resume_channel(&ctx, &channel).await;
+        }
+    }
+
+    /// Protects the channel's `count` oldest messages from ever being deleted
+    pub async fn set_keep_oldest(&mut self, ctx: &Context, channel: &ChannelId, count: usize) -> String {
+        if let Err(not_managed) = self.managed_queue(channel) {
+            return not_managed;
+        }
+
+        let protected_oldest: HashSet<MessageId> = if count > 0 {
+            match channel.messages(ctx, |retriever| retriever.after(MessageId(1)).limit(count as u64)).await {
+                Ok(oldest_messages) => oldest_messages.iter().map(|message| message.id).collect(),
+                Err(error) => {
+                    error!("Uh oh! Error: {}", error);
+                    return error.to_string();
+                }
+            }
+        } else {
+            HashSet::new()
+        };
+
+        if let Some(db) = self.database.as_ref() {
+            let result: Result<(), sqlx::Error> = async {
+                let mut transaction = db.begin().await?;
+                sqlx::query("UPDATE channel_limits SET keep_oldest=? WHERE channel_id=?")
+                    .bind(count as u32)
+                    .bind(channel.to_string())
+                    .execute(&mut transaction).await?;
+                sqlx::query("DELETE FROM protected_messages WHERE channel_id=?")
+                    .bind(channel.to_string())
+                    .execute(&mut transaction).await?;
+                for message_id in protected_oldest.iter() {
+                    sqlx::query("INSERT INTO protected_messages VALUES (?, ?)")
+                        .bind(channel.to_string())
+                        .bind(message_id.to_string())
+                        .execute(&mut transaction).await?;
+                }
+                transaction.commit().await
+            }.await;
+            if let Err(error) = result {
+                error!("Failed to update protected messages: {}", error);
+            }
+        } else {
+            error!("Database is not initialized");
+        }
+
+        let cq = match self.managed_queue_mut(channel) {
+            Ok(cq) => cq,
+            Err(not_managed) => return not_managed,
+        };
+        cq.queue.retain(|message| !protected_oldest.contains(&message.id));
+        cq.heavy.retain(|message| !protected_oldest.contains(&message.id));
+        cq.keep_oldest = count;
+        cq.protected_oldest = protected_oldest;
+        if count > 0 {
+            format!("The {} oldest messages of <#{}> will never be deleted", cq.protected_oldest.len(), channel)
+        } else {
+            format!("The oldest messages of <#{}> are no longer protected", channel)
+        }
+    }
+
+    /// Saves a message from deletion, whether it is tracked yet or not
+    pub async fn keep_message(&mut self, ctx: &Context, channel: &ChannelId, message_id: MessageId) -> String {
+        let cq = match self.managed_queue(channel) {
+            Ok(cq) => cq,
+            Err(not_managed) => return not_managed,
+        };
+        if cq.kept.contains(&message_id) {
+            return format!("This message of <#{}> is already kept", channel);
+        }
+        // Untracked messages are looked up, so only messages that exist get kept
+        if !cq.queue.iter().any(|message| message.id == message_id) {
+            if let Err(error) = channel.message(ctx, message_id).await {
+                warn!("Cannot fetch message {} to keep in {}: {}", message_id, channel, error);
+                return format!("Couldn't find this message in <#{}>", channel);
+            }
+        }
+
+        // Without a database (memory-only mode) the message is only kept until a restart
+        if let Some(db) = self.database.as_ref() {
+            if let Err(error) = retry_write(move || sqlx::query("INSERT OR REPLACE INTO kept_messages VALUES (?, ?)")
+                .bind(channel.to_string())
+                .bind(message_id.to_string())
+                .execute(db)).await {
+                error!("Failed to insert kept message: {}", error);
+                return format!("Failed to keep the message in <#{}>", channel);
+            }
+        }
+
+        let Some(cq) = self.channel_queues.get_mut(channel) else { return ManagerError::NoLongerManaged(*channel).to_string(); };
+        cq.queue.retain(|message| message.id != message_id);
+        cq.heavy.retain(|message| message.id != message_id);
+        cq.kept.insert(message_id);
+        format!("This message will never be deleted from <#{}> ({} kept messages)", channel, cq.kept.len())
+    }
+
+    /// Lets a kept message be tracked (and deleted) again, as if it was just unpinned
+    pub async fn unkeep_message(&mut self, ctx: &Context, channel: &ChannelId, message_id: MessageId) -> String {
+        match self.managed_queue(channel) {
+            Ok(cq) if !cq.kept.contains(&message_id) => return format!("This message of <#{}> isn't kept", channel),
+            Ok(_) => {},
+            Err(not_managed) => return not_managed,
+        }
+        if let Some(db) = self.database.as_ref() {
+            if let Err(error) = retry_write(move || sqlx::query("DELETE FROM kept_messages WHERE channel_id=? AND message_id=?")
+                .bind(channel.to_string())
+                .bind(message_id.to_string())
+                .execute(db)).await {
+                error!("Failed to delete kept message: {}", error);
+                return format!("Failed to stop keeping the message in <#{}>", channel);
+            }
+        }
+        // Messages that were deleted in the meantime are simply forgotten
+        let message = channel.message(ctx, message_id).await;
+
+        let Some(cq) = self.channel_queues.get_mut(channel) else { return ManagerError::NoLongerManaged(*channel).to_string(); };
+        cq.kept.remove(&message_id);
+        match message {
+            Ok(message) if !message.pinned => {
+                insert_chronologically(&mut cq.queue, TrackedMessage::from(&message));
+                cq.evict_excess(ctx, "unkeep_message");
+            },
+            Ok(_) => {},
+            Err(error) => debug!("Kept message {} of {} is gone: {}", message_id, channel, error),
+        }
+        format!("This message of <#{}> can be deleted again", channel)
+    }
+
+    /// Enables (or disables, with 0 days) unmanaging a guild's channels once they have been idle for that long
+    pub async fn set_idle_unmanage(&mut self, guild_id: &GuildId, days: u64) -> String {
+        let Some(db) = self.database.as_ref() else {
+            error!("Database is not initialized");
+            return "Database is not initialized, please try again later".to_string();
+        };
+        let idle = Duration::from_secs(days * 24 * 60 * 60);
+        let idle_secs = if days > 0 { Some(idle.as_secs() as i64) } else { None };
+        let result = retry_write(move || sqlx::query("INSERT INTO guild_settings (guild_id, unmanage_after_idle) VALUES (?, ?) ON CONFLICT(guild_id) DO UPDATE SET unmanage_after_idle=excluded.unmanage_after_idle")
+            .bind(guild_id.to_string())
+            .bind(idle_secs)
+            .execute(db)).await;
+        if let Err(error) = result {
+            error!("Failed to update guild settings: {}", error);
+            return "Failed to update the idle setting".to_string();
+        }
+
+        if days > 0 {
+            self.unmanage_after_idle.insert(*guild_id, idle);
+            format!("Channels in this server will stop being autodeleted after {} days without messages", days)
+        } else {
+            self.unmanage_after_idle.remove(guild_id);
+            "Channels in this server will no longer stop being autodeleted when idle".to_string()
+        }
+    }
+
+    pub async fn set_announce_changes(&mut self, guild_id: &GuildId, enabled: bool) -> String {
+        let Some(db) = self.database.as_ref() else {
+            error!("Database is not initialized");
+            return "Database is not initialized, please try again later".to_string();
+        };
+        let result = retry_write(move || sqlx::query("INSERT INTO guild_settings (guild_id, announce_changes) VALUES (?, ?) ON CONFLICT(guild_id) DO UPDATE SET announce_changes=excluded.announce_changes")
+            .bind(guild_id.to_string())
+            .bind(enabled)
+            .execute(db)).await;
+        if let Err(error) = result {
+            error!("Failed to update guild settings: {}", error);
+            return "Failed to update the announcement setting".to_string();
+        }
+
+        if enabled {
+            self.announce_changes.insert(*guild_id);
+            "Limit changes will now be announced in the affected channels".to_string()
+        } else {
+            self.announce_changes.remove(guild_id);
+            "Limit changes will no longer be announced".to_string()
+        }
+    }
+
+    pub async fn set_confirm_important(&mut self, guild_id: &GuildId, enabled: bool) -> String {
+        let Some(db) = self.database.as_ref() else {
+            error!("Database is not initialized");
+            return "Database is not initialized, please try again later".to_string();
+        };
+        let result = retry_write(move || sqlx::query("INSERT INTO guild_settings (guild_id, confirm_important_channels) VALUES (?, ?) ON CONFLICT(guild_id) DO UPDATE SET confirm_important_channels=excluded.confirm_important_channels")
+            .bind(guild_id.to_string())
+            .bind(enabled)
+            .execute(db)).await;
+        if let Err(error) = result {
+            error!("Failed to update guild settings: {}", error);
+            return "Failed to update the confirmation setting".to_string();
+        }
+
+        if enabled {
+            self.skip_important_confirmation.remove(guild_id);
+            "Configuring the rules, system or announcement channels will now need a confirmation".to_string()
+        } else {
+            self.skip_important_confirmation.insert(*guild_id);
+            "Configuring the rules, system or announcement channels will no longer need a confirmation".to_string()
+        }
+    }
+
+    pub async fn set_purge_on_ban(&mut self, guild_id: &GuildId, enabled: bool) -> String {
+        let Some(db) = self.database.as_ref() else {
+            error!("Database is not initialized");
+            return "Database is not initialized, please try again later".to_string();
+        };
+        let result = retry_write(move || sqlx::query("INSERT INTO guild_settings (guild_id, purge_on_ban) VALUES (?, ?) ON CONFLICT(guild_id) DO UPDATE SET purge_on_ban=excluded.purge_on_ban")
+            .bind(guild_id.to_string())
+            .bind(enabled)
+            .execute(db)).await;
+        if let Err(error) = result {
+            error!("Failed to update guild settings: {}", error);
+            return "Failed to update the ban purge setting".to_string();
+        }
+
+        if enabled {
+            self.purge_on_ban.insert(*guild_id);
+            "The tracked messages of members banned from this server will now be deleted right away".to_string()
+        } else {
+            self.purge_on_ban.remove(guild_id);
+            "Banned members' messages will now be left to expire like any other".to_string()
+        }
+    }
+
+    /// Starts a backup of the database, when backups are enabled and the database is up
+    pub fn backup_database(&self) {
+        let Some(backups) = self.backups.as_ref() else { return; };
+        match self.database.as_ref() {
+            Some(db) => backups.start(db.clone()),
+            None => debug!("Database is not initialized, skipping the backup"),
+        }
+    }
+
+    /// Sets the reason shown in the guild's audit log, or goes back to the default with `None`
+    pub async fn set_deletion_reason(&mut self, guild_id: &GuildId, reason: Option<String>) -> String {
+        let Some(db) = self.database.as_ref() else {
+            error!("Database is not initialized");
+            return "Database is not initialized, please try again later".to_string();
+        };
+        let stored = reason.clone();
+        let result = retry_write(move || sqlx::query("INSERT INTO guild_settings (guild_id, deletion_reason) VALUES (?, ?) ON CONFLICT(guild_id) DO UPDATE SET deletion_reason=excluded.deletion_reason")
+            .bind(guild_id.to_string())
+            .bind(stored.clone())
+            .execute(db)).await;
+        if let Err(error) = result {
+            error!("Failed to update guild settings: {}", error);
+            return "Failed to update the deletion reason".to_string();
+        }
+
+        self.deletion_reasons.set(*guild_id, reason.clone());
+        match (reason, self.deletion_reasons.default_reason()) {
+            (Some(reason), _) => format!("Deletions will now show \"{}\" in the audit log", reason),
+            (None, Some(default)) => format!("Deletions will now show the default reason in the audit log (\"{}\")", default),
+            (None, None) => "Deletions will no longer show a reason in the audit log".to_string(),
+        }
+    }
+
+    /// Sets (or disables, with `None`) the guild's fullness warning
+    pub async fn set_fullness_warning(&mut self, guild_id: &GuildId, warning: Option<FullnessWarning>) -> String {
+        let Some(db) = self.database.as_ref() else {
+            error!("Database is not initialized");
+            return "Database is not initialized, please try again later".to_string();
+        };
+        let result = retry_write(move || sqlx::query("INSERT INTO guild_settings (guild_id, warning_threshold, warning_channel) VALUES (?, ?, ?) ON CONFLICT(guild_id) DO UPDATE SET warning_threshold=excluded.warning_threshold, warning_channel=excluded.warning_channel")
+            .bind(guild_id.to_string())
+            .bind(warning.map_or(0, |warning| warning.threshold))
+            .bind(warning.map(|warning| warning.log_channel.to_string()))
+            .execute(db)).await;
+        if let Err(error) = result {
+            error!("Failed to update guild settings: {}", error);
+            return "Failed to update the fullness warning".to_string();
+        }
+
+        // Channels already above the new threshold warn once more
+        for cq in self.channel_queues.values_mut() {
+            cq.warning_fired = false;
+        }
+        match warning {
+            Some(warning) => {
+                self.fullness_warnings.insert(*guild_id, warning);
+                format!("A notice will be posted in <#{}> when a channel gets {}% full", warning.log_channel, warning.threshold)
+            },
+            None => {
+                self.fullness_warnings.remove(guild_id);
+                "Fullness warnings are now disabled".to_string()
+            },
+        }
+    }
+
+    /// Posts a notice about the channel's new limit (`None` once removed) in the channel itself, if its guild opted in
+    async fn announce_limit(&mut self, channel: &ChannelId, limit: Option<usize>) {
+        if self.announce_changes.is_empty() {
+            return;
+        }
+        let Some(ctx) = self.context.clone() else { return; };
+        let Some(Channel::Guild(guild_channel)) = channel.to_channel_cached(&ctx.cache) else { return; };
+        if !self.announce_changes.contains(&guild_channel.guild_id) {
+            return;
+        }
+        let content = match limit {
+            Some(limit) => format!("Autodelete now keeps the last {} messages in this channel", limit),
+            None => "Autodelete is no longer active in this channel".to_string(),
+        };
+        match channel.say(&ctx, content).await {
+            Ok(message) => {
+                self.announcements.insert(message.id);
+            },
+            Err(error) => warn!("Cannot announce limit change in {}: {}", channel, error),
+        }
+    }
+
+    /// Removes management from channels of opted-in guilds that haven't received messages in a while
+    pub async fn unmanage_idle_channels(&mut self) {
+        let Some(ctx) = self.context.clone() else { return; };
+        if self.unmanage_after_idle.is_empty() {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut idle_channels = Vec::new();
+        for channel in self.channel_queues.keys() {
+            // Channels start counting from the first check after they become managed
+            let last_activity = *self.last_activity.entry(*channel).or_insert(now);
+            let Some(Channel::Guild(guild_channel)) = channel.to_channel_cached(&ctx.cache) else { continue; };
+            let Some(idle) = self.unmanage_after_idle.get(&guild_channel.guild_id) else { continue; };
+            if now.duration_since(last_activity) >= *idle {
+                idle_channels.push(*channel);
+            }
+        }
+
+        for channel in idle_channels {
+            let result = outcome_message(&self.remove_limit(&channel, ctx.cache.current_user_id()).await);
+            self.last_activity.remove(&channel);
+            info!("Unmanaged idle channel {}: {}", channel, result);
+        }
+    }
+
+    /// Checks whether the oldest tracked messages of each channel still exist, dropping those that don't.
+    /// Missed delete events leave such phantom messages in the queue, where they take up room until evicted.
+    /// The messages are fetched by a spawned task, which sends the phantoms back as `Command::DropPhantoms`.
+    pub fn audit_queues(&mut self) {
+        let (Some(ctx), Some(commands)) = (self.context.clone(), self.commands.clone()) else { return; };
+        if self.auditing {
+            debug!("The previous queue audit is still running, skipping this one");
+            return;
+        }
+        let samples: Vec<(ChannelId, Vec<MessageId>)> = self.channel_queues.iter()
+            .map(|(channel, cq)| (*channel, cq.queue.iter().take(self.audit_sample_size).map(|message| message.id).collect()))
+            .collect();
+        self.auditing = true;
+        tokio::spawn(async move {
+            let mut phantoms = Vec::new();
+            for (channel, sample) in samples {
+                let mut gone = Vec::new();
+                for message_id in sample {
+                    match channel.message(&ctx, message_id).await {
+                        Ok(_) => {},
+                        Err(error) if is_message_gone(&error) => gone.push(message_id),
+                        Err(error) => debug!("Cannot check message {} (channel={}): {}", message_id, channel, error),
+                    }
+                }
+                if !gone.is_empty() {
+                    phantoms.push((channel, gone));
+                }
+            }
+            if commands.send(Command::DropPhantoms { phantoms }).await.is_err() {
+                debug!("Manager is gone, dropping the queue audit");
+            }
+        });
+    }
+
+    /// Drops the phantom messages a queue audit found, from the channels still managed
+    pub fn drop_phantoms(&mut self, phantoms: Vec<(ChannelId, Vec<MessageId>)>) {
+        self.auditing = false;
+        let mut reclaimed = 0;
+        for (channel, phantoms) in phantoms {
+            let Some(cq) = self.channel_queues.get_mut(&channel) else { continue; };
+            let before = cq.queue.len();
+            cq.queue.retain(|message| !phantoms.contains(&message.id));
+            cq.heavy.retain(|message| !phantoms.contains(&message.id));
+            let dropped = before - cq.queue.len();
+            if dropped > 0 {
+                warn!("Dropping {} phantom messages from the queue of {}", dropped, channel);
+                reclaimed += dropped;
+            }
+        }
+        self.phantoms_reclaimed += reclaimed;
+        info!("Queue audit reclaimed {} phantom messages ({} since startup)", reclaimed, self.phantoms_reclaimed);
+    }
+
+    /// Sets (or clears, with `None`) the separate limit for heavy messages.
+    /// Only messages received from now on are checked, already tracked ones are never considered heavy.
+    pub async fn set_heavy_rule(&mut self, ctx: &Context, channel: &ChannelId, rule: Option<HeavyRule>) -> String {
+        // Borrowing just the queues, as the database is needed while holding on to the queue
+        let Some(cq) = self.channel_queues.get_mut(channel) else {
+            return ManagerError::NotManaged(*channel).to_string();
+        };
+        cq.heavy_rule = rule;
+        if rule.is_none() {
+            cq.heavy.clear();
+        }
+
+        if let Some(db) = self.database.as_ref() {
+            match retry_write(move || sqlx::query("UPDATE channel_limits SET heavy_limit=?, heavy_min_size=?, heavy_videos=? WHERE channel_id=?")
+                .bind(rule.map_or(0, |rule| rule.limit as u32))
+                .bind(rule.map_or(0, |rule| rule.min_size as i64))
+                .bind(rule.is_some_and(|rule| rule.videos))
+                .bind(channel.to_string())
+                .execute(db)).await {
+                Ok(result) => debug!("DB update affected {:?} rows", result.rows_affected()),
+                Err(error) => error!("Failed to update heavy messages rule: {}", error),
+            }
+        } else {
+            error!("Database is not initialized");
+        }
+
+        match rule {
+            Some(rule) => {
+                cq.evict_excess(ctx, "set_heavy_rule");
+                format!("<#{}> will now keep at most {} heavy messages", channel, rule.limit)
+            },
+            None => format!("Heavy messages in <#{}> now only count toward the regular limit", channel),
+        }
+    }
+
+    pub async fn set_always_keep_latest(&mut self, ctx: &Context, channel: &ChannelId, enabled: bool) -> String {
+        let cq = match self.managed_queue_mut(channel) {
+            Ok(cq) => cq,
+            Err(not_managed) => return not_managed,
+        };
+        cq.always_keep_latest = enabled;
+        // Turning it off may leave the queue one over its capacity
+        cq.evict_excess(ctx, "set_always_keep_latest");
+
+        if let Some(db) = self.database.as_ref() {
+            match retry_write(move || sqlx::query("UPDATE channel_limits SET always_keep_latest=? WHERE channel_id=?")
+                .bind(enabled)
+                .bind(channel.to_string())
+                .execute(db)).await {
+                Ok(result) => debug!("DB update affected {:?} rows", result.rows_affected()),
+                Err(error) => error!("Failed to update always_keep_latest: {}", error),
+            }
+        } else {
+            error!("Database is not initialized");
+        }
+
+        if enabled {
+            format!("The newest message of <#{}> will never be deleted, even if that leaves one message over the limit", channel)
+        } else {
+            format!("The newest message of <#{}> may now be deleted like any other", channel)
+        }
+    }
+
+    /// A thread started from a message shares its ID, so the message is found and set aside unless the channel deletes them anyway.
+    /// Too late for a message already handed off for deletion.
+    pub fn on_thread_created(&mut self, parent: &ChannelId, thread: ChannelId) {
+        let Some(cq) = self.channel_queues.get_mut(parent) else { return; };
+        if cq.delete_messages_with_threads {
+            return;
+        }
+        let message = MessageId::from(thread.0);
+        cq.threaded.insert(message);
+        let tracked = cq.queue.len();
+        cq.queue.retain(|tracked| tracked.id != message);
+        cq.heavy.retain(|tracked| tracked.id != message);
+        if cq.queue.len() < tracked {
+            debug!("Message {} of {} started a thread, it is no longer tracked", message, parent);
+        }
+    }
+
+    pub async fn set_delete_messages_with_threads(&mut self, channel: &ChannelId, delete: bool) -> String {
+        let cq = match self.managed_queue_mut(channel) {
+            Ok(cq) => cq,
+            Err(not_managed) => return not_managed,
+        };
+        cq.delete_messages_with_threads = delete;
+
+        if let Some(db) = self.database.as_ref() {
+            match retry_write(move || sqlx::query("UPDATE channel_limits SET delete_messages_with_threads=? WHERE channel_id=?")
+                .bind(delete)
+                .bind(channel.to_string())
+                .execute(db)).await {
+                Ok(result) => debug!("DB update affected {:?} rows", result.rows_affected()),
+                Err(error) => error!("Failed to update delete_messages_with_threads: {}", error),
+            }
+        } else {
+            error!("Database is not initialized");
+        }
+
+        if delete {
+            format!("Messages that started a thread in <#{}> will now be deleted like any other (the messages already set aside stay until the channel is configured again)", channel)
+        } else {
+            format!("Messages that start a thread in <#{}> will now be kept, and won't count toward the limit", channel)
+        }
+    }
+
+    /// Discord doesn't let bots edit anyone else's messages, so only ours get a tombstone
+    pub async fn set_tombstone(&mut self, channel: &ChannelId, enabled: bool) -> String {
+        let cq = match self.managed_queue_mut(channel) {
+            Ok(cq) => cq,
+            Err(not_managed) => return not_managed,
+        };
+        cq.tombstone = enabled;
+
+        if let Some(db) = self.database.as_ref() {
+            match retry_write(move || sqlx::query("UPDATE channel_limits SET tombstone=? WHERE channel_id=?")
+                .bind(enabled)
+                .bind(channel.to_string())
+                .execute(db)).await {
+                Ok(result) => debug!("DB update affected {:?} rows", result.rows_affected()),
+                Err(error) => error!("Failed to update tombstone: {}", error),
+            }
+        } else {
+            error!("Database is not initialized");
+        }
+
+        if enabled {
+            format!("The bot's own expired messages in <#{}> will be replaced with \"{}\" instead of deleted (Discord doesn't let bots edit other messages, so those are still deleted)", channel, TOMBSTONE)
+        } else {
+            format!("The bot's own expired messages in <#{}> will be deleted again", channel)
+        }
+    }
+
+    pub async fn set_delete_duplicates(&mut self, channel: &ChannelId, enabled: bool) -> String {
+        let cq = match self.managed_queue_mut(channel) {
+            Ok(cq) => cq,
+            Err(not_managed) => return not_managed,
+        };
+        cq.delete_duplicates = enabled;
+        cq.last_by_author.clear();
+
+        if let Some(db) = self.database.as_ref() {
+            match retry_write(move || sqlx::query("UPDATE channel_limits SET delete_duplicates=? WHERE channel_id=?")
+                .bind(enabled)
+                .bind(channel.to_string())
+                .execute(db)).await {
+                Ok(result) => debug!("DB update affected {:?} rows", result.rows_affected()),
+                Err(error) => error!("Failed to update delete_duplicates: {}", error),
+            }
+        } else {
+            error!("Database is not initialized");
+        }
+
+        if enabled {
+            format!("Repeated messages sent within {} seconds by the same author will now be deleted from <#{}>", DUPLICATE_WINDOW_SECS, channel)
+        } else {
+            format!("Repeated messages will no longer be deleted from <#{}>", channel)
+        }
+    }
+
+    pub async fn set_pins_count_toward_limit(&mut self, ctx: &Context, channel: &ChannelId, enabled: bool) -> String {
+        // Borrowing just the queues, as the database is needed while holding on to the queue
+        let Some(cq) = self.channel_queues.get_mut(channel) else {
+            return ManagerError::NotManaged(*channel).to_string();
+        };
+        cq.pins_count_toward_limit = enabled;
+
+        if let Some(db) = self.database.as_ref() {
+            match retry_write(move || sqlx::query("UPDATE channel_limits SET pins_count_toward_limit=? WHERE channel_id=?")
+                .bind(enabled)
+                .bind(channel.to_string())
+                .execute(db)).await {
+                Ok(result) => debug!("DB update affected {:?} rows", result.rows_affected()),
+                Err(error) => error!("Failed to update pins_count_toward_limit: {}", error),
+            }
+        } else {
+            error!("Database is not initialized");
+        }
+
+        if enabled {
+            cq.evict_excess(ctx, "set_pins_count_toward_limit");
+            format!("Pinned messages now count toward the limit of <#{}> ({} pins, {} messages kept)", channel, cq.pins.len(), cq.capacity())
+        } else {
+            format!("Pinned messages no longer count toward the limit of <#{}>", channel)
+        }
+    }
+
+    /// Ranks the managed channels by the metric, busiest first
+    pub fn leaderboard(&self, metric: LeaderboardMetric, count: usize) -> String {
+        if self.channel_queues.is_empty() {
+            return "There are no channels being autodeleted".to_string();
+        }
+        let mut ranked: Vec<(&ChannelId, f64, String)> = self.channel_queues.iter().map(|(channel, cq)| match metric {
+            LeaderboardMetric::Deleted => (channel, cq.deleted as f64, format!("{} deleted", cq.deleted)),
+            LeaderboardMetric::Fullness => (channel, cq.usage(), format!("{:.0}% full ({} / {})", cq.usage() * 100.0, cq.queue.len(), cq.limit)),
+            LeaderboardMetric::Rate => {
+                let rate = cq.messages_per_day().unwrap_or(0.0);
+                (channel, rate, format!("{:.1} messages per day", rate))
+            },
+        }).collect();
+        ranked.sort_by(|(_, a, _), (_, b, _)| b.total_cmp(a));
+
+        let title = match metric {
+            LeaderboardMetric::Deleted => "Channels with the most deleted messages",
+            LeaderboardMetric::Fullness => "Fullest channels",
+            LeaderboardMetric::Rate => "Busiest channels",
+        };
+        let mut content = format!("{}:\n", title);
+        for (rank, (channel, _, value)) in ranked.iter().take(count).enumerate() {
+            let line = format!("{}. <#{}> • {}\n", rank + 1, channel, value);
+            if content.len() + line.len() > MESSAGE_LENGTH_LIMIT {
+                break;
+            }
+            content.push_str(&line);
+        }
+        content
+    }
+
+    /// Lists the channel's latest deletions, newest first
+    pub fn recent_deletes(&self, channel: &ChannelId, count: usize) -> String {
+        let cq = match self.managed_queue(channel) {
+            Ok(cq) => cq,
+            Err(not_managed) => return not_managed,
+        };
+        if cq.deletion_log.is_empty() {
+            return format!("No deletions were logged in <#{}> yet", channel);
+        }
+        let mut content = format!("Latest messages deleted by the bot in <#{}> (see the limit edit history for configuration changes):\n", channel);
+        let mut listed = 0;
+        for entry in cq.deletion_log.iter().rev().take(count) {
+            let author = entry.author_id.map_or("an unknown author".to_string(), |author_id| format!("<@{}>", author_id));
+            let line = format!("- Message {} by {}, sent {}, deleted <t:{}:R>\n", entry.id, author, self.local_time(channel, entry.timestamp.unix_timestamp()), entry.deleted_at / 1000);
+            if content.len() + line.len() > MESSAGE_LENGTH_LIMIT {
+                break;
+            }
+            content.push_str(&line);
+            listed += 1;
+        }
+        debug!("Listed {} of {} requested deletions in {}", listed, count, channel);
+        content
+    }
+
+    /// Lists the limit changes made by a member, newest first and `AUDIT_PAGE_SIZE` at a time.
+    /// Channels known to belong to another guild are left out; deleted channels can't be told apart, so they stay.
+    pub async fn audit_by_user(&self, ctx: &Context, guild_id: Option<GuildId>, user_id: UserId, page: usize) -> String {
+        let Some(db) = self.database.as_ref() else {
+            error!("Database is not initialized");
+            return "Database is not initialized, please try again later".to_string();
+        };
+        let entries = match sqlx::query_as::<_, ChannelLimitEditDatabaseEntry>("SELECT channel_id, channel_limit, created_at, guild_id FROM channel_limit_edits WHERE user_id=? ORDER BY CAST(created_at AS INTEGER) DESC")
+            .bind(user_id.to_string())
+            .fetch_all(db).await {
+            Ok(entries) => entries,
+            Err(error) => {
+                error!("Couldn't load limit edits of {}: {}", user_id, error);
+                return "Failed to read the limit changes".to_string();
+            }
+        };
+        let entries: Vec<ChannelLimitEditDatabaseEntry> = entries.into_iter().filter(|entry| {
+            if let Some(entry_guild) = entry.guild_id.as_ref() {
+                return Some(entry_guild.as_str()) == guild_id.map(|guild_id| guild_id.to_string()).as_deref();
+            }
+            // Recorded before guilds were, so the channel's guild is looked up instead
+            let Ok(chn) = entry.channel_id.parse::<u64>() else { return false; };
+            match ChannelId::from(chn).to_channel_cached(&ctx.cache) {
+                Some(Channel::Guild(guild_channel)) => Some(guild_channel.guild_id) == guild_id,
+                _ => true,
+            }
+        }).collect();
+        if entries.is_empty() {
+            return format!("<@{}> hasn't changed any limit", user_id);
+        }
+
+        let pages = entries.len().div_ceil(AUDIT_PAGE_SIZE);
+        if page > pages {
+            return format!("There are only {} pages of limit changes by <@{}>", pages, user_id);
+        }
+        let mut content = format!("Limit changes by <@{}> (page {} of {}, {} changes):\n", user_id, page, pages, entries.len());
+        for entry in entries.iter().skip((page - 1) * AUDIT_PAGE_SIZE).take(AUDIT_PAGE_SIZE) {
+            // Stored in milliseconds
+            let when = match entry.created_at.parse::<i64>() {
+                Ok(created_at) => format!("<t:{}:f>", created_at / 1000),
+                Err(_) => entry.created_at.clone(),
+            };
+            let line = format!("- {} <#{}>: limit set to {}\n", when, entry.channel_id, entry.channel_limit);
+            if content.len() + line.len() > MESSAGE_LENGTH_LIMIT {
+                break;
+            }
+            content.push_str(&line);
+        }
+        content
+    }
+
+    /// Zeroes the deleted messages counter of a channel, or (with `None`) those of every managed channel of the guild.
+    /// The limit edit history is left untouched.
+    pub async fn reset_stats(&mut self, ctx: &Context, guild_id: Option<GuildId>, channel: Option<ChannelId>) -> String {
+        let channels: Vec<ChannelId> = match channel {
+            Some(channel) if !self.channel_queues.contains_key(&channel) => return ManagerError::NotManaged(channel).to_string(),
+            Some(channel) => vec![channel],
+            None => {
+                let Some(guild_id) = guild_id else { return "Statistics can only be reset in a server".to_string(); };
+                self.managed_channels_of(ctx, guild_id).await
+            },
+        };
+
+        if let Some(db) = self.database.as_ref() {
+            match reset_stats_rows(db, &channels).await {
+                Ok(()) => debug!("Reset the statistics of {} channels in the database", channels.len()),
+                Err(error) => {
+                    error!("Failed to reset channel statistics: {}", error);
+                    return "Failed to reset the statistics".to_string();
+                }
+            }
+        }
+
+        for channel in channels.iter() {
+            if let Some(cq) = self.channel_queues.get_mut(channel) {
+                cq.deleted = 0;
+            }
+        }
+        match channel {
+            Some(channel) => format!("Reset the deleted messages counter of <#{}>", channel),
+            None => format!("Reset the deleted messages counters of {} channels", channels.len()),
+        }
+    }
+
+    pub async fn set_system_message_policy(&mut self, channel: &ChannelId, policy: SystemMessagePolicy) -> String {
+        let cq = match self.managed_queue_mut(channel) {
+            Ok(cq) => cq,
+            Err(not_managed) => return not_managed,
+        };
+        cq.system_message_policy = policy;
+
+        if let Some(db) = self.database.as_ref() {
+            match retry_write(move || sqlx::query("UPDATE channel_limits SET system_message_policy=? WHERE channel_id=?")
+                .bind(policy.as_str())
+                .bind(channel.to_string())
+                .execute(db)).await {
+                Ok(result) => debug!("DB update affected {:?} rows", result.rows_affected()),
+                Err(error) => error!("Failed to update system_message_policy: {}", error),
+            }
+        } else {
+            error!("Database is not initialized");
+        }
+
+        match policy {
+            SystemMessagePolicy::Normal => format!("System messages in <#{}> now count toward the limit like any other message", channel),
+            SystemMessagePolicy::Delete => format!("System messages in <#{}> will now be deleted right away", channel),
+            SystemMessagePolicy::Keep => format!("System messages in <#{}> will no longer be deleted", channel),
+        }
+    }
+
+    /// Only changes which messages go from now on, messages that were already deleted are gone for good
+    pub async fn set_retention_direction(&mut self, channel: &ChannelId, direction: RetentionDirection) -> String {
+        let cq = match self.managed_queue_mut(channel) {
+            Ok(cq) => cq,
+            Err(not_managed) => return not_managed,
+        };
+        cq.retention = direction;
+        let limit = cq.limit;
+
+        if let Some(db) = self.database.as_ref() {
+            match retry_write(move || sqlx::query("UPDATE channel_limits SET retention_direction=? WHERE channel_id=?")
+                .bind(direction.as_str())
+                .bind(channel.to_string())
+                .execute(db)).await {
+                Ok(result) => debug!("DB update affected {:?} rows", result.rows_affected()),
+                Err(error) => error!("Failed to update retention_direction: {}", error),
+            }
+        } else {
+            error!("Database is not initialized");
+        }
+
+        match direction {
+            RetentionDirection::KeepNewest => format!("<#{}> now keeps its {} newest messages, deleting the oldest ones", channel, limit),
+            RetentionDirection::KeepOldest => format!("<#{}> now keeps its {} oldest messages, new messages are deleted once it is full", channel, limit),
+        }
+    }
+
+    /// Returns the remaining cooldown (in seconds) if the channel's limit was changed too recently,
+    /// otherwise records a new change for the channel and returns `None`
+    pub fn check_cooldown(&mut self, channel: &ChannelId) -> Option<u64> {
+        let now = Instant::now();
+        if let Some(last_change) = self.last_limit_change.get(channel) {
+            let elapsed = now.duration_since(*last_change);
+            if elapsed < self.limit_cooldown {
+                return Some((self.limit_cooldown - elapsed).as_secs_f64().ceil() as u64);
+            }
+        }
+        self.last_limit_change.insert(*channel, now);
+        None
+    }
+
+    /// Whether the manager is running without a database (memory-only mode)
+    fn is_persistence_disabled(&self) -> bool {
+        self.initialized && self.database.is_none()
+    }
+
+    pub fn version(&self, uptime: Duration) -> String {
+        let uptime = uptime.as_secs();
+        let mut builder = Builder::default();
+        builder.append(format!("autodeletto v{}", env!("CARGO_PKG_VERSION")));
+        if let Some(commit) = option_env!("GIT_COMMIT") {
+            builder.append(format!(" ({})", commit));
+        }
+        builder.append(format!("\nDatabase schema version: {}", format_schema_version(self.schema_version)));
+        builder.append(format!("\nUptime: {}d {}h {}m", uptime / 86400, uptime % 86400 / 3600, uptime % 3600 / 60));
+        builder.string().unwrap()
+    }
+
+    /// The channels to show in the status, those `names` were resolved for
+    fn status_channels<'a>(&'a self, names: &'a HashMap<ChannelId, String>) -> Vec<(&'a String, &'a CappedQueue)> {
+        names.iter().filter_map(|(channel, name)| self.channel_queues.get(channel).map(|cq| (name, cq))).collect()
+    }
+
+    /// Lists the channels `names` were resolved for (the guild's), with the manager's overall state
+    pub fn get_status(&self, names: &HashMap<ChannelId, String>) -> String {
+        let mut builder = Builder::default();
+        let channels = self.status_channels(names);
+        if !channels.is_empty() {
+            builder.append("The following channels are being autodeleted:\n");
+            for (name, cq) in channels {
+                let usage = cq.usage();
+                builder.append(format!("- {} | {} / {} ({:.0}% full){}\n", name, cq.queue.len(), cq.limit, usage * 100.0, cq.status_note()));
+            }
+        } else {
+            builder.append("There are no channels being autodeleted\n");
+        }
+        if self.is_persistence_disabled() {
+            builder.append("Persistence is disabled (the database is unavailable), changes will be lost on restart\n");
+        }
+        if self.dry_run {
+            builder.append("**Dry run**: messages are only logged, nothing is actually deleted\n");
+        }
+        builder.append(format!("Database schema version: {}", format_schema_version(self.schema_version)));
+        builder.string().unwrap()
+    }
+
+    /// Builds the status overview as embeds, paginated to respect the embed field limit
+    pub fn get_status_embeds(&self, names: &HashMap<ChannelId, String>) -> Vec<CreateEmbed> {
+        // Overall health is about whether the manager itself is working
+        let colour = if !self.initialized {
+            Colour::ORANGE
+        } else if self.database.is_none() {
+            Colour::RED
+        } else {
+            Colour::DARK_GREEN
+        };
+        let channels = self.status_channels(names);
+        let tracked: usize = channels.iter().map(|(_, cq)| cq.queue.len()).sum();
+        let deleted: usize = channels.iter().map(|(_, cq)| cq.deleted).sum();
+        let mut footer = format!("{} channels • {} tracked messages • {} deleted • schema {}", channels.len(), tracked, deleted, format_schema_version(self.schema_version));
+        if self.is_persistence_disabled() {
+            footer.push_str(" • persistence disabled");
+        }
+        if self.dry_run {
+            footer.push_str(" • DRY RUN, nothing is deleted");
+        }
+
+        if channels.is_empty() {
+            let mut embed = CreateEmbed::default();
+            embed.title("Autodelete status")
+                .description("There are no channels being autodeleted")
+                .colour(colour)
+                .footer(|f| f.text(&footer));
+            return vec![embed];
+        }
+
+        let pages = channels.len().div_ceil(STATUS_FIELDS_PER_EMBED);
+        channels.chunks(STATUS_FIELDS_PER_EMBED).enumerate().map(|(page, chunk)| {
+            let mut embed = CreateEmbed::default();
+            embed.title(format!("Autodelete status ({}/{})", page + 1, pages))
+                .colour(colour)
+                .footer(|f| f.text(&footer));
+            for (name, cq) in chunk {
+                let usage = cq.usage();
+                embed.field(name, format!("{} / {} ({:.0}% full){}", cq.queue.len(), cq.limit, usage * 100.0, cq.status_note()), true);
+            }
+            embed
+        }).collect()
+    }
+
+    pub async fn remove_limit(&mut self, channel: &ChannelId, user_id: UserId) -> Result<OperationReport, ManagerError> {
+        match self.channel_queues.remove(channel) {
+            Some(mut old_cq) => {
+                old_cq.queue.clear();
+                self.auto_configured_channels.remove(channel);
+                self.pins_cache.remove(channel);
+                self.expected_pin_changes.remove(channel);
+                if let Some(db) = self.database.as_ref() {
+                    // Before the limit row goes, as the guild is taken from it
+                    let timestamp = Utc::now().timestamp_millis();
+                    match retry_write(move || sqlx::query("INSERT INTO channel_limit_edits (user_id, channel_id, channel_limit, created_at, guild_id) VALUES (?,?,?,?,(SELECT guild_id FROM channel_limits WHERE channel_id=?))")
+                        .bind(user_id.to_string())
+                        .bind(channel.to_string())
+                        .bind(0 as u32)
+                        .bind(timestamp)
+                        .bind(channel.to_string())
+                        .execute(db)).await {
+                        Ok(result_audit) => debug!("DB update affected {:?} rows", result_audit.rows_affected()),
+                        Err(error) => error!("Failed to insert channel limit edit: {}", error),
+                    }
+
+                    match retry_write(move || sqlx::query("DELETE FROM channel_limits WHERE channel_id=?").bind(channel.to_string()).execute(db)).await {
+                        Ok(result_limit) => debug!("DB update affected {:?} rows", result_limit.rows_affected()),
+                        Err(error) => error!("Failed to delete channel limit: {}", error),
+                    }
+
+                    match retry_write(move || sqlx::query("DELETE FROM tracked_messages WHERE channel_id=?").bind(channel.to_string()).execute(db)).await {
+                        Ok(result_tracked) => debug!("DB update affected {:?} rows", result_tracked.rows_affected()),
+                        Err(error) => error!("Failed to delete tracked messages: {}", error),
+                    }
+
+                    match retry_write(move || sqlx::query("DELETE FROM protected_messages WHERE channel_id=?").bind(channel.to_string()).execute(db)).await {
+                        Ok(result_protected) => debug!("DB update affected {:?} rows", result_protected.rows_affected()),
+                        Err(error) => error!("Failed to delete protected messages: {}", error),
+                    }
+
+                    match retry_write(move || sqlx::query("DELETE FROM kept_messages WHERE channel_id=?").bind(channel.to_string()).execute(db)).await {
+                        Ok(result_kept) => debug!("DB update affected {:?} rows", result_kept.rows_affected()),
+                        Err(error) => error!("Failed to delete kept messages: {}", error),
+                    }
+
+                    match retry_write(move || sqlx::query("DELETE FROM channel_stats WHERE channel_id=?").bind(channel.to_string()).execute(db)).await {
+                        Ok(result_stats) => debug!("DB update affected {:?} rows", result_stats.rows_affected()),
+                        Err(error) => error!("Failed to delete channel statistics: {}", error),
+                    }
+
+                    match retry_write(move || sqlx::query("DELETE FROM deletion_log WHERE channel_id=?").bind(channel.to_string()).execute(db)).await {
+                        Ok(result_log) => debug!("DB update affected {:?} rows", result_log.rows_affected()),
+                        Err(error) => error!("Failed to delete deletion log: {}", error),
+                    }
+                } else {
+                    error!("Database is not initialized");
+                }
+                if let Some(webhook) = self.config_webhook.as_ref() {
+                    webhook.notify(channel, Some(old_cq.limit), None, Some(user_id));
+                }
+                self.announce_limit(channel, None).await;
+                Ok(OperationReport::Removed { channel: *channel, old_limit: old_cq.limit })
+            }
+            None => Err(ManagerError::NotManaged(*channel))
+        }
+    }
+
+    /// Stops managing every channel of a guild, removing all of their settings in a single transaction
+    pub async fn remove_all_limits(&mut self, ctx: &Context, guild_id: &GuildId, user_id: UserId) -> String {
+        let managed: Vec<ChannelId> = self.channel_queues.keys().cloned().collect();
+        let mut channels = Vec::new();
+        for channel in managed {
+            let resolved = match channel.to_channel_cached(&ctx.cache) {
+                Some(resolved) => Ok(resolved),
+                None => channel.to_channel(ctx).await,
+            };
+            match resolved {
+                Ok(Channel::Guild(guild_channel)) if guild_channel.guild_id == *guild_id => channels.push(channel),
+                Ok(_) => {},
+                Err(error) => debug!("Cannot resolve channel {}: {}", channel, error),
+            }
+        }
+        if channels.is_empty() {
+            return "There are no channels being autodeleted in this server".to_string();
+        }
+
+        if let Some(db) = self.database.as_ref() {
+            if let Err(error) = delete_channel_rows(db, &channels, user_id).await {
+                error!("Failed to remove all channel limits of guild {}: {}", guild_id, error);
+                return "Failed to remove the limits, nothing was changed".to_string();
+            }
+        } else {
+            error!("Database is not initialized");
+        }
+
+        for channel in channels.iter() {
+            let Some(old_cq) = self.channel_queues.remove(channel) else { continue; };
+            self.pins_cache.remove(channel);
+            self.expected_pin_changes.remove(channel);
+            if let Some(webhook) = self.config_webhook.as_ref() {
+                webhook.notify(channel, Some(old_cq.limit), None, Some(user_id));
+            }
+        }
+        info!("Removed the limits of {} channels in guild {}", channels.len(), guild_id);
+        format!("Removed the limits of {} channels", channels.len())
+    }
+
+    /// Walks the channel's history (newest first), keeping the `keep` most recent messages and deleting the rest.
+    /// Kept messages are inserted into the channel's queue, if there is one.
+    /// Deletions are handed off in batches as the walk goes, rather than once it is over.
+    /// Only messages older than `before` are backlog: newer ones were posted after the walk was asked for,
+    /// so they go through `insert_message` like live messages once the backlog is settled.
+    /// Returns how many backlog messages were kept and deleted.
+    /// The walk runs on the command loop, which handles nothing else until it is over (callers bound it with
+    /// `HISTORY_WALK_TIMEOUT`), so the `requester` is shown how far it got in place of the deferred reply.
+    async fn walk_history(&mut self, ctx: &Context, channel: &ChannelId, keep: usize, before: MessageId, requester: Option<&ApplicationCommandInteraction>) -> Result<(usize, usize), ManagerError> {
+        self.walk_messages(ctx, channel, keep, before, requester, channel.messages_iter(ctx).boxed()).await
+    }
+
+    /// Walks `all_messages` as the history of the channel, see `walk_history`. A failed page is fetched again
+    /// when the stream is polled after an error.
+    async fn walk_messages<S>(&mut self, ctx: &Context, channel: &ChannelId, keep: usize, before: MessageId, requester: Option<&ApplicationCommandInteraction>, mut all_messages: S) -> Result<(usize, usize), ManagerError>
+    where
+        S: Stream<Item = serenity::Result<Message>> + Unpin,
+    {
+        let mut walked = 0;
+        let mut message_count = 0;
+        let mut deleted_count = 0;
+        let mut failed_attempts = 0;
+        let mut batch = Vec::with_capacity(BULK_DELETE_MAX);
+        // Newest first, as walked
+        let mut fresh = Vec::new();
+        let tombstone = self.channel_queues.get(channel).is_some_and(|cq| cq.tombstone);
+        let keep_oldest = self.channel_queues.get(channel).is_some_and(|cq| cq.retention == RetentionDirection::KeepOldest);
+        let can_displace = self.channel_queues.get(channel).is_some_and(|cq| cq.walk_can_displace());
+        let started_at = Instant::now();
+
+        while let Some(message_result) = all_messages.next().await {
+            // A failed page is fetched again on the next poll, so the walk resumes where it stopped
+            let msg = match message_result {
+                Ok(msg) => {
+                    failed_attempts = 0;
+                    msg
+                },
+                Err(error) if failed_attempts + 1 < HISTORY_PAGE_ATTEMPTS && is_transient(&error) => {
+                    failed_attempts += 1;
+                    warn!("walk_history: Failed to fetch history of {} after {} messages, retrying (attempt {}/{}): {}", channel, message_count, failed_attempts, HISTORY_PAGE_ATTEMPTS, error);
+                    tokio::time::sleep(HISTORY_PAGE_RETRY_DELAY * failed_attempts).await;
+                    continue;
+                },
+                Err(error) => {
+                    warn!("walk_history: Giving up on history of {} after {} messages: {}", channel, message_count, error);
+                    // What was already decided still goes
+                    self.deleter().submit_batch(ctx, batch, tombstone, "walk_history");
+                    return Err(ManagerError::HistoryIncomplete(*channel, message_count, error));
+                },
+            };
+            walked += 1;
+            if walked % HISTORY_PAGE_SIZE == 0 {
+                // Let the delete workers and event handlers get on with it between pages
+                tokio::task::yield_now().await;
+            }
+            if walked % HISTORY_PROGRESS_INTERVAL == 0 {
+                info!("walk_history: Walked {} messages of {}, {} handed off for deletion so far", walked, channel, deleted_count);
+                if let Some(interaction) = requester {
+                    let progress = format!("Going through the history of <#{}>: {} messages so far, {} being deleted…", channel, walked, deleted_count);
+                    if let Err(why) = interaction.edit_original_interaction_response(ctx, |response| response.content(progress)).await {
+                        debug!("Cannot report history walk progress: {}", why);
+                    }
+                }
+            }
+            if msg.pinned { 
+                // Skip pinned messages (they are handled separately)
+                continue;
+            }
+            if msg.id >= before {
+                // Posted while the walk was pending, it mustn't take a backlog message's place (or be purged as one)
+                fresh.push(msg);
+                continue;
+            }
+            // debug!("update_limit init it {:#?}", msg);
+            if msg.kind == MessageType::ThreadStarterMessage {
+                debug!("Ignoring message {} of type {:?}", msg.id, msg.kind);
+                continue;
+            }
+            if self.channel_queues.get(channel).is_some_and(|cq| cq.protected_oldest.contains(&msg.id) || cq.kept.contains(&msg.id)) {
+                // Skip the channel's protected oldest and kept messages (they are never deleted)
+                continue;
+            }
+            if msg.thread.is_some() && self.channel_queues.get(channel).is_none_or(|cq| !cq.delete_messages_with_threads) {
+                // Skip messages that started a thread, deleting them would orphan the conversation
+                if let Some(cq) = self.channel_queues.get_mut(channel) {
+                    cq.threaded.insert(msg.id);
+                }
+                continue;
+            }
+            if self.is_ignored_type(ctx, &msg) {
+                // Skip ignored message types, they neither count nor get deleted
+                continue;
+            }
+            if self.has_protected_role(ctx, &msg) {
+                // Skip messages from members with a protected role, they neither count nor get deleted
+                continue;
+            }
+            if tombstone && msg.author.id == ctx.cache.current_user_id() && msg.content == TOMBSTONE {
+                // Already expired, it's only there as a record
+                continue;
+            }
+            if keep == 0 && msg.author.id == ctx.cache.current_user_id() {
+                // Ephemeral channels still keep our own messages
+                continue;
+            }
+            if is_system_message(msg.kind) && self.channel_queues.get(channel).is_some_and(|cq| cq.system_message_policy == SystemMessagePolicy::Keep) {
+                // Skip kept system messages, they neither count nor get deleted
+                continue;
+            }
+            let queue_full = self.channel_queues.get(channel).is_some_and(|cq| cq.queue.len() >= cq.capacity());
+            if keep_oldest && can_displace && queue_full && !is_system_message(msg.kind) {
+                // The newest tracked message goes with the older backlog, in bulk, instead of on its own
+                let displaced = self.channel_queues.get_mut(channel).and_then(|cq| cq.displace_newest(TrackedMessage::from(&msg)));
+                if let Some(message) = displaced {
+                    if message.is_bulk_deletable() {
+                        batch.push(message);
+                        if batch.len() == BULK_DELETE_MAX {
+                            self.deleter().submit_batch(ctx, std::mem::replace(&mut batch, Vec::with_capacity(BULK_DELETE_MAX)), tombstone, "walk_history");
+                        }
+                    } else {
+                        self.deleter().submit_batch(ctx, vec![message], tombstone, "walk_history");
+                    }
                 }
+            } else if keep_oldest {
+                // Walking from newest to oldest, every message goes in and the newest ones are evicted
+                self.insert_message(ctx, msg, false).await
+            } else if message_count < keep {
+                self.insert_message(ctx, msg, false).await
             } else {
-                error!("Queue is full but failed to pop message");
+                // We can already delete older messages (counting them as soon as they're handed off).
+                // Messages too old to be bulk deleted go on their own, so workers delete them in parallel.
+                let message = TrackedMessage::from(&msg);
+                if message.is_bulk_deletable() {
+                    batch.push(message);
+                    if batch.len() == BULK_DELETE_MAX {
+                        self.deleter().submit_batch(ctx, std::mem::replace(&mut batch, Vec::with_capacity(BULK_DELETE_MAX)), tombstone, "walk_history");
+                    }
+                } else {
+                    self.deleter().submit_batch(ctx, vec![message], tombstone, "walk_history");
+                }
+                deleted_count += 1;
+            }
+            message_count += 1;
+        }
+        self.deleter().submit_batch(ctx, batch, tombstone, "walk_history");
+
+        if keep_oldest {
+            // Evictions happened while inserting, whatever isn't tracked was handed off
+            let tracked = self.channel_queues.get(channel).map_or(0, |cq| cq.queue.len());
+            deleted_count = message_count.saturating_sub(tracked);
+        }
+
+        // Their own MessageCreated may have come before the channel was managed, so they are inserted here;
+        // those still waiting to be handled are recognized as already tracked
+        let fresh_count = fresh.len();
+        for msg in fresh.into_iter().rev() {
+            self.insert_message(ctx, msg, true).await;
+        }
+        if fresh_count > 0 {
+            debug!("walk_history: {} messages of {} were posted after the walk was asked for", fresh_count, channel);
+        }
+        info!("walk_history: Walked {} messages of {} in {} ms, {} handed off for deletion", walked, channel, started_at.elapsed().as_millis(), deleted_count);
+        Ok((message_count.min(keep), deleted_count))
+    }
+
+    /// The kind of a guild channel, or `None` when it can't be resolved (or isn't a guild channel)
+    async fn channel_kind(&self, ctx: &Context, channel: &ChannelId) -> Option<ChannelType> {
+        let resolved = match channel.to_channel_cached(&ctx.cache) {
+            Some(resolved) => Ok(resolved),
+            None => channel.to_channel(ctx).await,
+        };
+        match resolved {
+            Ok(Channel::Guild(guild_channel)) => Some(guild_channel.kind),
+            _ => None,
+        }
+    }
+
+    /// Rejects channels without messages of their own. Voice and stage channels have a text chat, which
+    /// goes through the same message endpoints as text channels, so they are managed like any other.
+    async fn check_channel_kind(&self, ctx: &Context, channel: &ChannelId) -> Result<(), ManagerError> {
+        match self.channel_kind(ctx, channel).await {
+            Some(ChannelType::Forum) => Err(ManagerError::ForumChannel(*channel)),
+            Some(ChannelType::Text | ChannelType::News | ChannelType::Voice | ChannelType::Stage
+                | ChannelType::PublicThread | ChannelType::PrivateThread | ChannelType::NewsThread) => Ok(()),
+            Some(kind) => Err(ManagerError::UnsupportedChannel(*channel, kind)),
+            // Let the history walk tell whether it can be read
+            None => Ok(()),
+        }
+    }
+
+    fn autoconfig_limit(&self, guild_id: &GuildId, name: &str) -> Option<usize> {
+        self.autoconfig_patterns.get(guild_id)?
+            .iter()
+            .find(|(pattern, _)| glob_matches(pattern, name))
+            .map(|(_, limit)| *limit)
+    }
+
+    /// Manages a channel whose name matches one of its guild's patterns, and stops managing
+    /// an auto-configured channel once it no longer matches
+    pub async fn on_channel_changed(&mut self, ctx: &Context, channel: &GuildChannel) {
+        if channel.kind != ChannelType::Text {
+            return;
+        }
+        match self.autoconfig_limit(&channel.guild_id, &channel.name) {
+            Some(limit) => {
+                // Channels that were already managed were configured by someone, and are left to them
+                if self.channel_queues.contains_key(&channel.id) {
+                    return;
+                }
+                let result = self.update_limit(ctx, &channel.id, limit, false, Some(ctx.cache.current_user_id()), None).await;
+                if result.is_ok() {
+                    self.mark_auto_configured(&channel.id).await;
+                }
+                info!("Auto-configured #{} ({}): {}", channel.name, channel.id, outcome_message(&result));
+            },
+            None => {
+                if self.auto_configured_channels.remove(&channel.id) {
+                    let result = outcome_message(&self.remove_limit(&channel.id, ctx.cache.current_user_id()).await);
+                    info!("Channel #{} ({}) no longer matches any pattern: {}", channel.name, channel.id, result);
+                }
+            },
+        }
+    }
+
+    async fn mark_auto_configured(&mut self, channel: &ChannelId) {
+        self.auto_configured_channels.insert(*channel);
+        let Some(db) = self.database.as_ref() else { return; };
+        match retry_write(move || sqlx::query("UPDATE channel_limits SET auto_configured=1 WHERE channel_id=?").bind(channel.to_string()).execute(db)).await {
+            Ok(result) => debug!("DB update affected {:?} rows", result.rows_affected()),
+            Err(error) => error!("Failed to mark channel {} as auto-configured: {}", channel, error),
+        }
+    }
+
+    async fn apply_autoconfig_patterns(&mut self, ctx: &Context, guild_id: &GuildId) {
+        match guild_id.channels(ctx).await {
+            Ok(channels) => {
+                for channel in channels.values() {
+                    self.on_channel_changed(ctx, channel).await;
+                }
+            },
+            Err(error) => error!("Cannot fetch channels of guild {}: {}", guild_id, error),
+        }
+    }
+
+    /// Stores a name pattern for a guild (a limit of 0 removes it) and applies it to the existing channels
+    pub async fn set_autoconfig_pattern(&mut self, ctx: &Context, guild_id: &GuildId, pattern: String, limit: usize) -> String {
+        let Some(db) = self.database.as_ref() else {
+            error!("Database is not initialized");
+            return "Database is not initialized, please try again later".to_string();
+        };
+
+        let db_pattern = pattern.clone();
+        let result = if limit == 0 {
+            retry_write(move || sqlx::query("DELETE FROM autoconfig_patterns WHERE guild_id=? AND pattern=?")
+                .bind(guild_id.to_string())
+                .bind(db_pattern.clone())
+                .execute(db)).await
+        } else {
+            retry_write(move || sqlx::query("INSERT OR REPLACE INTO autoconfig_patterns VALUES (?, ?, ?)")
+                .bind(guild_id.to_string())
+                .bind(db_pattern.clone())
+                .bind(limit as u32)
+                .execute(db)).await
+        };
+        if let Err(error) = result {
+            error!("Failed to update autoconfig patterns: {}", error);
+            return format!("Failed to update pattern `{}`", pattern);
+        }
+
+        let patterns = self.autoconfig_patterns.entry(*guild_id).or_default();
+        patterns.retain(|(existing, _)| existing != &pattern);
+        if limit == 0 {
+            if patterns.is_empty() {
+                self.autoconfig_patterns.remove(guild_id);
             }
+            return format!("Channels matching `{}` will no longer be configured automatically", pattern);
         }
+        patterns.push((pattern.clone(), limit));
 
-        cq.pins = updated_pins.into();
-        debug!("Local pins list now has {} items", cq.pins.len());
+        self.apply_autoconfig_patterns(ctx, guild_id).await;
+        format!("Channels matching `{}` will now be configured with a limit of {}", pattern, limit)
     }
 
-    pub async fn insert_message(&mut self, ctx: &Context, msg: Message, push_back: bool) {
-        let Some(cq) = self.channel_queues.get_mut(&msg.channel_id) else {return};
+    fn is_blocked(&self, msg: &Message) -> bool {
+        let Some(keywords) = self.blocked_keywords.get(&msg.channel_id) else { return false; };
+        let content = msg.content.to_lowercase();
+        keywords.iter().any(|keyword| content.contains(keyword.as_str()))
+    }
 
-        // Ideally this should not be executed in threads but...
-        // debug!("insert_message {:#?}", msg);
-        match msg.kind {
-            MessageType::ThreadStarterMessage => {
-                debug!("Ignoring message {} of type {:?}", msg.id, msg.kind);
-                return;
-            }
-            _ => {}
+    pub async fn update_blocked_keywords(&mut self, channel: &ChannelId, action: KeywordAction) -> String {
+        let keyword = match action {
+            KeywordAction::List => {
+                return match self.blocked_keywords.get(channel) {
+                    Some(keywords) if !keywords.is_empty() => format!("Blocked keywords for <#{}>: {}", channel, keywords.iter().map(|keyword| format!("`{}`", keyword)).collect::<Vec<_>>().join(", ")),
+                    _ => format!("<#{}> has no blocked keywords", channel),
+                };
+            },
+            KeywordAction::Add(ref keyword) | KeywordAction::Remove(ref keyword) => keyword.trim().to_lowercase(),
+        };
+        if keyword.is_empty() {
+            return "Please provide a keyword".to_string();
         }
+        let Some(db) = self.database.as_ref() else {
+            error!("Database is not initialized");
+            return "Database is not initialized, please try again later".to_string();
+        };
 
-        // If queue is already full, remove the oldest message and delete it
-        while cq.queue.len() >= cq.limit {
-            if let Some(old_message) = cq.queue.pop_front() {
-                debug!("insert_message: Popping and deleting last message (now {} vs {})", cq.queue.len(), cq.limit);
-                if let Err(error) = old_message.delete(ctx).await {
-                    error!("insert_message: Failed to delete message: {}", error);
-                }
-            } else {
-                error!("insert_message: Queue is full but failed to pop message");
-            }
+        let db_keyword = keyword.clone();
+        let result = match action {
+            KeywordAction::Add(_) => retry_write(move || sqlx::query("INSERT OR REPLACE INTO blocked_keywords VALUES (?, ?)")
+                .bind(channel.to_string())
+                .bind(db_keyword.clone())
+                .execute(db)).await,
+            _ => retry_write(move || sqlx::query("DELETE FROM blocked_keywords WHERE channel_id=? AND keyword=?")
+                .bind(channel.to_string())
+                .bind(db_keyword.clone())
+                .execute(db)).await,
+        };
+        if let Err(error) = result {
+            error!("Failed to update blocked keywords: {}", error);
+            return format!("Failed to update blocked keywords for <#{}>", channel);
         }
-        if push_back {
-            cq.queue.push_back(msg);
-        } else {
-            cq.queue.push_front(msg);
+
+        let keywords = self.blocked_keywords.entry(*channel).or_default();
+        keywords.retain(|existing| existing != &keyword);
+        match action {
+            KeywordAction::Add(_) => {
+                keywords.push(keyword.clone());
+                format!("Messages containing `{}` will now be deleted from <#{}>", keyword, channel)
+            },
+            _ => {
+                if keywords.is_empty() {
+                    self.blocked_keywords.remove(channel);
+                }
+                format!("Messages containing `{}` will no longer be deleted from <#{}>", keyword, channel)
+            },
         }
-        debug!("Pushed new message (now {} vs {})", cq.queue.len(), cq.limit);
     }
 
-    pub fn remove_message(&mut self, _ctx: &Context, msg_id: MessageId, channel_id: &ChannelId) {
-        let Some(cq) = self.channel_queues.get_mut(channel_id) else {return};
-        cq.queue.retain(|message| message.id != msg_id);
-        cq.pins.retain(|message| message.id != msg_id);
-        debug!("Queue after remove_message len={}", cq.queue.len());
-        debug!("Pins after remove_message len={}", cq.pins.len());
+    /// Whether the author holds one of the channel's protected roles.
+    /// This is decided once, when the message is first seen: messages are never tracked again if their
+    /// author loses the role later, and messages tracked before the author gained it stay deletable.
+    fn has_protected_role(&mut self, ctx: &Context, msg: &Message) -> bool {
+        if !self.protected_roles.contains_key(&msg.channel_id) {
+            return false;
+        }
+        // Live messages carry their author's roles, history messages need the member cache
+        let author_roles = match msg.member.as_ref() {
+            Some(member) => member.roles.clone(),
+            None => {
+                let guild_id = msg.guild_id.or_else(|| ctx.cache.guild_channel(msg.channel_id).map(|channel| channel.guild_id));
+                guild_id.and_then(|guild_id| self.member_roles(ctx, guild_id, msg.author.id)).unwrap_or_default()
+            },
+        };
+        let Some(roles) = self.protected_roles.get(&msg.channel_id) else { return false; };
+        author_roles.iter().any(|role| roles.contains(role))
     }
 
-    pub fn remove_messages(&mut self, _ctx: &Context, msg_ids: Vec<MessageId>, channel_id: &ChannelId) {
-        let Some(cq) = self.channel_queues.get_mut(channel_id) else {return};
-        cq.queue.retain(|message| !msg_ids.contains(&message.id));
-        cq.pins.retain(|message| !msg_ids.contains(&message.id));
-        debug!("Queue after remove_messages len={}", cq.queue.len());
-        debug!("Pins after remove_messages len={}", cq.pins.len());
+    /// Roles of a member, cached for a short while.
+    /// Features checking members on every message should go through this rather than resolving them each time.
+    pub fn member_roles(&mut self, ctx: &Context, guild_id: GuildId, user_id: UserId) -> Option<Vec<RoleId>> {
+        self.member_cache.roles(ctx, guild_id, user_id)
     }
 
-    pub fn insert_pin(&mut self, _ctx: &Context, msg: Message) {
-        let Some(cq) = self.channel_queues.get_mut(&msg.channel_id) else {return};
+    pub fn on_member_updated(&mut self, guild_id: GuildId, user_id: UserId) {
+        self.member_cache.invalidate_member(guild_id, user_id);
+    }
 
-        if cq.pins.is_empty() {
-            // Simply insert it
-            debug!("Insert new pin {} (channel={}; ts={}) at the front", msg.id, msg.channel_id, msg.timestamp);
-            cq.pins.push_front(msg);
+    /// Deletes what the banned member has in the guild's queues, when the guild asked for it.
+    /// Only tracked messages are found: untracked ones are left to Discord's own ban purge.
+    pub async fn on_user_banned(&mut self, ctx: &Context, guild_id: GuildId, user_id: UserId) {
+        if !self.purge_on_ban.contains(&guild_id) {
             return;
         }
-
-        // Insert in the correct chronological position
-        let mut index = 0;
-        for queued_msg in cq.pins.iter() {
-            if queued_msg.timestamp > msg.timestamp {
-                debug!("Insert new pin {} (channel={}; ts={}) at idx={} (was msg {}; ts={})", msg.id, msg.channel_id, msg.timestamp, index, queued_msg.id, queued_msg.timestamp);
-                cq.pins.insert(index, msg);
-                return;
+        let deleter = self.deleter();
+        let mut purged = 0;
+        for (channel, cq) in self.channel_queues.iter_mut() {
+            if guild_of(ctx, channel).await != Some(guild_id) {
+                continue;
             }
-            index += 1;
+            let (banned, kept): (VecDeque<TrackedMessage>, VecDeque<TrackedMessage>) = cq.queue.drain(..).partition(|message| message.author_id == Some(user_id));
+            cq.queue = kept;
+            if banned.is_empty() {
+                continue;
+            }
+            cq.heavy.retain(|message| message.author_id != Some(user_id));
+            debug!("Deleting {} messages of banned user {} in {}", banned.len(), user_id, channel);
+            purged += banned.len();
+            deleter.submit_batch(ctx, banned.into_iter().collect(), false, "on_user_banned");
         }
-        
-        // If its still not added, we can assume it is the newest
-        debug!("Insert new pin {} (channel={}; ts={}) at the back (now len={})", msg.id, msg.channel_id, msg.timestamp, cq.pins.len());
-        cq.pins.push_back(msg);
+        info!("Handed off {} tracked messages of {}, banned from guild {}, for deletion", purged, user_id, guild_id);
     }
 
-    pub fn get_status(&self) -> String {
-        let mut builder = Builder::default();
-        if self.channel_queues.len() > 0 {
-            builder.append("The following channels are being autodeleted:\n");
-            for (channel, cq) in self.channel_queues.iter() {
-                let usage = (cq.queue.len() as f64) / (cq.limit as f64);
-                builder.append(format!("- {} | {} / {} ({:.0}% full)\n", channel.mention(), cq.queue.len(), cq.limit, usage * 100.0));
-            }
-        } else {
-            builder.append("There are no channels being autodeleted");
-        }
-        builder.string().unwrap()
+    /// A role's permissions may have changed, and any member of the guild may hold it
+    pub fn on_roles_updated(&mut self, guild_id: GuildId) {
+        self.member_cache.invalidate_guild(guild_id);
+        debug!("Forgot cached members of guild {} ({} members still cached)", guild_id, self.member_cache.len());
     }
 
-    pub async fn remove_limit(&mut self, channel: &ChannelId, user_id: UserId) -> String {
-        match self.channel_queues.remove(channel) {
-            Some(mut old_cq) => {
-                old_cq.queue.clear();
-                if let Some(db) = self.database.as_ref() {
-                    let _result_limit = sqlx::query("DELETE FROM channel_limits WHERE channel_id=?").bind(channel.to_string()).execute(db).await.unwrap();
-                    debug!("DB update affected {:?} rows", _result_limit.rows_affected());
+    pub async fn update_protected_roles(&mut self, ctx: &Context, channel: &ChannelId, action: RoleAction) -> String {
+        let role = match action {
+            RoleAction::List => {
+                return match self.protected_roles.get(channel) {
+                    Some(roles) if !roles.is_empty() => format!("Protected roles for <#{}>: {}", channel, roles.iter().map(|role| format!("<@&{}>", role)).collect::<Vec<_>>().join(", ")),
+                    _ => format!("<#{}> has no protected roles", channel),
+                };
+            },
+            RoleAction::Add(role) | RoleAction::Remove(role) => role,
+        };
+        let Some(db) = self.database.as_ref() else {
+            error!("Database is not initialized");
+            return "Database is not initialized, please try again later".to_string();
+        };
 
-                    let _result_audit = sqlx::query("INSERT INTO channel_limit_edits VALUES (?,?,?,?)")
-                        .bind(user_id.to_string())
-                        .bind(channel.to_string())
-                        .bind(0 as u32)
-                        .bind(Utc::now().timestamp_millis())
-                        .execute(db).await.unwrap();
-                    debug!("DB update affected {:?} rows", _result_audit.rows_affected());
-                } else {
-                    error!("Database is not initialized");
+        let result = match action {
+            RoleAction::Add(_) => retry_write(move || sqlx::query("INSERT OR REPLACE INTO protected_roles VALUES (?, ?)")
+                .bind(channel.to_string())
+                .bind(role.to_string())
+                .execute(db)).await,
+            _ => retry_write(move || sqlx::query("DELETE FROM protected_roles WHERE channel_id=? AND role_id=?")
+                .bind(channel.to_string())
+                .bind(role.to_string())
+                .execute(db)).await,
+        };
+        if let Err(error) = result {
+            error!("Failed to update protected roles: {}", error);
+            return format!("Failed to update protected roles for <#{}>", channel);
+        }
+
+        let roles = self.protected_roles.entry(*channel).or_default();
+        roles.retain(|existing| *existing != role);
+        match action {
+            RoleAction::Add(_) => {
+                roles.push(role);
+                // Already tracked messages of current role members are released as well, as far as the cache knows them
+                let guild_id = ctx.cache.guild_channel(channel).map(|channel| channel.guild_id);
+                if let (Some(guild_id), Some(cq)) = (guild_id, self.channel_queues.get_mut(channel)) {
+                    let is_protected = |message: &TrackedMessage| message.author_id
+                        .and_then(|author_id| ctx.cache.member(guild_id, author_id))
+                        .is_some_and(|member| member.roles.contains(&role));
+                    let tracked = cq.queue.len();
+                    cq.queue.retain(|message| !is_protected(message));
+                    cq.heavy.retain(|message| !is_protected(message));
+                    debug!("Released {} tracked messages of role {} in {}", tracked - cq.queue.len(), role, channel);
+                }
+                format!("Messages from members with <@&{}> will no longer be deleted from <#{}>", role, channel)
+            },
+            _ => {
+                if roles.is_empty() {
+                    self.protected_roles.remove(channel);
                 }
-                format!("Removed limit ({}) from <#{}>", old_cq.limit, channel)
+                format!("Messages from members with <@&{}> will be deleted from <#{}> again, starting with new ones", role, channel)
+            },
+        }
+    }
+
+    /// Denied channels are never permitted; if any channel is allowed, only allowed channels are permitted
+    fn is_channel_permitted(&self, channel: &ChannelId) -> bool {
+        if self.denied_channels.contains(channel) {
+            return false;
+        }
+        self.allowed_channels.is_empty() || self.allowed_channels.contains(channel)
+    }
+
+    pub async fn set_channel_access(&mut self, channel: &ChannelId, access: ChannelAccess) -> String {
+        let Some(db) = self.database.as_ref() else {
+            error!("Database is not initialized");
+            return "Database is not initialized, please try again later".to_string();
+        };
+
+        let result = match access {
+            ChannelAccess::Clear => retry_write(move || sqlx::query("DELETE FROM channel_access WHERE channel_id=?")
+                .bind(channel.to_string())
+                .execute(db)).await,
+            _ => retry_write(move || sqlx::query("INSERT OR REPLACE INTO channel_access VALUES (?, ?)")
+                .bind(channel.to_string())
+                .bind(access == ChannelAccess::Allow)
+                .execute(db)).await,
+        };
+        match result {
+            Ok(result_access) => debug!("DB update affected {:?} rows", result_access.rows_affected()),
+            Err(error) => {
+                error!("Failed to update channel access: {}", error);
+                return format!("Failed to update access for <#{}>", channel);
             }
-            None => format!("<#{}> doesn't have a limit!", channel)
+        }
+
+        self.allowed_channels.remove(channel);
+        self.denied_channels.remove(channel);
+        match access {
+            ChannelAccess::Allow => {
+                self.allowed_channels.insert(*channel);
+                format!("<#{}> is now allowed to be autodeleted", channel)
+            },
+            ChannelAccess::Deny => {
+                self.denied_channels.insert(*channel);
+                format!("<#{}> is now denied from being autodeleted", channel)
+            },
+            ChannelAccess::Clear => format!("<#{}> is no longer explicitly allowed or denied", channel),
         }
     }
 
-    pub async fn update_limit(&mut self, ctx: &Context, channel: &ChannelId, new_limit: usize, is_init: bool, user_id: Option<UserId>) -> String {
-        
-        async fn update_db(channel: &ChannelId, new_limit: usize, user_id: Option<UserId>, db_ref: Option<&Pool<Sqlite>>) -> Result<(), ()> {
-            if let Some(db) = db_ref {
-                let _result_limit = sqlx::query("INSERT OR REPLACE INTO channel_limits VALUES (?, ?)")
-                    .bind(channel.to_string())
-                    .bind(new_limit as u32)
-                    .execute(db).await.unwrap();
-                debug!("DB update affected {:?} rows", _result_limit.rows_affected());
+    pub async fn trim(&mut self, ctx: &Context, channel: &ChannelId, count: usize, requester: Option<&ApplicationCommandInteraction>) -> String {
+        if !self.is_channel_permitted(channel) {
+            return format!("<#{}> is not permitted to be autodeleted by this bot", channel);
+        }
+        if self.channel_queues.contains_key(channel) {
+            return format!("<#{}> is already being autodeleted, use /configure to change its limit instead", channel);
+        }
 
-                let _result_audit = sqlx::query("INSERT INTO channel_limit_edits VALUES (?,?,?,?)")
-                    .bind(user_id.expect("Limit updated but no user received").to_string())
-                    .bind(channel.to_string())
-                    .bind(new_limit as u32)
-                    .bind(Utc::now().timestamp_millis())
-                    .execute(db).await.unwrap();
-                debug!("DB update affected {:?} rows", _result_audit.rows_affected());
-                Ok(())
-            } else {
-                error!("Database is not initialized");
-                Err(())
+        // Without a queue for the channel, kept messages are simply not tracked
+        match self.walk_history(ctx, channel, count, snowflake_now(), requester).await {
+            Ok((_kept, deleted)) => format!("Trimmed <#{}> down to {} messages, deleting {} messages", channel, count, deleted),
+            Err(error) => {
+                error!("Uh oh! Error: {}", error);
+                error.to_string()
             }
         }
+    }
+
+    /// When a `requester` is given, they are told how a purge that outlives the command went.
+    /// Forum channels cannot be managed as a whole: each of their posts is a thread, which is managed on its own.
+    pub async fn update_limit(&mut self, ctx: &Context, channel: &ChannelId, new_limit: usize, is_init: bool, user_id: Option<UserId>, requester: Option<&ApplicationCommandInteraction>) -> Result<OperationReport, ManagerError> {
+        if !self.is_channel_permitted(channel) {
+            return Err(ManagerError::NotPermitted(*channel));
+        }
+        // Interaction IDs are snowflakes too, created when the command was invoked
+        let invoked = requester.map_or_else(snowflake_now, |interaction| MessageId::from(interaction.id.0));
+
+        if !is_init && !self.channel_queues.contains_key(channel) {
+            self.check_channel_kind(ctx, channel).await?;
+        }
+
         let Some(queue) = self.channel_queues.get_mut(channel) else {
             // We do not have a queue for this channel yet, so create it
-            let new_queue = CappedQueue { queue: VecDeque::with_capacity(new_limit), pins: VecDeque::with_capacity(CHANNEL_PIN_LIMIT), limit: new_limit};
+            let mut new_queue = CappedQueue::new(new_limit, self.deletion_rate, self.deleter());
+            new_queue.protected_oldest = self.pending_protected_oldest.remove(channel).unwrap_or_default();
+            new_queue.kept = self.pending_kept.remove(channel).unwrap_or_default();
+            new_queue.retention = self.pending_retention.remove(channel).unwrap_or_default();
             self.channel_queues.insert(*channel, new_queue);
             
             // Now iterate over the channel's messages and delete as needed
-            let mut all_messages = channel.messages_iter(ctx).boxed();
-            let mut message_count = 0;
-
-            while let Some(message_result) = all_messages.next().await {
-                match message_result {
-                    Ok(msg) => {
-                        if msg.pinned { 
-                            // Skip pinned messages (they are handled separately)
-                            continue;
-                        }
-                        // debug!("update_limit init it {:#?}", msg);
-                        match msg.kind {
-                            MessageType::ThreadStarterMessage => {
-                                debug!("Ignoring message {} of type {:?}", msg.id, msg.kind);
-                                continue;
-                            }
-                            _ => {}
-                        }
-                        if message_count < new_limit {
-                            self.insert_message(ctx, msg, false).await
-                        } else {
-                            // We can already delete older messages
-                            if let Err(error) = msg.delete(ctx).await {
-                                error!("update_limit init: Failed to delete message: {}", error);
-                            }
+            let mut catching_up = false;
+            let (message_count, deleted) = match tokio::time::timeout(HISTORY_WALK_TIMEOUT, self.walk_history(ctx, channel, new_limit, invoked, requester)).await {
+                Ok(Ok(walked)) => walked,
+                Ok(Err(error)) => {
+                    error!("Uh oh! Error: {}", error);
+                    // What was processed honors the limit, but a channel that was never configured isn't kept half-walked
+                    if !is_init {
+                        self.channel_queues.remove(channel);
+                    }
+                    return Err(error)
+                },
+                Err(_) => {
+                    // Keep whatever was processed so far; once the queue is full, everything older goes
+                    // (unless the oldest are kept, then the unwalked messages are left alone)
+                    let Some(cq) = self.channel_queues.get(channel) else { return Err(ManagerError::NoLongerManaged(*channel)); };
+                    warn!("History walk of {} timed out after {:?} with {} messages tracked", channel, HISTORY_WALK_TIMEOUT, cq.queue.len());
+                    if cq.retention == RetentionDirection::KeepNewest && cq.queue.len() >= cq.capacity() {
+                        if let Some(oldest) = cq.queue.front() {
+                            let requester = requester.cloned().map(|interaction| (interaction, self.purge_summary_dm));
+                            let exemptions = PurgeExemptions { protected_roles: self.protected_roles.get(channel).cloned().unwrap_or_default(), ..cq.purge_exemptions() };
+                            tokio::spawn(purge_older_than(ctx.clone(), *channel, oldest.id, exemptions, requester, self.dry_run, self.deletion_reasons.for_channel(ctx, *channel)));
                         }
-                    },
-                    Err(error) => {
-                        error!("Uh oh! Error: {}", error);
-                        return error.to_string()
-                    },
-                };
-                message_count = message_count + 1;
-            }
+                    }
+                    catching_up = true;
+                    (cq.queue.len(), cq.deleted)
+                },
+            };
 
             match channel.pins(ctx).await {
                 Ok(pinned_messages) => {
@@ -397,47 +5650,741 @@ impl MessageManager {
             debug!("Sanity set queue limit to {} (message_count={})", new_limit, message_count);
 
             if !is_init {
-                let _ = update_db(channel, new_limit, user_id, self.database.as_ref()).await;
-                return format!("Created limit {} for channel <#{}>, and I'm already purging older messages!", new_limit, channel);
+                // Set by hand, so the channel is no longer the autoconfig's to remove
+                self.auto_configured_channels.remove(channel);
+                let _ = update_limit_db(channel, guild_of(ctx, channel).await, new_limit, user_id, self.database.as_ref()).await;
+                if let Some(webhook) = self.config_webhook.as_ref() {
+                    webhook.notify(channel, None, Some(new_limit), user_id);
+                }
+                self.announce_limit(channel, Some(new_limit)).await;
+                let slow_fill_note = if catching_up { "" } else { self.channel_queues.get(channel).map_or("", |cq| cq.slow_fill_note()) };
+                return Ok(OperationReport::Created { channel: *channel, limit: new_limit, deleted, catching_up, slow_fill_note });
             } else {
-                return format!("Initialized channel {} limit to {}", channel, new_limit);
+                return Ok(OperationReport::Initialized { channel: *channel, limit: new_limit });
             }
         };
 
-        let _ = update_db(channel, new_limit, user_id, self.database.as_ref()).await;
+        self.auto_configured_channels.remove(channel);
+        let _ = update_limit_db(channel, guild_of(ctx, channel).await, new_limit, user_id, self.database.as_ref()).await;
 
         let old_limit = queue.limit;
         let old_capacity = queue.queue.capacity();
 
         // Edge case, but we can early return here
-        if old_limit == new_limit {return format!("{} already is the limit for <#{}>!", new_limit, channel)};
+        if old_limit == new_limit {return Ok(OperationReport::Unchanged { channel: *channel, limit: new_limit })};
 
-        if old_limit < new_limit {
-            // Capacity is increasing, just update it (not like we can recover deleted messages anyway)
-            debug!("Increase capacity (alloc diff = {})", new_limit - old_capacity);
-            if new_limit > old_capacity {
-                queue.queue.reserve(new_limit - old_capacity);
+        if let Some(webhook) = self.config_webhook.as_ref() {
+            webhook.notify(channel, Some(old_limit), Some(new_limit), user_id);
+        }
+
+        let report = if old_limit < new_limit {
+            // Capacity is increasing, just update it. History isn't walked again: whatever was over the old limit
+            // was deleted, so the extra room only fills up with new messages.
+            // The allocated capacity may already exceed the new limit (VecDeque rounds it up)
+            let missing_capacity = new_limit.saturating_sub(old_capacity);
+            debug!("Increase capacity (alloc diff = {})", missing_capacity);
+            if missing_capacity > 0 {
+                // `reserve` counts from the length, not from the allocated capacity
+                queue.queue.reserve(new_limit.saturating_sub(queue.queue.len()));
+            }
+            let merged = queue.merge_duplicates();
+            if merged > 0 {
+                warn!("Merged {} duplicate entries in the queue of {}", merged, channel);
             }
             queue.limit = new_limit;
-            format!("Okay, I increased the limit of <#{}> from {} to {}!", channel, old_limit, new_limit)
+            OperationReport::Increased { channel: *channel, old_limit, new_limit, slow_fill_note: queue.slow_fill_note() }
         } else {
             // Capacity is decreasing, so we need to purge (old_limit - new_limit) messages from the queue
-            let mut remaining_messages = if queue.queue.len() > new_limit {queue.queue.len() - new_limit} else {0};
-            debug!("Have to delete {} messages", remaining_messages);
-            while remaining_messages > 0 {
-                if let Some(old_message) = queue.queue.pop_front() {
-                    debug!("update_limit: Popping and deleting last message (now {} vs {})", queue.queue.len(), queue.limit);
-                    if let Err(error) = old_message.delete(ctx).await {
-                        error!("update_limit Failed to delete message: {}", error);
+            queue.limit = new_limit;
+            debug!("Have to delete {} messages", queue.queue.len().saturating_sub(queue.capacity()));
+            let deleted = queue.evict_excess(ctx, "update_limit");
+            debug!("Cut capacity down -> now is {} (should be {})", queue.queue.len(), queue.capacity());
+            OperationReport::Decreased { channel: *channel, old_limit, new_limit, deleted }
+        };
+        self.announce_limit(channel, Some(new_limit)).await;
+        Ok(report)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serenity::cache::Cache;
+    use serenity::client::bridge::gateway::ShardMessenger;
+    use serenity::futures::channel::mpsc::unbounded;
+    use serenity::http::Http;
+
+    const GUILD: u64 = 1;
+    const CHANNEL: u64 = 1000;
+    const AUTHOR: u64 = 2000;
+
+    /// A context whose cache knows `CHANNEL` (so its guild is never fetched), and which can't reach Discord
+    fn test_context() -> Context {
+        let (shard_tx, _shard_rx) = unbounded();
+        let ctx = Context {
+            data: Arc::new(RwLock::new(TypeMap::new())),
+            shard: ShardMessenger::new(shard_tx),
+            shard_id: 0,
+            http: Arc::new(Http::new("token")),
+            cache: Arc::new(Cache::new()),
+        };
+        cache_channel(&ctx, CHANNEL, GUILD, "general");
+        ctx
+    }
+
+    /// Makes a text channel known to the cache
+    fn cache_channel(ctx: &Context, channel: u64, guild: u64, name: &str) {
+        let mut channel_create: serenity::model::event::ChannelCreateEvent = serde_json::from_value(serde_json::json!({
+            "id": channel.to_string(),
+            "guild_id": guild.to_string(),
+            "type": 0,
+            "name": name,
+            "position": 0,
+            "permission_overwrites": [],
+        })).expect("Test channel is valid");
+        ctx.cache.update(&mut channel_create);
+    }
+
+    /// A deleter whose jobs are kept for the test to inspect, instead of being sent to Discord
+    fn test_deleter() -> (Deleter, UnboundedReceiver<DeleteJob>) {
+        let (jobs, receiver) = mpsc::unbounded_channel();
+        (Deleter { jobs }, receiver)
+    }
+
+    /// A message of `CHANNEL` posted `id` seconds after the start of 2023
+    fn test_message(id: u64, content: &str, pinned: bool) -> Message {
+        serde_json::from_value(serde_json::json!({
+            "id": id.to_string(),
+            "channel_id": CHANNEL.to_string(),
+            "author": { "id": AUTHOR.to_string(), "username": "author", "discriminator": "0001", "avatar": null },
+            "content": content,
+            "timestamp": Timestamp::from_unix_timestamp(1_672_531_200 + id as i64).unwrap().to_string(),
+            "edited_timestamp": null,
+            "tts": false,
+            "mention_everyone": false,
+            "mentions": [],
+            "mention_roles": [],
+            "attachments": [],
+            "embeds": [],
+            "pinned": pinned,
+            "type": 0,
+        })).expect("Test message is valid")
+    }
+
+    /// A message that started a thread of the same ID
+    fn test_thread_starter(id: u64) -> Message {
+        let mut message = test_message(id, "starts a thread", false);
+        message.thread = Some(serde_json::from_value(serde_json::json!({
+            "id": id.to_string(),
+            "guild_id": GUILD.to_string(),
+            "parent_id": CHANNEL.to_string(),
+            "type": 11,
+            "name": "thread",
+        })).expect("Test thread is valid"));
+        message
+    }
+
+    /// A manager with one managed channel, tracking `messages` under `limit`
+    fn test_manager(limit: usize, messages: &[Message]) -> (MessageManager, UnboundedReceiver<DeleteJob>) {
+        let (deleter, jobs) = test_deleter();
+        let mut cq = CappedQueue::new(limit, 0, deleter.clone());
+        for message in messages {
+            insert_chronologically(&mut cq.queue, TrackedMessage::from(message));
+        }
+        let mut message_manager = MessageManager { deleter: Some(deleter), initialized: true, ..Default::default() };
+        message_manager.channel_queues.insert(ChannelId::from(CHANNEL), cq);
+        (message_manager, jobs)
+    }
+
+    fn queued_ids(message_manager: &MessageManager) -> Vec<u64> {
+        message_manager.channel_queues[&ChannelId::from(CHANNEL)].queue.iter().map(|message| message.id.0).collect()
+    }
+
+    /// IDs of the messages handed off for deletion so far, in order
+    fn handed_off(jobs: &mut UnboundedReceiver<DeleteJob>) -> Vec<u64> {
+        let mut ids = Vec::new();
+        while let Ok(job) = jobs.try_recv() {
+            ids.extend(job.messages.iter().map(|message| message.id.0));
+        }
+        ids
+    }
+
+    /// An error response from Discord with the given status
+    fn discord_error(status: u16) -> serenity::Error {
+        let response = serenity::http::error::ErrorResponse {
+            status_code: reqwest::StatusCode::from_u16(status).expect("Valid status code"),
+            url: reqwest::Url::parse("https://discord.com/api/v10/channels").expect("Valid URL"),
+            error: serde_json::from_value(serde_json::json!({ "code": 0, "message": "Test error" })).expect("Valid error"),
+        };
+        serenity::Error::Http(Box::new(serenity::http::error::Error::UnsuccessfulRequest(response)))
+    }
+
+    /// The channel's history as `messages_iter` yields it, newest first
+    fn history(messages: Vec<serenity::Result<Message>>) -> impl Stream<Item = serenity::Result<Message>> + Unpin {
+        serenity::futures::stream::iter(messages)
+    }
+
+    #[tokio::test]
+    async fn pins_burst_reuses_cache_and_reconciles_last_event() {
+        let ctx = test_context();
+        let channel = ChannelId::from(CHANNEL);
+        let messages = [test_message(1, "first", false), test_message(2, "second", false), test_message(3, "third", false)];
+        let (mut message_manager, _jobs) = test_manager(5, &messages);
+        let (commands, mut refetches) = mpsc::channel(4);
+        message_manager.commands = Some(commands);
+
+        // The first event of the burst was fetched just before the cache expires
+        let fetched_at = Instant::now() - (PINS_CACHE_TTL - Duration::from_millis(50));
+        message_manager.pins_cache.insert(channel, (vec![test_message(1, "first", true)], fetched_at));
+        message_manager.on_pins_updated(&ctx, channel).await;
+        assert_eq!(queued_ids(&message_manager), vec![2, 3]);
+
+        // A second event within the TTL reuses the cache, and only one refetch is planned
+        message_manager.on_pins_updated(&ctx, channel).await;
+        assert_eq!(message_manager.pins_cache[&channel].1, fetched_at);
+        assert_eq!(queued_ids(&message_manager), vec![2, 3]);
+        assert_eq!(message_manager.pins_refetches.len(), 1);
+
+        // Message 2 was pinned by that second event, which the trailing fetch finds
+        let Some(Command::RefetchPins { channel: refetched, .. }) = refetches.recv().await else { panic!("Pins refetch wasn't scheduled"); };
+        assert_eq!(refetched, channel);
+        message_manager.pins_cache.insert(channel, (vec![test_message(1, "first", true), test_message(2, "second", true)], Instant::now()));
+        message_manager.on_pins_refetch(&ctx, refetched).await;
+        assert_eq!(queued_ids(&message_manager), vec![3]);
+        let pins: Vec<u64> = message_manager.channel_queues[&channel].pins.iter().map(|message| message.id.0).collect();
+        assert_eq!(pins, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn supervisor_restarts_crashed_manager_for_next_command() {
+        let (sender, receiver) = mpsc::channel(4);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let supervisor = tokio::spawn(supervise(move |restarts| {
+            let receiver = receiver.clone();
+            async move {
+                loop {
+                    let Some(cmd) = receiver.lock().await.recv().await else { break; };
+                    match cmd {
+                        Command::Flush { .. } if restarts == 0 => panic!("Simulated manager crash"),
+                        Command::Flush { done } => { let _ = done.send(()); },
+                        _ => {},
                     }
-                } else {
-                    error!("Queue is full but failed to pop message");
                 }
-                remaining_messages = remaining_messages - 1;
             }
-            queue.limit = new_limit;
-            debug!("Cut capacity down -> now is {} (should be {})", queue.queue.len(), new_limit);
-            format!("Okay, I decreased the limit of <#{}> from {} to {}, and I'm already purging older messages!", channel, old_limit, new_limit)
+        }, MANAGER_STABLE_PERIOD));
+
+        let (done, _) = oneshot::channel();
+        sender.send(Command::Flush { done }).await.expect("Manager is running");
+        // Handled by the restarted manager, from the same command channel
+        let (done, flushed) = oneshot::channel();
+        sender.send(Command::Flush { done }).await.expect("Command channel survives the crash");
+        tokio::time::timeout(MANAGER_RESTART_DELAY * 5, flushed).await
+            .expect("Restarted manager handles the command")
+            .expect("Restarted manager replies");
+
+        drop(sender);
+        supervisor.await.expect("Supervisor stops with the manager");
+    }
+
+    /// A database file of its own for a test, removed (with its WAL files) when dropped
+    struct TestDatabase(PathBuf);
+
+    impl TestDatabase {
+        fn new(name: &str) -> Self {
+            TestDatabase(std::env::temp_dir().join(format!("autodeletto-{}-{}.sqlite", name, std::process::id())))
+        }
+    }
+
+    impl Drop for TestDatabase {
+        fn drop(&mut self) {
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{}", self.0.display(), suffix));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_limit_updates_from_two_pools() {
+        let file = TestDatabase::new("two-pools");
+        let first = connect_database(&file.0).await.expect("First pool connects");
+        sqlx::migrate!("./migrations").run(&first).await.expect("Migrations run");
+        let second = connect_database(&file.0).await.expect("Second pool connects");
+
+        // Both pools write the same channels at once, as two bot instances sharing the file would
+        let channels: Vec<ChannelId> = (0..10).map(|i| ChannelId::from(CHANNEL + i)).collect();
+        let writes = (0..50).map(|i| {
+            let db = if i % 2 == 0 { &first } else { &second };
+            let user_id = if i % 5 == 0 { None } else { Some(UserId::from(AUTHOR)) };
+            update_limit_db(&channels[i % 10], Some(GuildId::from(GUILD)), 10 + i, user_id, Some(db))
+        });
+        let results = serenity::futures::future::join_all(writes).await;
+        assert!(results.iter().all(Result::is_ok));
+
+        let limits: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM channel_limits").fetch_one(&second).await.unwrap();
+        assert_eq!(limits, 10);
+        // Changes nobody made aren't recorded as edits
+        let edits: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM channel_limit_edits").fetch_one(&first).await.unwrap();
+        assert_eq!(edits, 40);
+    }
+
+    #[test]
+    fn duplicate_detection_forgets_authors_out_of_the_window() {
+        let (deleter, _jobs) = test_deleter();
+        let mut cq = CappedQueue::new(10, 0, deleter);
+        cq.delete_duplicates = true;
+        let mut other_author = test_message(1, "hello", false);
+        other_author.author.id = UserId::from(AUTHOR + 1);
+        assert!(!cq.is_duplicate(&other_author));
+        assert!(!cq.is_duplicate(&test_message(2, "hello", false)));
+        assert!(cq.is_duplicate(&test_message(3, "hello", false)));
+        assert_eq!(cq.last_by_author.len(), 2);
+
+        // Both authors' last messages are out of the window by then
+        let later = test_message(3 + DUPLICATE_WINDOW_SECS as u64, "hello", false);
+        assert!(!cq.is_duplicate(&later));
+        assert_eq!(cq.last_by_author.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn message_posted_during_purge_is_inserted_against_final_limit() {
+        let ctx = test_context();
+        let channel = ChannelId::from(CHANNEL);
+        let (mut message_manager, mut jobs) = test_manager(2, &[]);
+
+        // Message 5 was posted after /configure was invoked, while the walk was under way
+        let walk = history((1..=5).rev().map(|id| test_message(id, "backlog", false)).map(Ok).collect());
+        let walked = message_manager.walk_messages(&ctx, &channel, 2, MessageId::from(5), None, walk).await;
+        assert_eq!(walked.ok(), Some((2, 2)));
+        // It takes the place of the oldest kept message (3) instead of being purged with the backlog
+        assert_eq!(handed_off(&mut jobs), vec![2, 1, 3]);
+        assert_eq!(queued_ids(&message_manager), vec![4, 5]);
+
+        // Its own event, waiting behind the walk, doesn't track it twice
+        message_manager.insert_message(&ctx, test_message(5, "backlog", false), true).await;
+        assert_eq!(queued_ids(&message_manager), vec![4, 5]);
+        // Later messages go through the final limit as usual
+        message_manager.insert_message(&ctx, test_message(6, "live", false), true).await;
+        assert_eq!(queued_ids(&message_manager), vec![5, 6]);
+        assert_eq!(handed_off(&mut jobs), vec![4]);
+    }
+
+    #[tokio::test]
+    async fn more_pins_than_discord_allows_are_reconciled() {
+        let ctx = test_context();
+        let channel = ChannelId::from(CHANNEL);
+        let messages: Vec<Message> = (1..=60).map(|id| test_message(id, "message", false)).collect();
+        let (mut message_manager, mut jobs) = test_manager(100, &messages);
+
+        let pins: Vec<Message> = (1..=CHANNEL_PIN_LIMIT as u64 + 1).map(|id| test_message(id, "message", true)).collect();
+        message_manager.pins_cache.insert(channel, (pins, Instant::now()));
+        message_manager.on_pins_updated(&ctx, channel).await;
+        assert_eq!(message_manager.channel_queues[&channel].pins.len(), CHANNEL_PIN_LIMIT + 1);
+        assert_eq!(queued_ids(&message_manager), (CHANNEL_PIN_LIMIT as u64 + 2..=60).collect::<Vec<u64>>());
+
+        message_manager.insert_pin(&ctx, test_message(61, "message", true));
+        let cq = &message_manager.channel_queues[&channel];
+        assert_eq!(cq.pins.len(), CHANNEL_PIN_LIMIT + 2);
+        assert!(cq.pins.iter().zip(cq.pins.iter().skip(1)).all(|(older, newer)| older.chronological_key() < newer.chronological_key()));
+        assert!(handed_off(&mut jobs).is_empty());
+    }
+
+    #[tokio::test]
+    async fn messages_sharing_a_timestamp_are_evicted_by_id() {
+        let ctx = test_context();
+        let (mut message_manager, mut jobs) = test_manager(2, &[test_message(1, "first", false)]);
+        // A bulk webhook post, whose messages arrive out of order with the same timestamp
+        let newer = test_message(11, "newer", false);
+        let mut older = test_message(10, "older", false);
+        older.timestamp = newer.timestamp;
+
+        message_manager.insert_message(&ctx, newer, true).await;
+        message_manager.insert_message(&ctx, older, true).await;
+        assert_eq!(handed_off(&mut jobs), vec![1]);
+        assert_eq!(queued_ids(&message_manager), vec![10, 11]);
+
+        message_manager.insert_message(&ctx, test_message(20, "latest", false), true).await;
+        assert_eq!(handed_off(&mut jobs), vec![10]);
+        assert_eq!(queued_ids(&message_manager), vec![11, 20]);
+    }
+
+    #[tokio::test]
+    async fn ignored_message_types_are_never_enqueued() {
+        let ctx = test_context();
+        let guild_id = GuildId::from(GUILD);
+        let (mut message_manager, mut jobs) = test_manager(1, &[]);
+
+        // Without a choice of the guild, pin and thread notices are ignored
+        for (id, kind) in [(1, MessageType::PinsAdd), (2, MessageType::ThreadCreated)] {
+            let mut notice = test_message(id, "", false);
+            notice.kind = kind;
+            message_manager.insert_message(&ctx, notice, true).await;
+        }
+        assert!(queued_ids(&message_manager).is_empty());
+
+        message_manager.ignored_types.insert(guild_id, IGNORABLE_MESSAGE_TYPES.iter().map(|(name, _)| *name).collect());
+        let mut id = 10;
+        for (name, kinds) in IGNORABLE_MESSAGE_TYPES {
+            for kind in kinds.iter() {
+                let mut message = test_message(id, name, false);
+                message.kind = *kind;
+                message.guild_id = Some(guild_id);
+                message_manager.insert_message(&ctx, message, true).await;
+                assert!(queued_ids(&message_manager).is_empty(), "{:?} was enqueued", kind);
+                id += 1;
+            }
         }
+        assert!(handed_off(&mut jobs).is_empty());
+
+        // Regular messages still go through
+        let mut regular = test_message(id, "regular", false);
+        regular.guild_id = Some(guild_id);
+        message_manager.insert_message(&ctx, regular, true).await;
+        assert_eq!(queued_ids(&message_manager), vec![id]);
+    }
+
+    #[tokio::test]
+    async fn pins_update_caused_by_the_bot_is_not_reconciled() {
+        let ctx = test_context();
+        let channel = ChannelId::from(CHANNEL);
+        let (mut message_manager, mut jobs) = test_manager(2, &[test_message(2, "second", false), test_message(3, "third", false)]);
+        message_manager.insert_pin(&ctx, test_message(1, "pinned", true));
+
+        // The pinned message was deleted (and so unpinned), its pins update event follows
+        message_manager.remove_message(&ctx, MessageId::from(1), &channel);
+        assert_eq!(message_manager.expected_pin_changes[&channel].0, 1);
+        message_manager.on_pins_updated(&ctx, channel).await;
+
+        // Nothing was fetched, and the event was used up
+        assert!(!message_manager.pins_cache.contains_key(&channel));
+        assert!(!message_manager.expected_pin_changes.contains_key(&channel));
+        assert!(message_manager.channel_queues[&channel].pins.is_empty());
+        assert_eq!(queued_ids(&message_manager), vec![2, 3]);
+        assert!(handed_off(&mut jobs).is_empty());
+    }
+
+    #[tokio::test]
+    async fn history_walk_retries_failed_pages() {
+        let ctx = test_context();
+        let channel = ChannelId::from(CHANNEL);
+        let (mut message_manager, mut jobs) = test_manager(2, &[]);
+        let walk = history(vec![
+            Ok(test_message(6, "message", false)),
+            Ok(test_message(5, "message", false)),
+            Err(discord_error(503)),
+            Ok(test_message(4, "message", false)),
+            Ok(test_message(3, "message", false)),
+            Err(discord_error(429)),
+            Ok(test_message(2, "message", false)),
+            Ok(test_message(1, "message", false)),
+        ]);
+        let walked = message_manager.walk_messages(&ctx, &channel, 2, MessageId::from(100), None, walk).await;
+        assert_eq!(walked.ok(), Some((2, 4)));
+        assert_eq!(queued_ids(&message_manager), vec![5, 6]);
+        assert_eq!(handed_off(&mut jobs), vec![4, 3, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn history_walk_gives_up_after_repeated_failures_with_limit_honored() {
+        let ctx = test_context();
+        let channel = ChannelId::from(CHANNEL);
+        let (mut message_manager, mut jobs) = test_manager(2, &[]);
+        let mut messages: Vec<serenity::Result<Message>> = (3..=6).rev().map(|id| test_message(id, "message", false)).map(Ok).collect();
+        messages.extend((0..HISTORY_PAGE_ATTEMPTS).map(|_| discord_error(502)).map(Err));
+        messages.push(Ok(test_message(2, "never reached", false)));
+
+        let walked = message_manager.walk_messages(&ctx, &channel, 2, MessageId::from(100), None, history(messages)).await;
+        assert!(matches!(walked, Err(ManagerError::HistoryIncomplete(failed, 4, _)) if failed == channel));
+        // What was walked is consistent: the newest messages are kept, the rest was still handed off
+        assert_eq!(queued_ids(&message_manager), vec![5, 6]);
+        assert_eq!(handed_off(&mut jobs), vec![4, 3]);
+    }
+
+    #[tokio::test]
+    async fn limit_changes_with_capacity_at_the_edges() {
+        let ctx = test_context();
+        let channel = ChannelId::from(CHANNEL);
+        let messages: Vec<Message> = (1..=5).map(|id| test_message(id, "message", false)).collect();
+        let (mut message_manager, mut jobs) = test_manager(8, &messages);
+        message_manager.channel_queues.get_mut(&channel).unwrap().queue.reserve(100);
+
+        // The allocated capacity is way past the new limit
+        let report = message_manager.update_limit(&ctx, &channel, 3, false, None, None).await;
+        assert!(matches!(report, Ok(OperationReport::Decreased { deleted: 2, .. })));
+        assert_eq!(queued_ids(&message_manager), vec![3, 4, 5]);
+        // The queue is shorter than the new limit, which is still below the allocated capacity
+        let report = message_manager.update_limit(&ctx, &channel, 10, false, None, None).await;
+        assert!(matches!(report, Ok(OperationReport::Increased { old_limit: 3, new_limit: 10, .. })));
+        assert_eq!(handed_off(&mut jobs), vec![1, 2]);
+
+        // More pins than the limit leave no room at all, rather than underflowing
+        let cq = message_manager.channel_queues.get_mut(&channel).unwrap();
+        cq.pins_count_toward_limit = true;
+        cq.pins = (10..22).map(|id| TrackedMessage::from(&test_message(id, "pinned", true))).collect();
+        assert_eq!(cq.capacity(), 0);
+        let report = message_manager.update_limit(&ctx, &channel, 5, false, None, None).await;
+        assert!(matches!(report, Ok(OperationReport::Decreased { deleted: 3, .. })));
+        assert!(queued_ids(&message_manager).is_empty());
+    }
+
+    #[tokio::test]
+    async fn late_older_message_is_queued_chronologically() {
+        let ctx = test_context();
+        let (mut message_manager, mut jobs) = test_manager(3, &[test_message(5, "message", false), test_message(6, "message", false)]);
+
+        // Replayed after a reconnection, it is older than everything tracked
+        message_manager.insert_message(&ctx, test_message(3, "late", false), true).await;
+        assert_eq!(queued_ids(&message_manager), vec![3, 5, 6]);
+        assert!(handed_off(&mut jobs).is_empty());
+        // So it's the first to go, not the newest
+        message_manager.insert_message(&ctx, test_message(7, "message", false), true).await;
+        assert_eq!(queued_ids(&message_manager), vec![5, 6, 7]);
+        assert_eq!(handed_off(&mut jobs), vec![3]);
+    }
+
+    #[tokio::test]
+    async fn newest_message_survives_rapid_messages_at_limit_one() {
+        let ctx = test_context();
+        let channel = ChannelId::from(CHANNEL);
+        for limit in [1, 0] {
+            let (mut message_manager, mut jobs) = test_manager(limit, &[]);
+            message_manager.channel_queues.get_mut(&channel).unwrap().always_keep_latest = true;
+            for id in 1..=5 {
+                message_manager.insert_message(&ctx, test_message(id, "rapid", false), true).await;
+                assert_eq!(queued_ids(&message_manager), vec![id], "limit {}", limit);
+            }
+            assert_eq!(handed_off(&mut jobs), vec![1, 2, 3, 4], "limit {}", limit);
+        }
+    }
+
+    #[tokio::test]
+    async fn limit_increase_followed_by_new_messages() {
+        let ctx = test_context();
+        let channel = ChannelId::from(CHANNEL);
+        let (mut message_manager, mut jobs) = test_manager(2, &[test_message(1, "message", false), test_message(2, "message", false)]);
+        // Tracked twice, it mustn't take two of the new slots
+        message_manager.channel_queues.get_mut(&channel).unwrap().queue.push_back(TrackedMessage::from(&test_message(2, "message", false)));
+
+        let report = message_manager.update_limit(&ctx, &channel, 4, false, None, None).await;
+        assert!(matches!(report, Ok(OperationReport::Increased { old_limit: 2, new_limit: 4, .. })));
+        // Nothing older is recovered, the new room is filled by new messages without deleting any
+        assert_eq!(queued_ids(&message_manager), vec![1, 2]);
+        for id in 3..=4 {
+            message_manager.insert_message(&ctx, test_message(id, "message", false), true).await;
+        }
+        assert_eq!(queued_ids(&message_manager), vec![1, 2, 3, 4]);
+        assert!(handed_off(&mut jobs).is_empty());
+
+        message_manager.insert_message(&ctx, test_message(5, "message", false), true).await;
+        assert_eq!(queued_ids(&message_manager), vec![2, 3, 4, 5]);
+        assert_eq!(handed_off(&mut jobs), vec![1]);
+    }
+
+    #[tokio::test]
+    async fn thread_starter_messages_are_kept_out_of_the_limit() {
+        let ctx = test_context();
+        let channel = ChannelId::from(CHANNEL);
+        let mut starter = test_thread_starter(2);
+
+        // Neither walked into the queue nor deleted as backlog
+        let (mut message_manager, mut jobs) = test_manager(1, &[]);
+        let walk = history(vec![Ok(test_message(3, "message", false)), Ok(starter.clone()), Ok(test_message(1, "message", false))]);
+        let walked = message_manager.walk_messages(&ctx, &channel, 1, MessageId::from(100), None, walk).await;
+        assert_eq!(walked.ok(), Some((1, 1)));
+        assert_eq!(queued_ids(&message_manager), vec![3]);
+        assert_eq!(handed_off(&mut jobs), vec![1]);
+        assert!(message_manager.channel_queues[&channel].threaded.contains(&starter.id));
+
+        // Nor when received live, even once its thread is gone from the update
+        let (mut message_manager, mut jobs) = test_manager(1, &[test_message(1, "message", false)]);
+        message_manager.insert_message(&ctx, starter.clone(), true).await;
+        starter.thread = None;
+        message_manager.insert_message(&ctx, starter.clone(), true).await;
+        assert_eq!(queued_ids(&message_manager), vec![1]);
+        assert!(handed_off(&mut jobs).is_empty());
+
+        // Unless the channel deletes them anyway
+        let (mut message_manager, mut jobs) = test_manager(1, &[test_message(1, "message", false)]);
+        message_manager.channel_queues.get_mut(&channel).unwrap().delete_messages_with_threads = true;
+        message_manager.insert_message(&ctx, starter, true).await;
+        assert_eq!(queued_ids(&message_manager), vec![2]);
+        assert_eq!(handed_off(&mut jobs), vec![1]);
+    }
+
+    #[test]
+    fn purge_after_a_timed_out_walk_skips_thread_starters() {
+        let ctx = test_context();
+        let channel = ChannelId::from(CHANNEL);
+        let (mut message_manager, _jobs) = test_manager(1, &[]);
+        let starter = test_thread_starter(2);
+        assert!(message_manager.channel_queues[&channel].purge_exemptions().exempts(&ctx, &starter));
+        assert!(!message_manager.channel_queues[&channel].purge_exemptions().exempts(&ctx, &test_message(1, "message", false)));
+
+        message_manager.channel_queues.get_mut(&channel).unwrap().delete_messages_with_threads = true;
+        assert!(!message_manager.channel_queues[&channel].purge_exemptions().exempts(&ctx, &starter));
+    }
+
+    #[test]
+    fn purge_after_a_timed_out_walk_skips_protected_and_kept_messages() {
+        const ROLE: u64 = 3000;
+        let ctx = test_context();
+        let channel = ChannelId::from(CHANNEL);
+        let (mut message_manager, _jobs) = test_manager(1, &[test_message(5, "message", false)]);
+        let cq = message_manager.channel_queues.get_mut(&channel).unwrap();
+        // Seeded on restart before the walk, which then timed out
+        cq.protected_oldest = HashSet::from([MessageId::from(1), MessageId::from(2)]);
+        cq.kept = HashSet::from([MessageId::from(3)]);
+
+        let exemptions = cq.purge_exemptions();
+        let skipped: Vec<u64> = (1..=4).filter(|id| exemptions.exempts(&ctx, &test_message(*id, "message", false))).collect();
+        assert_eq!(skipped, vec![1, 2, 3]);
+
+        // Nor messages from members with a protected role
+        let exemptions = PurgeExemptions { protected_roles: vec![RoleId::from(ROLE)], ..exemptions };
+        let mut protected = test_message(4, "message", false);
+        protected.member = Some(serde_json::from_value(serde_json::json!({ "roles": [ROLE.to_string()], "deaf": false, "mute": false })).expect("Test member is valid"));
+        assert!(exemptions.exempts(&ctx, &protected));
+        assert!(!exemptions.exempts(&ctx, &test_message(4, "message", false)));
+    }
+
+    #[tokio::test]
+    async fn messages_newer_than_the_invocation_are_not_backlog() {
+        let ctx = test_context();
+        let channel = ChannelId::from(CHANNEL);
+        let (mut message_manager, mut jobs) = test_manager(3, &[]);
+        let invoked = snowflake_now();
+        let posted_during_walk = invoked.0 + 1;
+        let mut fresh = test_message(5, "fresh", false);
+        fresh.id = MessageId::from(posted_during_walk);
+
+        let mut walk: Vec<serenity::Result<Message>> = vec![Ok(fresh)];
+        walk.extend((1..=4).rev().map(|id| test_message(id, "backlog", false)).map(Ok));
+        let walked = message_manager.walk_messages(&ctx, &channel, 3, invoked, None, history(walk)).await;
+        // Only the backlog is counted, kept or purged: the fresh message is received like a live one
+        assert_eq!(walked.ok(), Some((3, 1)));
+        assert_eq!(queued_ids(&message_manager), vec![3, 4, posted_during_walk]);
+        assert_eq!(handed_off(&mut jobs), vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn message_deleted_by_someone_else_is_dropped_without_counting() {
+        let ctx = test_context();
+        let channel = ChannelId::from(CHANNEL);
+        let (mut message_manager, mut jobs) = test_manager(2, &[test_message(1, "message", false), test_message(2, "message", false)]);
+        message_manager.insert_message(&ctx, test_message(3, "message", false), true).await;
+        assert_eq!(handed_off(&mut jobs), vec![1]);
+        let evicted = TrackedMessage::from(&test_message(1, "message", false));
+
+        // A moderator got to it first: it's gone as intended, but not by us
+        message_manager.on_deletion_finished(evicted.clone(), "test", Err(discord_error(404))).await;
+        assert_eq!(queued_ids(&message_manager), vec![2, 3]);
+        assert_eq!(message_manager.channel_queues[&channel].deleted, 0);
+
+        // Even when its creation was handled after the handoff, tracking it again
+        insert_chronologically(&mut message_manager.channel_queues.get_mut(&channel).unwrap().queue, evicted.clone());
+        message_manager.on_deletion_finished(evicted.clone(), "test", Err(discord_error(404))).await;
+        assert_eq!(queued_ids(&message_manager), vec![2, 3]);
+        assert_eq!(message_manager.channel_queues[&channel].deleted, 0);
+
+        // Unlike a real failure, which is retried
+        message_manager.on_deletion_finished(evicted.clone(), "test", Err(discord_error(503))).await;
+        assert_eq!(queued_ids(&message_manager), vec![1, 2, 3]);
+        message_manager.on_deletion_finished(evicted, "test", Ok(())).await;
+        assert_eq!(message_manager.channel_queues[&channel].deleted, 1);
+    }
+
+    /// The previous walk of a channel keeping its oldest messages took every message through `insert_message`,
+    /// each displaced message being deleted on its own: compares it with the walk on a 10k messages channel,
+    /// and with the walk keeping the newest, which stops tracking at the limit and deletes the rest in bulk.
+    #[tokio::test]
+    async fn history_walk_keeping_oldest_batches_large_backlogs() {
+        const WALKED: u64 = 10_000;
+        let ctx = test_context();
+        let channel = ChannelId::from(CHANNEL);
+        let limit = QUEUE_LIMIT_MAX as usize;
+        // Recent enough to be bulk deleted
+        let posted_since = Utc::now().timestamp() - WALKED as i64;
+        let backlog: Vec<Message> = (1..=WALKED).rev().map(|id| {
+            let mut message = test_message(id, "backlog", false);
+            message.timestamp = Timestamp::from_unix_timestamp(posted_since + id as i64).unwrap();
+            message
+        }).collect();
+        let keeping_oldest = |limit| {
+            let (mut message_manager, jobs) = test_manager(limit, &[]);
+            message_manager.channel_queues.get_mut(&channel).unwrap().retention = RetentionDirection::KeepOldest;
+            (message_manager, jobs)
+        };
+        let drain = |jobs: &mut UnboundedReceiver<DeleteJob>| {
+            let (mut requests, mut deleted) = (0, 0);
+            while let Ok(job) = jobs.try_recv() {
+                requests += 1;
+                deleted += job.messages.len();
+            }
+            (requests, deleted)
+        };
+
+        let (mut message_manager, mut jobs) = keeping_oldest(limit);
+        for message in backlog.iter().cloned() {
+            message_manager.insert_message(&ctx, message, false).await;
+        }
+        let (requests_before, deleted_before) = drain(&mut jobs);
+        let kept_before = queued_ids(&message_manager);
+
+        let (mut message_manager, mut jobs) = keeping_oldest(limit);
+        let walk = history(backlog.iter().cloned().map(Ok).collect());
+        let walked = message_manager.walk_messages(&ctx, &channel, limit, MessageId::from(WALKED + 1), None, walk).await;
+        let (requests_after, deleted_after) = drain(&mut jobs);
+
+        let excess = WALKED as usize - limit;
+        assert_eq!(walked.ok(), Some((limit, excess)));
+        // The same messages are kept and deleted, in bulk rather than one by one
+        assert_eq!(queued_ids(&message_manager), kept_before);
+        assert_eq!(kept_before, (1..=limit as u64).collect::<Vec<_>>());
+        assert_eq!((deleted_before, deleted_after), (excess, excess));
+        assert_eq!(requests_before, excess);
+        assert_eq!(requests_after, excess.div_ceil(BULK_DELETE_MAX));
+
+        let (mut message_manager, mut jobs) = test_manager(limit, &[]);
+        let walk = history(backlog.into_iter().map(Ok).collect());
+        let walked = message_manager.walk_messages(&ctx, &channel, limit, MessageId::from(WALKED + 1), None, walk).await;
+        assert_eq!(walked.ok(), Some((limit, excess)));
+        assert_eq!(queued_ids(&message_manager), ((excess + 1) as u64..=WALKED).collect::<Vec<_>>());
+        assert_eq!(drain(&mut jobs), (excess.div_ceil(BULK_DELETE_MAX), excess));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn status_only_names_the_channels_of_the_guild() {
+        let ctx = test_context();
+        cache_channel(&ctx, CHANNEL + 1, GUILD + 1, "elsewhere");
+        let (mut message_manager, _jobs) = test_manager(5, &[]);
+        message_manager.channel_queues.insert(ChannelId::from(CHANNEL + 1), CappedQueue::new(5, 0, message_manager.deleter()));
+
+        let names = message_manager.resolve_channel_names(&ctx, Some(GuildId::from(GUILD))).await;
+        assert_eq!(names.keys().collect::<Vec<_>>(), vec![&ChannelId::from(CHANNEL)]);
+        let status = message_manager.get_status(&names);
+        assert!(status.contains("#general") && !status.contains("elsewhere"), "{}", status);
+        let embeds = message_manager.get_status_embeds(&names);
+        let fields = format!("{:?}", embeds[0].0);
+        assert!(fields.contains("#general") && !fields.contains("elsewhere"), "{}", fields);
+
+        // Outside of a guild, no channel is shown
+        let names = message_manager.resolve_channel_names(&ctx, None).await;
+        assert!(message_manager.get_status(&names).contains("There are no channels being autodeleted"));
+    }
+
+    #[test]
+    fn phantoms_found_by_an_audit_are_dropped_from_managed_queues() {
+        let channel = ChannelId::from(CHANNEL);
+        let messages: Vec<Message> = (1..=4).map(|id| test_message(id, "message", false)).collect();
+        let (mut message_manager, _jobs) = test_manager(5, &messages);
+        message_manager.auditing = true;
+
+        // Deleted while the audit ran, message 2 was already dropped; the other channel is no longer managed
+        message_manager.channel_queues.get_mut(&channel).unwrap().queue.retain(|message| message.id != MessageId::from(2));
+        message_manager.drop_phantoms(vec![
+            (channel, vec![MessageId::from(1), MessageId::from(2)]),
+            (ChannelId::from(CHANNEL + 1), vec![MessageId::from(10)]),
+        ]);
+        assert_eq!(queued_ids(&message_manager), vec![3, 4]);
+        assert_eq!(message_manager.phantoms_reclaimed, 1);
+        assert!(!message_manager.auditing);
+    }
+}
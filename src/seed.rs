@@ -0,0 +1,59 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+use serenity::model::prelude::ChannelId;
+
+use crate::{is_valid_limit, EPHEMERAL_LIMIT, QUEUE_LIMIT_MIN, QUEUE_LIMIT_MAX};
+
+/// Channel limits to apply on startup, read from the file at `CONFIG_FILE`:
+///
+/// ```json
+/// {
+///     "override": false,
+///     "channels": [
+///         { "channel_id": "123456789012345678", "limit": 100 }
+///     ]
+/// }
+/// ```
+///
+/// Channels already configured in the database are left alone unless `override` is set.
+#[derive(Clone, Default)]
+pub struct SeedConfig {
+    pub override_existing: bool,
+    pub limits: Vec<(ChannelId, usize)>,
+}
+
+impl SeedConfig {
+    /// Reads and validates the whole file, so a single bad entry rejects it rather than applying half of it
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|error| format!("Cannot read {}: {}", path.display(), error))?;
+        let root: Value = serde_json::from_str(&contents).map_err(|error| format!("{} is not valid JSON: {}", path.display(), error))?;
+
+        let override_existing = match root.get("override") {
+            None => false,
+            Some(value) => value.as_bool().ok_or("\"override\" must be true or false")?,
+        };
+        let entries = root.get("channels").and_then(Value::as_array).ok_or("Expected a \"channels\" list")?;
+
+        let mut limits = Vec::with_capacity(entries.len());
+        for (index, entry) in entries.iter().enumerate() {
+            // Snowflakes don't always survive JSON numbers, so strings are accepted too
+            let channel_id = match entry.get("channel_id") {
+                Some(Value::String(channel_id)) => channel_id.parse::<u64>().ok(),
+                Some(Value::Number(channel_id)) => channel_id.as_u64(),
+                _ => None,
+            }.ok_or(format!("Entry {} has no valid \"channel_id\"", index))?;
+            let limit = entry.get("limit").and_then(Value::as_i64).ok_or(format!("Entry {} has no valid \"limit\"", index))?;
+            if !is_valid_limit(limit) {
+                return Err(format!("Entry {} has limit {}, which should be {} or between {} and {}", index, limit, EPHEMERAL_LIMIT, QUEUE_LIMIT_MIN, QUEUE_LIMIT_MAX));
+            }
+            let channel = ChannelId::from(channel_id);
+            if limits.iter().any(|(seeded, _)| *seeded == channel) {
+                return Err(format!("Channel {} is listed more than once", channel));
+            }
+            limits.push((channel, limit as usize));
+        }
+        Ok(SeedConfig { override_existing, limits })
+    }
+}
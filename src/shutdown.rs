@@ -0,0 +1,28 @@
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::Duration;
+
+// How often a received signal is checked for, as a handler can't do more than record it
+const SIGNAL_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+// The last stop signal received, 0 until then
+static RECEIVED_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+extern "C" fn record_signal(signal: libc::c_int) {
+    RECEIVED_SIGNAL.store(signal, Ordering::SeqCst);
+}
+
+/// Resolves with the signal once the process is asked to stop, with Ctrl+C (SIGINT) or SIGTERM
+pub async fn stop_requested() -> i32 {
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+    unsafe {
+        libc::signal(libc::SIGINT, record_signal as extern "C" fn(libc::c_int) as libc::sighandler_t);
+        libc::signal(libc::SIGTERM, record_signal as extern "C" fn(libc::c_int) as libc::sighandler_t);
+    }
+    loop {
+        let signal = RECEIVED_SIGNAL.load(Ordering::SeqCst);
+        if signal != 0 {
+            return signal;
+        }
+        tokio::time::sleep(SIGNAL_POLL_INTERVAL).await;
+    }
+}
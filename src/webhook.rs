@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use log::{debug, warn};
+use ring::hmac;
+use serde_json::json;
+use serenity::model::prelude::{ChannelId, UserId};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+const SIGNATURE_HEADER: &str = "X-Autodeletto-Signature";
+
+/// Notifies an external endpoint whenever a channel's limit changes
+#[derive(Clone)]
+pub struct ConfigWebhook {
+    url: String,
+    key: Option<hmac::Key>,
+    client: reqwest::Client,
+}
+
+impl ConfigWebhook {
+    /// When a secret is given, every payload is signed with an HMAC-SHA256 of its body
+    pub fn new(url: String, secret: Option<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .expect("Couldn't build webhook client");
+        let key = secret.map(|secret| hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()));
+        ConfigWebhook { url, key, client }
+    }
+
+    /// Posts the change in the background, so a slow endpoint never holds up the caller.
+    /// A limit of `None` means the channel was (or becomes) unmanaged.
+    pub fn notify(&self, channel: &ChannelId, old_limit: Option<usize>, new_limit: Option<usize>, actor: Option<UserId>) {
+        let body = json!({
+            "channel_id": channel.to_string(),
+            "old_limit": old_limit,
+            "new_limit": new_limit,
+            "actor_id": actor.map(|actor| actor.to_string()),
+            "timestamp": Utc::now().to_rfc3339(),
+        }).to_string();
+
+        let mut request = self.client.post(&self.url).header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(key) = self.key.as_ref() {
+            let signature = hmac::sign(key, body.as_bytes());
+            request = request.header(SIGNATURE_HEADER, format!("sha256={}", hex::encode(signature.as_ref())));
+        }
+
+        let channel = *channel;
+        tokio::spawn(async move {
+            match request.body(body).send().await.and_then(|response| response.error_for_status()) {
+                Ok(response) => debug!("Config webhook for {} answered {}", channel, response.status()),
+                Err(error) => warn!("Config webhook for {} failed: {}", channel, error),
+            }
+        });
+    }
+}